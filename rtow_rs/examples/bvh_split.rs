@@ -0,0 +1,92 @@
+//! Compare the BVH split strategies on the RTOW cover scene.
+//!
+//! RTNW 3.9 splits at the median along a random axis, 3.10 along the longest axis of the
+//! node box; the SAH picks the cheapest split by surface area. Run with
+//! `cargo run --release --example bvh_split`.
+
+use std::time::Instant;
+
+use rtow_rs::bvh::{Bvh, SplitStrategy};
+use rtow_rs::camera::Camera;
+use rtow_rs::hittable::HitRecord;
+use rtow_rs::hittable_list::{HittableCollection, HittableList};
+use rtow_rs::interval::Interval;
+use rtow_rs::ray::Ray;
+use rtow_rs::rtweekend::{random_double_range, seeded_rng};
+use rtow_rs::scenes::final_scene;
+use rtow_rs::vec3::{Point3, Vec3};
+
+const RAY_COUNT: usize = 2_000_000;
+
+fn main() {
+    let strategies = [
+        ("3.9 random axis", SplitStrategy::RandomAxis),
+        ("3.10 longest axis", SplitStrategy::LongestAxis),
+        ("sah", SplitStrategy::Sah),
+    ];
+
+    // Primary-like rays from around the cover camera, the same for every strategy.
+    let mut rng = seeded_rng(1);
+    let rays: Vec<Ray> = (0..RAY_COUNT)
+        .map(|_| {
+            let origin = Point3::new(12.0, 2.0, 3.0) + Vec3::random(&mut rng, -1.0, 1.0);
+            let target = Point3::new(
+                random_double_range(&mut rng, -11.0, 11.0),
+                random_double_range(&mut rng, 0.0, 2.0),
+                random_double_range(&mut rng, -11.0, 11.0),
+            );
+            Ray::new(origin, target - origin)
+        })
+        .collect();
+
+    // Without any acceleration, to see what the hierarchy buys.
+    let list = final_scene(&mut seeded_rng(0));
+    let start = Instant::now();
+    let hits = trace(&list, &rays);
+    println!(
+        "{:<18} build {:>8} us  trace {:>6} ms  hits {}",
+        "linear",
+        0,
+        start.elapsed().as_millis(),
+        hits
+    );
+
+    for (name, strategy) in strategies {
+        let start = Instant::now();
+        let bvh = Bvh::new(final_scene(&mut seeded_rng(0)), strategy);
+        let build = start.elapsed();
+
+        let start = Instant::now();
+        let hits = trace(&bvh, &rays);
+        let trace_time = start.elapsed();
+
+        let mut camera = Camera::new();
+        camera.aspect_ratio = 16.0 / 9.0;
+        camera.image_width = 400;
+        camera.samples_per_pixel = 8;
+        camera.vfov = 20.0;
+        camera.lookfrom = Point3::new(12.0, 2.0, 3.0);
+        camera.lookat = Point3::new(0.0, 0.0, 0.0);
+        let start = Instant::now();
+        camera.render(&bvh, &HittableList::new());
+        let render = start.elapsed();
+
+        println!(
+            "{:<18} build {:>8} us  trace {:>6} ms  hits {}  render {:>6} ms  nodes {}  depth {}",
+            name,
+            build.as_micros(),
+            trace_time.as_millis(),
+            hits,
+            render.as_millis(),
+            bvh.node_count(),
+            bvh.depth()
+        );
+    }
+}
+
+fn trace<T: HittableCollection>(world: &T, rays: &[Ray]) -> usize {
+    let mut rec: HitRecord = Default::default();
+    rays.iter()
+        .filter(|r| world.hit(r, Interval::new(0.001, f64::INFINITY), &mut rec))
+        .count()
+}
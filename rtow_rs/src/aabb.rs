@@ -0,0 +1,220 @@
+use std::ops::Add;
+
+use crate::{
+    interval::Interval,
+    ray::Ray,
+    vec3::{Point3, Vec3},
+};
+
+/// Axis-aligned bounding box, stored as one interval per axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub x: Interval,
+    pub y: Interval,
+    pub z: Interval,
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Aabb::empty()
+    }
+}
+
+impl Aabb {
+    pub fn new(x: Interval, y: Interval, z: Interval) -> Self {
+        let mut bbox = Aabb { x, y, z };
+        bbox.pad_to_minimums();
+        bbox
+    }
+
+    /// Treat the two points a and b as extrema for the bounding box,
+    /// so we don't require a particular minimum/maximum coordinate order.
+    pub fn from_points(a: Point3, b: Point3) -> Self {
+        Aabb::new(
+            Interval::new(a.x().min(b.x()), a.x().max(b.x())),
+            Interval::new(a.y().min(b.y()), a.y().max(b.y())),
+            Interval::new(a.z().min(b.z()), a.z().max(b.z())),
+        )
+    }
+
+    /// Smallest box holding all the points.
+    pub fn enclosing_points(points: &[Point3]) -> Self {
+        let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for p in points {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(p[axis]);
+                max[axis] = max[axis].max(p[axis]);
+            }
+        }
+        Aabb::from_points(min, max)
+    }
+
+    pub fn surrounding(box0: &Aabb, box1: &Aabb) -> Self {
+        Aabb {
+            x: Interval::enclosing(&box0.x, &box1.x),
+            y: Interval::enclosing(&box0.y, &box1.y),
+            z: Interval::enclosing(&box0.z, &box1.z),
+        }
+    }
+
+    pub fn empty() -> Self {
+        Aabb {
+            x: Interval::empty(),
+            y: Interval::empty(),
+            z: Interval::empty(),
+        }
+    }
+
+    pub fn universe() -> Self {
+        Aabb {
+            x: Interval::universe(),
+            y: Interval::universe(),
+            z: Interval::universe(),
+        }
+    }
+
+    /// The eight corners of the box.
+    pub fn corners(&self) -> [Point3; 8] {
+        let mut corners = [Point3::default(); 8];
+        for (index, corner) in corners.iter_mut().enumerate() {
+            let pick = |interval: &Interval, bit: usize| {
+                if index & bit == 0 {
+                    interval.min
+                } else {
+                    interval.max
+                }
+            };
+            *corner = Point3::new(pick(&self.x, 1), pick(&self.y, 2), pick(&self.z, 4));
+        }
+        corners
+    }
+
+    pub fn axis_interval(&self, n: usize) -> &Interval {
+        match n {
+            1 => &self.y,
+            2 => &self.z,
+            _ => &self.x,
+        }
+    }
+
+    pub fn hit(&self, r: &Ray, mut ray_t: Interval) -> bool {
+        let ray_orig = r.origin();
+        let ray_dir = r.direction();
+
+        for axis in 0..3 {
+            let ax = self.axis_interval(axis);
+            let adinv = 1.0 / ray_dir[axis];
+
+            let t0 = (ax.min - ray_orig[axis]) * adinv;
+            let t1 = (ax.max - ray_orig[axis]) * adinv;
+
+            // the slab overlap of all three axes is the part of the ray inside the box
+            let (near, far) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+            if near > ray_t.min {
+                ray_t.min = near;
+            }
+            if far < ray_t.max {
+                ray_t.max = far;
+            }
+
+            if ray_t.max <= ray_t.min {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Returns the index of the longest axis of the bounding box.
+    pub fn longest_axis(&self) -> usize {
+        if self.x.size() > self.y.size() {
+            if self.x.size() > self.z.size() {
+                0
+            } else {
+                2
+            }
+        } else if self.y.size() > self.z.size() {
+            1
+        } else {
+            2
+        }
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        Point3::new(
+            0.5 * (self.x.min + self.x.max),
+            0.5 * (self.y.min + self.y.max),
+            0.5 * (self.z.min + self.z.max),
+        )
+    }
+
+    /// Surface area of the box, the relative hit probability used by the SAH.
+    pub fn surface_area(&self) -> f64 {
+        if self.x.size() < 0.0 || self.y.size() < 0.0 || self.z.size() < 0.0 {
+            return 0.0;
+        }
+        let extent = Vec3::new(self.x.size(), self.y.size(), self.z.size());
+        2.0 * (extent.x() * extent.y() + extent.y() * extent.z() + extent.z() * extent.x())
+    }
+
+    /// Adjust the AABB so that no side is narrower than some delta, padding if necessary.
+    fn pad_to_minimums(&mut self) {
+        let delta = 0.0001;
+        if self.x.size() < delta {
+            self.x = self.x.expand(delta);
+        }
+        if self.y.size() < delta {
+            self.y = self.y.expand(delta);
+        }
+        if self.z.size() < delta {
+            self.z = self.z.expand(delta);
+        }
+    }
+}
+
+impl Add<Vec3> for Aabb {
+    type Output = Aabb;
+
+    fn add(self, offset: Vec3) -> Aabb {
+        Aabb {
+            x: Interval::new(self.x.min + offset.x(), self.x.max + offset.x()),
+            y: Interval::new(self.y.min + offset.y(), self.y.max + offset.y()),
+            z: Interval::new(self.z.min + offset.z(), self.z.max + offset.z()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aabb_hit() {
+        let bbox = Aabb::from_points(Point3::new(1.0, 1.0, -3.0), Point3::new(-1.0, -1.0, -5.0));
+        let ray_t = Interval::new(0.001, f64::INFINITY);
+
+        let toward = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(bbox.hit(&toward, ray_t));
+
+        let away = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(!bbox.hit(&away, ray_t));
+
+        let beside = Ray::new(Point3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!bbox.hit(&beside, ray_t));
+
+        let short = Interval::new(0.001, 2.0);
+        assert!(
+            !bbox.hit(&toward, short),
+            "The box lies beyond the ray interval."
+        );
+    }
+
+    #[test]
+    fn test_aabb_surface_area() {
+        let bbox = Aabb::from_points(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 2.0, 3.0));
+        assert_eq!(bbox.surface_area(), 22.0);
+        assert_eq!(bbox.longest_axis(), 2);
+        assert_eq!(Aabb::empty().surface_area(), 0.0);
+    }
+}
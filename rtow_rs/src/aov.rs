@@ -0,0 +1,196 @@
+//! Arbitrary output variables: what the camera rays first hit, rendered along with the image
+//! for compositing and denoising.
+
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::rtweekend::mix64;
+use crate::vec3::Vec3;
+
+/// One pass of the arbitrary output variables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    /// Reflectance of the material, averaged over the pixel
+    Albedo,
+    /// Shading normal facing the camera, averaged over the pixel
+    Normal,
+    /// Distance to the first hit
+    Depth,
+    /// Which object of the world was hit
+    ObjectId,
+    /// Which material was hit
+    MaterialId,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 5] = [
+        Aov::Albedo,
+        Aov::Normal,
+        Aov::Depth,
+        Aov::ObjectId,
+        Aov::MaterialId,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Albedo => "albedo",
+            Aov::Normal => "normal",
+            Aov::Depth => "depth",
+            Aov::ObjectId => "object_id",
+            Aov::MaterialId => "material_id",
+        }
+    }
+}
+
+/// First hit of one camera sample.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct AovSample {
+    pub albedo: Color,
+    pub normal: Vec3,
+    /// Distance along the ray, infinite for a miss
+    pub depth: f64,
+    /// Index of the hit object in the world plus one, 0 for a miss
+    pub object: usize,
+    /// Id of the hit material, 0 for a miss
+    pub material: u32,
+}
+
+impl AovSample {
+    /// Sample of a ray seeing only the background.
+    pub fn miss() -> Self {
+        Self {
+            depth: f64::INFINITY,
+            ..Default::default()
+        }
+    }
+}
+
+/// The passes of a whole image, row by row from the top like a framebuffer.
+///
+/// The albedo and the normal are averaged over the samples of a pixel. The depth and the ids
+/// come from its first sample: a mix of two objects has no meaningful id. Ids start at 1, 0 is
+/// the background; materials keep the id their scene gave them, see `material::Numbered`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aovs {
+    width: u32,
+    height: u32,
+    albedo: Vec<Color>,
+    normal: Vec<Vec3>,
+    depth: Vec<f64>,
+    object_id: Vec<u32>,
+    material_id: Vec<u32>,
+}
+
+impl Aovs {
+    /// Gather the first hits of every pixel, `pixels` holds the averaged albedo and normal and
+    /// the first sample of each pixel.
+    pub(crate) fn new(width: u32, height: u32, pixels: &[(Color, Vec3, AovSample)]) -> Self {
+        Self {
+            width,
+            height,
+            albedo: pixels.iter().map(|(albedo, _, _)| *albedo).collect(),
+            normal: pixels.iter().map(|(_, normal, _)| *normal).collect(),
+            depth: pixels.iter().map(|(_, _, first)| first.depth).collect(),
+            object_id: pixels
+                .iter()
+                .map(|(_, _, first)| first.object as u32)
+                .collect(),
+            material_id: pixels.iter().map(|(_, _, first)| first.material).collect(),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn albedo(&self) -> &[Color] {
+        &self.albedo
+    }
+
+    pub fn normal(&self) -> &[Vec3] {
+        &self.normal
+    }
+
+    /// Distance to the first hit, infinite for the background.
+    pub fn depth(&self) -> &[f64] {
+        &self.depth
+    }
+
+    pub fn object_id(&self) -> &[u32] {
+        &self.object_id
+    }
+
+    pub fn material_id(&self) -> &[u32] {
+        &self.material_id
+    }
+
+    /// The values of a pass for compositing, as linear colors: the albedo, the normal in
+    /// [-1, 1], and the depth and the ids repeated in the three channels, the depth infinite
+    /// for the background.
+    pub fn data(&self, aov: Aov) -> Framebuffer {
+        let gray = |value: f64| Color::new(value, value, value);
+        let colors = match aov {
+            Aov::Albedo => self.albedo.clone(),
+            Aov::Normal => self.normal.clone(),
+            Aov::Depth => self.depth.iter().map(|&depth| gray(depth)).collect(),
+            Aov::ObjectId => self.object_id.iter().map(|&id| gray(id as f64)).collect(),
+            Aov::MaterialId => self.material_id.iter().map(|&id| gray(id as f64)).collect(),
+        };
+        self.to_framebuffer(colors)
+    }
+
+    /// Image of a pass for viewing: normals are mapped from [-1, 1] to [0, 1], depths are
+    /// divided by the farthest hit with the background black, and ids get random colors.
+    pub fn preview(&self, aov: Aov) -> Framebuffer {
+        let far = self
+            .depth
+            .iter()
+            .copied()
+            .filter(|depth| depth.is_finite())
+            .fold(0.0, f64::max);
+        let id_color = |id: u32| {
+            if id == 0 {
+                return Color::default();
+            }
+            let hash = mix64(id as u64);
+            let channel = |shift: u32| 0.2 + 0.8 * ((hash >> shift) & 0xff) as f64 / 255.0;
+            Color::new(channel(0), channel(8), channel(16))
+        };
+
+        let colors: Vec<Color> = match aov {
+            Aov::Albedo => self.albedo.clone(),
+            Aov::Normal => self
+                .normal
+                .iter()
+                .map(|n| 0.5 * (*n + Vec3::new(1.0, 1.0, 1.0)))
+                .collect(),
+            Aov::Depth => self
+                .depth
+                .iter()
+                .map(|&depth| {
+                    let d = if depth.is_finite() && far > 0.0 {
+                        depth / far
+                    } else {
+                        0.0
+                    };
+                    Color::new(d, d, d)
+                })
+                .collect(),
+            Aov::ObjectId => self.object_id.iter().map(|&id| id_color(id)).collect(),
+            Aov::MaterialId => self.material_id.iter().map(|&id| id_color(id)).collect(),
+        };
+        self.to_framebuffer(colors)
+    }
+
+    fn to_framebuffer(&self, colors: Vec<Color>) -> Framebuffer {
+        let mut framebuffer = Framebuffer::new(self.width, self.height);
+        for (index, color) in colors.into_iter().enumerate() {
+            let width = self.width as usize;
+            framebuffer.set((index % width) as u32, (index / width) as u32, color);
+        }
+        framebuffer
+    }
+}
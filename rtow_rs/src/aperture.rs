@@ -0,0 +1,152 @@
+//! Shapes of the lens opening, which give their outline to the out of focus highlights.
+//!
+//! Points are drawn on the lens in units of the defocus radius: the disk and the polygons fit
+//! in the unit circle, an image mask covers the square around it.
+
+use std::f64::consts::PI;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::rtweekend::{degress_to_radians, random_double, RtRng};
+use crate::vec3::Vec3;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Aperture {
+    /// Round opening of a wide open lens
+    #[default]
+    Disk,
+    /// Regular polygon left by the `blades` of a stopped down diaphragm, its first corner
+    /// `rotation` degrees counter-clockwise from the right
+    Polygon { blades: u32, rotation: f64 },
+    /// Opening drawn by the brightness of an image
+    Mask(Arc<ApertureMask>),
+}
+
+impl Aperture {
+    /// Random point of the opening in the lens plane, x to the right and y up.
+    pub fn sample(&self, rng: &mut RtRng) -> Vec3 {
+        match self {
+            Aperture::Disk => Vec3::random_in_unit_disk(rng),
+            Aperture::Polygon { blades, rotation } => sample_polygon(*blades, *rotation, rng),
+            Aperture::Mask(mask) => mask.sample(rng),
+        }
+    }
+}
+
+/// Uniform point of the regular polygon inscribed in the unit circle, picked in one of the
+/// equal triangles joining its center to its sides.
+fn sample_polygon(blades: u32, rotation: f64, rng: &mut RtRng) -> Vec3 {
+    let blades = blades.max(3);
+    let step = 2.0 * PI / blades as f64;
+    let side = ((random_double(rng) * blades as f64) as u32).min(blades - 1);
+    let start = degress_to_radians(rotation) + side as f64 * step;
+    let a = Vec3::new(start.cos(), start.sin(), 0.0);
+    let b = Vec3::new((start + step).cos(), (start + step).sin(), 0.0);
+
+    // uniform in the triangle (0, a, b)
+    let r = random_double(rng).sqrt();
+    let t = random_double(rng);
+    r * ((1.0 - t) * a + t * b)
+}
+
+/// Opening transmitting light in proportion to the brightness of the pixels of an image, for
+/// custom bokeh shapes.
+#[derive(Debug, PartialEq)]
+pub struct ApertureMask {
+    width: u32,
+    height: u32,
+    /// Running sum of the pixel weights, row by row from the top
+    cdf: Vec<f64>,
+}
+
+impl ApertureMask {
+    /// Load the mask from the luminance of an image, white lets all the light through.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, image::ImageError> {
+        let image = image::open(path)?.into_luma8();
+        let weights = image.pixels().map(|pixel| pixel.0[0] as f64).collect();
+        Self::from_weights(image.width(), image.height(), weights).ok_or_else(|| {
+            image::ImageError::Parameter(image::error::ParameterError::from_kind(
+                image::error::ParameterErrorKind::Generic("the mask is black".to_string()),
+            ))
+        })
+    }
+
+    /// Mask of `width` by `height` pixel weights, row by row from the top; None when no pixel
+    /// lets light through.
+    pub fn from_weights(width: u32, height: u32, weights: Vec<f64>) -> Option<Self> {
+        assert_eq!(weights.len(), (width * height) as usize);
+        let cdf: Vec<f64> = weights
+            .iter()
+            .scan(0.0, |sum, weight| {
+                *sum += weight.max(0.0);
+                Some(*sum)
+            })
+            .collect();
+        (cdf.last().copied().unwrap_or(0.0) > 0.0).then_some(Self { width, height, cdf })
+    }
+
+    /// Random point of the mask, the longer side of the image spans [-1, 1].
+    pub fn sample(&self, rng: &mut RtRng) -> Vec3 {
+        let total = self.cdf[self.cdf.len() - 1];
+        let target = random_double(rng) * total;
+        let index = self
+            .cdf
+            .partition_point(|&sum| sum <= target)
+            .min(self.cdf.len() - 1);
+
+        let x = (index as u32 % self.width) as f64 + random_double(rng);
+        let y = (index as u32 / self.width) as f64 + random_double(rng);
+        let scale = 2.0 / self.width.max(self.height) as f64;
+        Vec3::new(
+            (x - self.width as f64 / 2.0) * scale,
+            (self.height as f64 / 2.0 - y) * scale,
+            0.0,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtweekend::seeded_rng;
+
+    #[test]
+    fn test_polygon_samples_stay_inside() {
+        let mut rng = seeded_rng(5);
+        let aperture = Aperture::Polygon {
+            blades: 6,
+            rotation: 30.0,
+        };
+        // a hexagon with a corner straight up has its sides 0.5 * sqrt(3) away from the
+        // center, left and right
+        let apothem = 3f64.sqrt() / 2.0;
+        let mut widest: f64 = 0.0;
+        for _ in 0..10_000 {
+            let p = aperture.sample(&mut rng);
+            assert!(p.length() <= 1.0 + 1e-12);
+            assert!(p.x().abs() <= apothem + 1e-12, "{p:?}");
+            widest = widest.max(p.x().abs());
+        }
+        assert!(widest > 0.98 * apothem);
+    }
+
+    #[test]
+    fn test_mask_follows_brightness() {
+        let mut rng = seeded_rng(6);
+        // 2x1 mask, the right pixel lets three times as much light through
+        let mask = ApertureMask::from_weights(2, 1, vec![1.0, 3.0]).unwrap();
+        let n = 40_000;
+        let mut right = 0;
+        for _ in 0..n {
+            let p = mask.sample(&mut rng);
+            assert!((-1.0..=1.0).contains(&p.x()) && (-0.5..=0.5).contains(&p.y()));
+            if p.x() > 0.0 {
+                right += 1;
+            }
+        }
+        let fraction = right as f64 / n as f64;
+        assert!((fraction - 0.75).abs() < 0.01, "{fraction}");
+
+        assert!(ApertureMask::from_weights(1, 1, vec![0.0]).is_none());
+    }
+}
@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use crate::{color::Color, environment::EnvironmentMap, vec3::Vec3};
+
+/// Radiance seen by the rays leaving the scene.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Background {
+    /// Black, the scene is only lit by its emissive materials
+    None,
+    /// The same color in every direction
+    Solid(Color),
+    /// Vertical blend from `bottom` (straight down) to `top` (straight up)
+    Gradient { bottom: Color, top: Color },
+    /// Equirectangular map lighting the scene, drawn from by the light sampling
+    Environment(Arc<EnvironmentMap>),
+}
+
+impl Default for Background {
+    /// The sky of RTOW, white at the bottom to light blue at the top.
+    fn default() -> Self {
+        Self::Gradient {
+            bottom: Color::new(1.0, 1.0, 1.0),
+            top: Color::new(0.5, 0.7, 1.0),
+        }
+    }
+}
+
+impl Background {
+    pub fn value(&self, direction: &Vec3) -> Color {
+        match self {
+            Self::None => Color::new(0.0, 0.0, 0.0),
+            Self::Solid(color) => *color,
+            Self::Gradient { bottom, top } => {
+                let a = 0.5 * (direction.unit().y() + 1.0);
+                (1.0 - a) * *bottom + a * *top
+            }
+            Self::Environment(map) => map.value(direction),
+        }
+    }
+
+    /// Map of the background sampled along with the lights, when it has one with some light.
+    pub fn environment(&self) -> Option<&EnvironmentMap> {
+        match self {
+            Self::Environment(map) if !map.is_black() => Some(map),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_background_value() {
+        let up = Vec3::new(0.0, 3.0, 0.0);
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        let sky = Background::default();
+
+        assert_eq!(sky.value(&up), Color::new(0.5, 0.7, 1.0));
+        assert_eq!(sky.value(&down), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(Background::None.value(&up), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(
+            Background::Solid(Color::new(0.7, 0.8, 1.0)).value(&down),
+            Color::new(0.7, 0.8, 1.0)
+        );
+    }
+}
@@ -0,0 +1,350 @@
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    hittable_list::HittableCollection,
+    interval::Interval,
+    ray::Ray,
+    rtweekend::{random_int, seeded_rng, RtRng},
+};
+
+/// Cost of visiting an interior node relative to one object intersection, used by the SAH.
+const TRAVERSAL_COST: f64 = 0.125;
+/// Leaves built by the SAH never hold more objects than this.
+const MAX_LEAF_SIZE: usize = 4;
+
+/// How a BVH node divides its objects between the two children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SplitStrategy {
+    /// Sort along a random axis and split at the median, as RTNW section 3.9 does.
+    RandomAxis,
+    /// Sort along the longest axis of the node box and split at the median, as RTNW section 3.10 does.
+    LongestAxis,
+    /// Surface area heuristic, choose the axis and split position with the lowest expected cost.
+    #[default]
+    Sah,
+}
+
+#[derive(Debug)]
+enum NodeKind {
+    /// Objects [start, start + count) of the reordered object list
+    Leaf {
+        start: usize,
+        count: usize,
+    },
+    Interior {
+        left: usize,
+        right: usize,
+    },
+}
+
+#[derive(Debug)]
+struct BvhNode {
+    bbox: Aabb,
+    kind: NodeKind,
+}
+
+/// Bounding volume hierarchy, the nodes live in a flat array and the root is node 0.
+pub struct Bvh {
+    objects: Vec<Box<dyn Hittable>>,
+    /// Index of each object in the list the BVH was built from
+    ids: Vec<usize>,
+    nodes: Vec<BvhNode>,
+}
+
+impl Bvh {
+    pub fn new(objects: Vec<Box<dyn Hittable>>, strategy: SplitStrategy) -> Self {
+        let boxes: Vec<Aabb> = objects.iter().map(|object| object.bounding_box()).collect();
+        let mut order: Vec<usize> = (0..objects.len()).collect();
+
+        let mut builder = BvhBuilder {
+            boxes: &boxes,
+            nodes: Vec::new(),
+            strategy,
+            // a fixed seed keeps the random axis strategy reproducible
+            rng: seeded_rng(0),
+        };
+        if !order.is_empty() {
+            builder.build(&mut order, 0);
+        }
+        let nodes = builder.nodes;
+
+        // Move the objects into the leaf order so that each leaf references a contiguous span.
+        let mut slots: Vec<Option<Box<dyn Hittable>>> = objects.into_iter().map(Some).collect();
+        let objects = order
+            .iter()
+            .map(|&index| slots[index].take().unwrap())
+            .collect();
+
+        Self {
+            objects,
+            ids: order,
+            nodes,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Number of nodes on the longest path from the root to a leaf.
+    pub fn depth(&self) -> usize {
+        fn node_depth(nodes: &[BvhNode], index: usize) -> usize {
+            match nodes[index].kind {
+                NodeKind::Leaf { .. } => 1,
+                NodeKind::Interior { left, right } => {
+                    1 + node_depth(nodes, left).max(node_depth(nodes, right))
+                }
+            }
+        }
+
+        if self.nodes.is_empty() {
+            0
+        } else {
+            node_depth(&self.nodes, 0)
+        }
+    }
+}
+
+impl Hittable for Bvh {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        let mut hit_anything = false;
+        let mut closest_so_far = ray_t.max;
+        let mut temp_rec: HitRecord = Default::default();
+
+        let mut stack: Vec<usize> = Vec::with_capacity(32);
+        stack.push(0);
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            // a ray can only hit one object first, boxes behind the closest hit are skipped
+            if !node.bbox.hit(r, Interval::new(ray_t.min, closest_so_far)) {
+                continue;
+            }
+
+            match node.kind {
+                NodeKind::Leaf { start, count } => {
+                    for slot in start..start + count {
+                        let interval = Interval::new(ray_t.min, closest_so_far);
+                        if self.objects[slot].hit(r, interval, &mut temp_rec) {
+                            hit_anything = true;
+                            closest_so_far = temp_rec.t;
+                            *rec = temp_rec.clone();
+                            rec.object_id = self.ids[slot];
+                        }
+                    }
+                }
+                NodeKind::Interior { left, right } => {
+                    stack.push(right);
+                    stack.push(left);
+                }
+            }
+        }
+
+        hit_anything
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.nodes
+            .first()
+            .map_or_else(Aabb::empty, |root| root.bbox)
+    }
+}
+
+impl HittableCollection for Bvh {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        Hittable::hit(self, r, ray_t, rec)
+    }
+}
+
+struct BvhBuilder<'a> {
+    boxes: &'a [Aabb],
+    nodes: Vec<BvhNode>,
+    strategy: SplitStrategy,
+    rng: RtRng,
+}
+
+impl BvhBuilder<'_> {
+    /// Build the subtree over `order`, which starts at `start` in the final object list.
+    fn build(&mut self, order: &mut [usize], start: usize) -> usize {
+        let bbox = order.iter().fold(Aabb::empty(), |bbox, &index| {
+            Aabb::surrounding(&bbox, &self.boxes[index])
+        });
+
+        let node_index = self.nodes.len();
+        self.nodes.push(BvhNode {
+            bbox,
+            kind: NodeKind::Leaf {
+                start,
+                count: order.len(),
+            },
+        });
+
+        let Some(mid) = self.split(order, &bbox) else {
+            return node_index;
+        };
+
+        let (left_order, right_order) = order.split_at_mut(mid);
+        let left = self.build(left_order, start);
+        let right = self.build(right_order, start + mid);
+        self.nodes[node_index].kind = NodeKind::Interior { left, right };
+
+        node_index
+    }
+
+    /// Reorder `order` and return the split position, or None to keep the node a leaf.
+    fn split(&mut self, order: &mut [usize], bbox: &Aabb) -> Option<usize> {
+        if order.len() <= 1 {
+            return None;
+        }
+
+        match self.strategy {
+            SplitStrategy::RandomAxis => {
+                let axis = random_int(&mut self.rng, 0, 2) as usize;
+                self.sort_by_box_min(order, axis);
+                Some(order.len() / 2)
+            }
+            SplitStrategy::LongestAxis => {
+                self.sort_by_box_min(order, bbox.longest_axis());
+                Some(order.len() / 2)
+            }
+            SplitStrategy::Sah => self.sah_split(order, bbox),
+        }
+    }
+
+    fn sort_by_box_min(&self, order: &mut [usize], axis: usize) {
+        order.sort_by(|&a, &b| {
+            let a_min = self.boxes[a].axis_interval(axis).min;
+            let b_min = self.boxes[b].axis_interval(axis).min;
+            a_min.total_cmp(&b_min)
+        });
+    }
+
+    fn sort_by_centroid(&self, order: &mut [usize], axis: usize) {
+        order.sort_by(|&a, &b| {
+            let a_center = self.boxes[a].centroid()[axis];
+            let b_center = self.boxes[b].centroid()[axis];
+            a_center.total_cmp(&b_center)
+        });
+    }
+
+    fn sah_split(&mut self, order: &mut [usize], bbox: &Aabb) -> Option<usize> {
+        let count = order.len();
+        let parent_area = bbox.surface_area();
+
+        let mut best: Option<(f64, usize, usize)> = None;
+        let mut right_areas = vec![0.0; count];
+
+        for axis in 0..3 {
+            self.sort_by_centroid(order, axis);
+
+            // right_areas[i] is the area of the box around objects [i, count)
+            let mut right_box = Aabb::empty();
+            for i in (1..count).rev() {
+                right_box = Aabb::surrounding(&right_box, &self.boxes[order[i]]);
+                right_areas[i] = right_box.surface_area();
+            }
+
+            let mut left_box = Aabb::empty();
+            for i in 1..count {
+                left_box = Aabb::surrounding(&left_box, &self.boxes[order[i - 1]]);
+                let cost = TRAVERSAL_COST
+                    + (left_box.surface_area() * i as f64 + right_areas[i] * (count - i) as f64)
+                        / parent_area;
+
+                if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                    best = Some((cost, axis, i));
+                }
+            }
+        }
+
+        let (cost, axis, mid) = best?;
+        if count <= MAX_LEAF_SIZE && count as f64 <= cost {
+            return None;
+        }
+
+        self.sort_by_centroid(order, axis);
+        Some(mid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::color::Color;
+    use crate::material::Lambertian;
+    use crate::rtweekend::random_double_range;
+    use crate::sphere::Sphere;
+    use crate::vec3::{Point3, Vec3};
+
+    fn random_spheres(count: usize) -> Vec<Box<dyn Hittable>> {
+        let mut rng = seeded_rng(3);
+        let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+
+        (0..count)
+            .map(|_| {
+                let center = Vec3::random(&mut rng, -10.0, 10.0);
+                let radius = random_double_range(&mut rng, 0.1, 1.0);
+                Box::new(Sphere::new(center, radius, mat.clone())) as Box<dyn Hittable>
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_bvh_matches_linear_search() {
+        let strategies = [
+            SplitStrategy::RandomAxis,
+            SplitStrategy::LongestAxis,
+            SplitStrategy::Sah,
+        ];
+        let list = random_spheres(200);
+        let mut rng = seeded_rng(5);
+
+        for strategy in strategies {
+            let bvh = Bvh::new(random_spheres(200), strategy);
+            assert_eq!(bvh.len(), 200);
+
+            for _ in 0..500 {
+                let origin = Vec3::random(&mut rng, -15.0, 15.0);
+                let target = Point3::random(&mut rng, -5.0, 5.0);
+                let r = Ray::new(origin, target - origin);
+                let ray_t = Interval::new(0.001, f64::INFINITY);
+
+                let mut expected: HitRecord = Default::default();
+                let mut actual: HitRecord = Default::default();
+                let hit_list = HittableCollection::hit(&list, &r, ray_t, &mut expected);
+                let hit_bvh = HittableCollection::hit(&bvh, &r, ray_t, &mut actual);
+
+                assert_eq!(hit_list, hit_bvh, "{strategy:?} disagrees on hit");
+                if hit_list {
+                    assert_eq!(expected.t, actual.t, "{strategy:?} found another hit");
+                    assert_eq!(expected.object_id, actual.object_id);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bvh_empty() {
+        let bvh = Bvh::new(Vec::new(), SplitStrategy::Sah);
+        let r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut rec: HitRecord = Default::default();
+
+        assert!(bvh.is_empty());
+        assert_eq!(bvh.depth(), 0);
+        assert!(!Hittable::hit(&bvh, &r, Interval::universe(), &mut rec));
+    }
+}
@@ -1,31 +1,127 @@
-use crate::color::{write_color, Color};
+use crate::aov::{AovSample, Aovs};
+use crate::aperture::Aperture;
+use crate::background::Background;
+use crate::color::{luminance, Color};
+use crate::framebuffer::Framebuffer;
 use crate::hittable::HitRecord;
-use crate::hittable_list::HittableCollection;
+use crate::hittable_list::{HittableCollection, HittableList};
+use crate::integrator::{Integrator, PathTracer, SceneView};
 use crate::interval::Interval;
+use crate::matrix::Mat4;
 use crate::ray::Ray;
-use crate::rtweekend::degress_to_radians;
+use crate::rtweekend::{
+    degress_to_radians, mix64, pixel_rng, pixel_seed, random_double_range, RtRng,
+};
+use crate::spectrum::Wavelength;
+use crate::stats::RunningStats;
 use crate::vec3::{Point3, Vec3};
 
-use std::fs::File;
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use std::io::Write;
+use rayon::prelude::*;
+
+/// Edge length in pixels of the square tiles handed to the worker pool.
+const TILE_SIZE: u32 = 16;
+
+/// Luminance below which the adaptive noise is measured in absolute rather than relative terms,
+/// so that nearly black pixels do not sample forever.
+const DARK_LUMINANCE: f64 = 0.01;
+
+/// Fractional part of the golden ratio, steps between the hero wavelengths of the samples.
+const GOLDEN_STEP: f64 = 0.618_033_988_749_895;
+
+/// Salt of the pixel seed giving the offset of its hero wavelengths.
+const WAVELENGTH_STREAM: u64 = 0x5745_4156_454c_454e;
+
+/// Color of a pixel rendered by a tile, with its count of samples and its first hits.
+struct Pixel {
+    color: Color,
+    samples: u32,
+    /// Averaged albedo and normal and the first sample, when rendering the AOVs
+    aov: Option<(Color, Vec3, AovSample)>,
+}
+
+/// How the camera rays leave the camera, through the pixels of the image.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Projection {
+    /// Pinhole or thin lens of vertical field of view `vfov`, the only one with a depth of
+    /// field
+    #[default]
+    Perspective,
+    /// Parallel rays along the view direction, from an image `height` world units tall
+    Orthographic { height: f64 },
+    /// Equidistant fisheye, the angle off the view direction grows linearly away from the
+    /// center of the image; the circle inscribed in the image spans `fov` degrees and the
+    /// pixels outside it stay black
+    Fisheye { fov: f64 },
+    /// Full 360 by 180 degree panorama, the longitude across the image and the latitude down,
+    /// with the view direction in the middle. Looking along +x with vup +y gives the layout of
+    /// the HDR skies of the wgpu tutorial.
+    Equirectangular,
+}
+
+/// Pixel rectangle [x0, x1) x [y0, y1) of the image rendered by one worker.
+#[derive(Debug, Clone, Copy)]
+struct Tile {
+    x0: u32,
+    y0: u32,
+    x1: u32,
+    y1: u32,
+}
 
 pub struct Camera {
     pub aspect_ratio: f64,
     pub image_width: u32,
+    /// Count of samples of every pixel, or of every batch when sampling adaptively
     pub samples_per_pixel: u32,
+    /// Relative standard error of the pixel luminance at which a pixel stops sampling,
+    /// 0 takes exactly `samples_per_pixel` samples everywhere
+    pub noise_threshold: f64,
+    /// Most samples an adaptive pixel may take
+    pub max_samples_per_pixel: u32,
     pub max_depth: i32,
+    pub projection: Projection,
     pub vfov: f64,
     pub lookfrom: Point3,
     pub lookat: Point3,
     pub vup: Vec3,
+    /// Degrees the camera turns around its view direction, counter-clockwise as seen from
+    /// behind it
+    pub roll: f64,
+    /// Camera to world rotation whose columns are the right, up and backward axes of the
+    /// camera, in place of `lookat` and `vup`; `lookfrom` stays the position
+    pub orientation: Option<Mat4>,
     pub defocus_angle: f64,
     pub focus_dist: f64,
+    /// Shape of the lens opening, drawn at the scale of `defocus_angle`
+    pub aperture: Aperture,
+    /// Offset of the image on the sensor, in fractions of its width and height to the right
+    /// and up; frames off axis without turning the camera, so verticals stay parallel
+    pub shift: [f64; 2],
+    /// Degrees the plane in focus turns counter-clockwise around the right and the up axes of
+    /// the camera, positive angles bring its top and its left closer
+    pub tilt: [f64; 2],
+    /// Radiance of the rays escaping the scene
+    pub background: Background,
+    /// Interval of time during which the shutter is open, the rays are spread over it
+    pub shutter_open: f64,
+    pub shutter_close: f64,
+    /// Seed of the render, the same seed always gives the same image
+    pub seed: u64,
+    /// Number of worker threads, 0 uses one thread per core
+    pub threads: usize,
+    /// How the color of every sample is computed
+    pub integrator: Box<dyn Integrator>,
+    /// Also render the first hit passes, see `aovs`
+    pub render_aovs: bool,
 
     /// Render image height
     image_height: u32,
-    /// Color scale factor for a sum of pixel samples
-    pixel_sample_scale: f64,
+    /// Samples taken by every pixel of the last render, row by row
+    sample_counts: Vec<u32>,
+    /// First hit passes of the last render
+    aovs: Option<Aovs>,
     center: Point3,
     // location of pixel, u->right, v->below
     pixel00_loc: Point3,
@@ -38,6 +134,14 @@ pub struct Camera {
     // Defocus disk raidus
     defocus_disk_u: Vec3,
     defocus_disk_v: Vec3,
+    /// Normal of the plane in focus when tilted, it goes through the middle of the viewport
+    focus_normal: Option<Vec3>,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Camera {
@@ -46,16 +150,32 @@ impl Camera {
             aspect_ratio: 1.0,
             image_width: 100,
             samples_per_pixel: 10,
+            noise_threshold: 0.0,
+            max_samples_per_pixel: 1024,
             max_depth: 10,
+            projection: Projection::Perspective,
             vfov: 90.0,
             lookfrom: Point3::new(0.0, 0.0, 0.0),
             lookat: Point3::new(0.0, 0.0, -1.0),
             vup: Vec3::new(0.0, 1.0, 0.0),
+            roll: 0.0,
+            orientation: None,
             defocus_angle: 0.0,
             focus_dist: 10.0,
+            aperture: Aperture::Disk,
+            shift: [0.0, 0.0],
+            tilt: [0.0, 0.0],
+            background: Background::default(),
+            shutter_open: 0.0,
+            shutter_close: 1.0,
+            seed: 0,
+            threads: 0,
+            integrator: Box::new(PathTracer::new()),
+            render_aovs: false,
 
             image_height: 0,
-            pixel_sample_scale: 0.0,
+            sample_counts: Vec::new(),
+            aovs: None,
             center: Point3::new(0.0, 0.0, 0.0),
             pixel00_loc: Point3::new(0.0, 0.0, 0.0),
             pixel_delta_u: Vec3::new(0.0, 0.0, 0.0),
@@ -66,6 +186,7 @@ impl Camera {
             w: Vec3::new(0.0, 0.0, 0.0),
             defocus_disk_u: Vec3::new(0.0, 0.0, 0.0),
             defocus_disk_v: Vec3::new(0.0, 0.0, 0.0),
+            focus_normal: None,
         }
     }
 
@@ -77,20 +198,38 @@ impl Camera {
             self.image_height = image_height;
         }
 
-        self.pixel_sample_scale = 1.0 / (self.samples_per_pixel as f64);
         self.center = self.lookfrom;
 
         // Determine viewport dimensions.
         let theta = degress_to_radians(self.vfov);
         let h = (theta / 2.0).tan();
-        let viewport_height = 2.0 * h * self.focus_dist;
+        // orthographic pixels lie on the plane of the camera center rather than in focus
+        let (viewport_height, viewport_dist) = match self.projection {
+            Projection::Orthographic { height } => (height, 0.0),
+            _ => (2.0 * h * self.focus_dist, self.focus_dist),
+        };
         let viewport_width = viewport_height * (self.image_width as f64 / self.image_height as f64);
 
-        // Calculate the u,v,w unit basis vectors for the camera coordinate frame;
-        // since we only have the exact OP vector, we cannot describe the rotation around OP(roll).
-        self.w = (self.lookfrom - self.lookat).unit();
-        self.u = self.vup.cross(self.w).unit();
+        // Calculate the u,v,w unit basis vectors for the camera coordinate frame, from the
+        // explicit orientation or from the view direction, then roll them around w.
+        if let Some(orientation) = &self.orientation {
+            // straighten the frame, in case the matrix was rounded
+            self.w = orientation
+                .transform_vector(&Vec3::new(0.0, 0.0, 1.0))
+                .unit();
+            let up = orientation.transform_vector(&Vec3::new(0.0, 1.0, 0.0));
+            self.u = up.cross(self.w).unit();
+        } else {
+            self.w = (self.lookfrom - self.lookat).unit();
+            self.u = self.vup.cross(self.w).unit();
+        }
         self.v = self.w.cross(self.u);
+        if self.roll != 0.0 {
+            let (sin, cos) = degress_to_radians(self.roll).sin_cos();
+            let (u, v) = (self.u, self.v);
+            self.u = cos * u + sin * v;
+            self.v = cos * v - sin * u;
+        }
 
         // Calculate the vectors across the horizontal and down the vertical
         // Vector across viewport horizontal edge
@@ -102,100 +241,647 @@ impl Camera {
         self.pixel_delta_u = viewport_u / self.image_width as f64;
         self.pixel_delta_v = viewport_v / self.image_height as f64;
 
-        // Calculate the location of the upper left pixel.
-        let viewport_upper_left =
-            self.center - (self.focus_dist * self.w) - viewport_u / 2.0 - viewport_v / 2.0;
+        // Calculate the location of the upper left pixel, shifted across the viewport plane.
+        let viewport_middle = self.center - (viewport_dist * self.w);
+        let shift = self.shift[0] * viewport_u - self.shift[1] * viewport_v;
+        let viewport_upper_left = viewport_middle + shift - viewport_u / 2.0 - viewport_v / 2.0;
         self.pixel00_loc = viewport_upper_left + 0.5 * (self.pixel_delta_u + self.pixel_delta_v);
 
         let defocus_radius = self.focus_dist * (degress_to_radians(self.defocus_angle / 2.0)).tan();
         self.defocus_disk_u = self.u * defocus_radius;
         self.defocus_disk_v = self.v * defocus_radius;
+
+        // the plane in focus is the viewport plane, turned around the axes of the image
+        self.focus_normal = (self.tilt != [0.0, 0.0]).then(|| {
+            let turn = Mat4::rotation(self.v, self.tilt[1]) * Mat4::rotation(self.u, self.tilt[0]);
+            turn.transform_vector(&self.w)
+        });
+    }
+
+    pub fn sample_square(rng: &mut RtRng) -> Vec3 {
+        // Returns the vector to a random point in the [-0.5, -0.5]-[+0.5, +0.5] unit square.
+        Vec3::random_random(rng) - Vec3::new(0.5, 0.5, 0.0)
+    }
+
+    /// Random ray through the pixel in column i and row j, None when the sample falls outside
+    /// the image circle of a fisheye.
+    pub fn get_ray(&self, i: i32, j: i32, rng: &mut RtRng) -> Option<Ray> {
+        let offset = Camera::sample_square(rng);
+        let pixel_sample = self.pixel00_loc
+            + ((i as f64 + offset.x()) * self.pixel_delta_u)
+            + ((j as f64 + offset.y()) * self.pixel_delta_v);
+        // position in pixels from the upper left corner of the image
+        let x = i as f64 + 0.5 + offset.x();
+        let y = j as f64 + 0.5 + offset.y();
+        let width = self.image_width as f64;
+        let height = self.image_height as f64;
+
+        let (ray_origin, ray_direction) = match self.projection {
+            Projection::Perspective => {
+                let ray_origin: Vec3 = if self.defocus_angle <= 0.0 {
+                    self.center
+                } else {
+                    self.defocus_disk_sample(rng)
+                };
+                (ray_origin, self.focus_point(pixel_sample) - ray_origin)
+            }
+            Projection::Orthographic { .. } => (pixel_sample, -self.w),
+            Projection::Fisheye { fov } => {
+                let dx = x - width / 2.0;
+                let dy = height / 2.0 - y;
+                let r = (dx * dx + dy * dy).sqrt() / (0.5 * width.min(height));
+                if r > 1.0 {
+                    return None;
+                }
+                let theta = r * degress_to_radians(fov) / 2.0;
+                let phi = dy.atan2(dx);
+                let direction =
+                    theta.sin() * (phi.cos() * self.u + phi.sin() * self.v) - theta.cos() * self.w;
+                (self.center, direction)
+            }
+            Projection::Equirectangular => {
+                let longitude = 2.0 * PI * (x / width - 0.5);
+                let latitude = PI * (0.5 - y / height);
+                let direction = latitude.cos()
+                    * (longitude.cos() * -self.w + longitude.sin() * self.u)
+                    + latitude.sin() * self.v;
+                (self.center, direction)
+            }
+        };
+
+        let ray_time = if self.shutter_close > self.shutter_open {
+            random_double_range(rng, self.shutter_open, self.shutter_close)
+        } else {
+            self.shutter_open
+        };
+
+        Some(Ray::with_time(ray_origin, ray_direction, ray_time))
     }
 
-    fn ray_color<T: HittableCollection>(r: &mut Ray, depth: i32, world: &T) -> Color {
-        if depth <= 0 {
-            return Color::new(0.0, 0.0, 0.0);
+    /// Render the world into a framebuffer of linear colors, tiles are shared out to the worker pool.
+    /// `lights` are copies of the emissive objects of the world that are sampled directly, it
+    /// may be empty.
+    ///
+    /// Each pixel owns a random generator seeded from `seed` and its position,
+    /// so the result does not depend on the number of threads or on the tile scheduling.
+    ///
+    /// With a `noise_threshold`, pixels take batches of `samples_per_pixel` samples, two at
+    /// least, until the standard error of their luminance falls under the threshold, relative to their mean, or
+    /// until `max_samples_per_pixel`; `sample_counts` then tells how many each one took.
+    pub fn render<T: HittableCollection>(
+        &mut self,
+        world: &T,
+        lights: &HittableList,
+    ) -> Framebuffer {
+        self.initialize();
+
+        let width = self.image_width;
+        let height = self.image_height;
+
+        let mut tiles = Vec::new();
+        for y0 in (0..height).step_by(TILE_SIZE as usize) {
+            for x0 in (0..width).step_by(TILE_SIZE as usize) {
+                tiles.push(Tile {
+                    x0,
+                    y0,
+                    x1: (x0 + TILE_SIZE).min(width),
+                    y1: (y0 + TILE_SIZE).min(height),
+                });
+            }
         }
 
-        let mut rec: HitRecord = Default::default();
-        let interval = Interval::new(0.001, std::f64::INFINITY);
-
-        if world.hit(r, interval, &mut rec) {
-            let mut scattered: Ray = Default::default();
-            let mut attenuation: Color = Default::default();
-            if let Some(ref mat) = rec.mat {
-                if mat
-                    .clone()
-                    .scatter(r, &mut rec, &mut attenuation, &mut scattered)
-                {
-                    return attenuation * Self::ray_color(&mut scattered, depth - 1, world);
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.threads)
+            .build()
+            .unwrap();
+
+        let remaining = AtomicUsize::new(tiles.len());
+        let rendered: Vec<Vec<Pixel>> = pool.install(|| {
+            tiles
+                .par_iter()
+                .map(|tile| {
+                    let colors = self.render_tile(tile, world, lights);
+                    let left = remaining.fetch_sub(1, Ordering::Relaxed) - 1;
+                    eprint!("\rTiles remaining: {left}    ");
+                    colors
+                })
+                .collect()
+        });
+
+        let mut framebuffer = Framebuffer::new(width, height);
+        self.sample_counts = vec![0; (width * height) as usize];
+        let mut first_hits = vec![Default::default(); (width * height) as usize];
+        for (tile, pixels) in tiles.iter().zip(rendered) {
+            let tile_width = (tile.x1 - tile.x0) as usize;
+            for (row, line) in pixels.chunks(tile_width).enumerate() {
+                let j = tile.y0 + row as u32;
+                for (column, pixel) in line.iter().enumerate() {
+                    let i = tile.x0 + column as u32;
+                    framebuffer.set(i, j, pixel.color);
+                    self.sample_counts[(j * width + i) as usize] = pixel.samples;
+                    if let Some(aov) = pixel.aov {
+                        first_hits[(j * width + i) as usize] = aov;
+                    }
                 }
             }
-            return Color::new(0.0, 0.0, 0.0);
         }
+        self.aovs = self
+            .render_aovs
+            .then(|| Aovs::new(width, height, &first_hits));
 
-        let unit_direction = r.direction().unit();
-        let a = 0.5 * (unit_direction.y() + 1.0);
-        (1.0 - a) * Color::new(1.0, 1.0, 1.0) + a * Color::new(0.5, 0.7, 1.0)
+        eprintln!("\rDone.                  ");
+        framebuffer
     }
 
-    pub fn sample_square() -> Vec3 {
-        // Returns the vector to a random point in the [-0.5, -0.5]-[+0.5, +0.5] unit square.
-        Vec3::random_random() - Vec3::new(0.5, 0.5, 0.0)
+    fn render_tile<T: HittableCollection>(
+        &self,
+        tile: &Tile,
+        world: &T,
+        lights: &HittableList,
+    ) -> Vec<Pixel> {
+        let mut pixels = Vec::with_capacity(((tile.x1 - tile.x0) * (tile.y1 - tile.y0)) as usize);
+        let batch = self.samples_per_pixel.max(1);
+        let max_samples = if self.noise_threshold > 0.0 {
+            self.max_samples_per_pixel.max(batch)
+        } else {
+            batch
+        };
+        let scene = SceneView {
+            world,
+            lights,
+            background: &self.background,
+            max_depth: self.max_depth,
+        };
+
+        for j in tile.y0..tile.y1 {
+            for i in tile.x0..tile.x1 {
+                let mut rng = pixel_rng(self.seed, i, j);
+
+                let mut pixel_color = Color::new(0.0, 0.0, 0.0);
+                let mut stats = RunningStats::new();
+                let mut albedo = Color::new(0.0, 0.0, 0.0);
+                let mut normal = Vec3::new(0.0, 0.0, 0.0);
+                let mut first = None;
+                // hero wavelengths spread evenly over the samples, whatever their count; the
+                // offset is hashed rather than drawn, so that the pixel generator and the
+                // renders of the scenes without dispersion stay the same
+                let bits = mix64(pixel_seed(self.seed, i, j) ^ WAVELENGTH_STREAM);
+                let mut hero = (bits >> 11) as f64 / (1u64 << 53) as f64;
+                while stats.count() < max_samples {
+                    for _ in 0..batch.min(max_samples - stats.count()) {
+                        let ray = self
+                            .get_ray(i as i32, j as i32, &mut rng)
+                            .map(|r| r.with_wavelength(Wavelength::hero(hero)));
+                        hero = (hero + GOLDEN_STEP).fract();
+                        let Some(r) = ray else {
+                            if self.render_aovs {
+                                first.get_or_insert(AovSample::miss());
+                            }
+                            stats.push(0.0);
+                            continue;
+                        };
+                        if self.render_aovs {
+                            let hit = self.first_hit(&r, world);
+                            albedo += hit.albedo;
+                            normal += hit.normal;
+                            first.get_or_insert(hit);
+                        }
+                        let sample_color = self.integrator.radiance(&r, &scene, &mut rng);
+
+                        pixel_color += sample_color;
+                        stats.push(luminance(&sample_color));
+                    }
+
+                    if self.converged(&stats) {
+                        break;
+                    }
+                }
+
+                let samples = stats.count() as f64;
+                pixels.push(Pixel {
+                    color: pixel_color / samples,
+                    samples: stats.count(),
+                    aov: first.map(|first| (albedo / samples, normal / samples, first)),
+                });
+            }
+        }
+
+        pixels
     }
 
-    pub fn get_ray(&self, i: i32, j: i32) -> Ray {
-        let offset = Camera::sample_square();
-        let pixel_sample = self.pixel00_loc
-            + ((i as f64 + offset.x()) * self.pixel_delta_u)
-            + ((j as f64 + offset.y()) * self.pixel_delta_v);
+    /// What a camera ray sees first, for the AOVs.
+    fn first_hit<T: HittableCollection>(&self, r: &Ray, world: &T) -> AovSample {
+        let mut rec: HitRecord = Default::default();
+        if !world.hit(r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return AovSample::miss();
+        }
 
-        // eprintln!("offset {:#?}, pixel_sample {:#?}\n", offset, pixel_sample);
-        let ray_origin: Vec3;
-        if self.defocus_angle <= 0.0 {
-            ray_origin = self.center;
-        } else {
-            ray_origin = self.defocus_disk_sample();
+        AovSample {
+            albedo: rec
+                .mat
+                .as_ref()
+                .map_or(Color::default(), |mat| mat.albedo(&rec)),
+            normal: rec.normal,
+            depth: rec.t * r.direction().length(),
+            object: rec.object_id + 1,
+            material: rec.mat.as_ref().map_or(0, |mat| mat.id()),
         }
+    }
 
-        // if self.defocus_angle <= 0.0
-        let ray_direction = pixel_sample - ray_origin;
+    /// Whether the pixel is smooth enough to stop adding samples. A single batch says little
+    /// about the variance: a dim pixel may well miss every light during its first samples.
+    fn converged(&self, stats: &RunningStats) -> bool {
+        stats.count() >= 2 * self.samples_per_pixel.max(1)
+            && stats.standard_error() <= self.noise_threshold * stats.mean().max(DARK_LUMINANCE)
+    }
 
-        Ray::new(ray_origin, ray_direction)
+    /// First hit passes of the last render, when `render_aovs` was set.
+    pub fn aovs(&self) -> Option<&Aovs> {
+        self.aovs.as_ref()
     }
 
-    pub fn render<T: HittableCollection>(&mut self, world: &T) {
-        self.initialize();
+    /// Samples taken by every pixel of the last render, row by row from the top.
+    pub fn sample_counts(&self) -> &[u32] {
+        &self.sample_counts
+    }
 
-        let mut file = File::create("output.ppm").unwrap();
+    /// False color image of `sample_counts`, from blue for the fewest samples a pixel can take
+    /// through green to red for the most.
+    pub fn sample_heatmap(&self) -> Framebuffer {
+        let mut heatmap = Framebuffer::new(self.image_width, self.image_height);
+        let least = (2 * self.samples_per_pixel.max(1)) as f64;
+        let most = (self.max_samples_per_pixel as f64).max(least);
+
+        for (index, &samples) in self.sample_counts.iter().enumerate() {
+            let t = if most > least {
+                ((samples as f64 - least) / (most - least)).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let heat = if t < 0.5 {
+                Color::new(0.0, 2.0 * t, 1.0 - 2.0 * t)
+            } else {
+                Color::new(2.0 * t - 1.0, 2.0 - 2.0 * t, 0.0)
+            };
+            let width = self.image_width as usize;
+            heatmap.set((index % width) as u32, (index / width) as u32, heat);
+        }
 
-        let _ = writeln!(file, "P3\n{} {}\n255", self.image_width, self.image_height);
+        heatmap
+    }
 
-        for j in 0..self.image_height {
-            eprint!("\rScanlines remaining: {}", self.image_height - j);
-            std::io::stderr().flush().unwrap();
-            for i in 0..self.image_width {
-                let mut pixel_color = Color::new(0.0, 0.0, 0.0);
-                for _ in 0..self.samples_per_pixel {
-                    let mut r = self.get_ray(i as i32, j as i32);
-                    let sample_color = Self::ray_color(&mut r, self.max_depth, world);
-                    // eprintln!("ray {:#?}, sample color: {:#?}", r, sample_color);
+    fn defocus_disk_sample(&self, rng: &mut RtRng) -> Point3 {
+        // Returns a random point in the camera lens opening.
+        let p = self.aperture.sample(rng);
+        self.center + (p.x() * self.defocus_disk_u) + (p.y() * self.defocus_disk_v)
+    }
 
-                    pixel_color += sample_color;
-                }
+    /// Point in focus seen through `pixel_sample` on the viewport: the pixel itself, or where
+    /// the ray through the center of the lens meets the tilted plane in focus.
+    fn focus_point(&self, pixel_sample: Point3) -> Point3 {
+        let Some(normal) = self.focus_normal else {
+            return pixel_sample;
+        };
+        let direction = pixel_sample - self.center;
+        let facing = direction.dot(&normal);
+        if facing >= 0.0 {
+            // the plane in focus runs away from this pixel
+            return pixel_sample;
+        }
+        let t = (-self.focus_dist * self.w).dot(&normal) / facing;
+        self.center + t * direction
+    }
+}
 
-                let pixel_color = pixel_color * self.pixel_sample_scale;
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::aov::Aov;
+    use crate::hittable::Hittable;
+    use crate::material::{Dielectric, DiffuseLight, Lambertian, Metal, Numbered};
+    use crate::rtweekend::seeded_rng;
+    use crate::sphere::Sphere;
+
+    fn small_scene() -> (Camera, Vec<Box<dyn Hittable>>) {
+        let world: Vec<Box<dyn Hittable>> = vec![
+            Box::new(Sphere::new(
+                Point3::new(0.0, -100.5, -1.0),
+                100.0,
+                Arc::new(Numbered::new(
+                    1,
+                    Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0))),
+                )),
+            )),
+            Box::new(Sphere::new(
+                Point3::new(0.0, 0.0, -1.2),
+                0.5,
+                Arc::new(Numbered::new(2, Arc::new(Dielectric::new(1.5)))),
+            )),
+            Box::new(Sphere::new(
+                Point3::new(1.0, 0.0, -1.0),
+                0.5,
+                Arc::new(Numbered::new(
+                    3,
+                    Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.3)),
+                )),
+            )),
+        ];
+
+        let mut camera = Camera::new();
+        camera.image_width = 37;
+        camera.aspect_ratio = 37.0 / 21.0;
+        camera.samples_per_pixel = 4;
+        camera.max_depth = 6;
+        camera.defocus_angle = 2.0;
+        camera.focus_dist = 1.0;
+        camera.seed = 7;
+
+        (camera, world)
+    }
 
-                write_color(&mut file, &pixel_color);
-            }
+    #[test]
+    fn test_render_independent_of_thread_count() {
+        let (mut camera, world) = small_scene();
+
+        camera.threads = 1;
+        let single = camera.render(&world, &HittableList::new());
+        camera.threads = 4;
+        let multi = camera.render(&world, &HittableList::new());
+
+        assert_eq!((single.width(), single.height()), (37, 21));
+        assert_eq!(single, multi, "Thread count must not change the image.");
+    }
+
+    #[test]
+    fn test_aovs() {
+        let (mut camera, world) = small_scene();
+        let image = camera.render(&world, &HittableList::new());
+        assert!(camera.aovs().is_none());
+
+        camera.render_aovs = true;
+        assert_eq!(camera.render(&world, &HittableList::new()), image);
+        let aovs = camera.aovs().unwrap();
+        let at = |i: usize, j: usize| j * 37 + i;
+
+        // the sky, the ground, then the glass and the metal spheres in the middle row
+        assert_eq!(aovs.object_id()[at(18, 0)], 0);
+        assert_eq!(aovs.depth()[at(18, 0)], f64::INFINITY);
+        assert_eq!(aovs.object_id()[at(18, 20)], 1);
+        assert_eq!(aovs.object_id()[at(18, 10)], 2);
+        assert_eq!(aovs.object_id()[at(34, 10)], 3);
+        assert_eq!(aovs.material_id()[at(18, 0)], 0);
+        assert_eq!(aovs.material_id()[at(18, 20)], 1);
+        assert_eq!(aovs.material_id()[at(18, 10)], 2);
+        assert_eq!(aovs.material_id()[at(34, 10)], 3);
+
+        assert!((aovs.depth()[at(18, 10)] - 0.7).abs() < 0.02);
+        assert!((aovs.normal()[at(18, 10)] - Vec3::new(0.0, 0.0, 1.0)).length() < 0.05);
+        assert_eq!(aovs.albedo()[at(18, 10)], Color::new(1.0, 1.0, 1.0));
+        assert!((aovs.albedo()[at(34, 10)] - Color::new(0.8, 0.6, 0.2)).length() < 1e-12);
+
+        // the data keeps the raw values, the preview normalizes them
+        let depth = aovs.data(Aov::Depth);
+        assert_eq!(depth.get(18, 10).x(), aovs.depth()[at(18, 10)]);
+        assert_eq!(depth.get(18, 0).x(), f64::INFINITY);
+        assert_eq!(
+            aovs.data(Aov::Normal).get(18, 10),
+            aovs.normal()[at(18, 10)]
+        );
+        assert_eq!(aovs.data(Aov::ObjectId).get(34, 10).x(), 3.0);
+        assert_eq!(aovs.preview(Aov::Depth).get(18, 0).x(), 0.0);
+        assert!(aovs.preview(Aov::Depth).get(18, 10).x() <= 1.0);
+
+        // the ids belong to the scene, not to the order the image meets the materials in
+        camera.lookfrom = Point3::new(0.0, 0.0, 1.0);
+        camera.image_width = 11;
+        camera.defocus_angle = 0.0;
+        camera.render(&world, &HittableList::new());
+        let aovs = camera.aovs().unwrap();
+        assert_eq!(aovs.material_id()[..11], [0; 11]);
+        assert_eq!(aovs.material_id()[aovs.material_id().len() - 6], 1);
+        assert_eq!(aovs.material_id()[2 * 11 + 5], 2);
+    }
+
+    #[test]
+    fn test_projections() {
+        let mut camera = Camera::new();
+        camera.image_width = 400;
+        camera.aspect_ratio = 2.0;
+        let mut rng = seeded_rng(4);
+        let forward = Vec3::new(0.0, 0.0, -1.0);
+
+        camera.projection = Projection::Orthographic { height: 4.0 };
+        camera.initialize();
+        let top_left = camera.get_ray(0, 0, &mut rng).unwrap();
+        let bottom_right = camera.get_ray(399, 199, &mut rng).unwrap();
+        assert_eq!(*top_left.direction(), forward);
+        assert_eq!(*bottom_right.direction(), forward);
+        let across = *bottom_right.origin() - *top_left.origin();
+        assert!((across.x() - 7.98).abs() < 0.03 && (across.y() + 3.98).abs() < 0.03);
+
+        camera.projection = Projection::Equirectangular;
+        camera.initialize();
+        let direction = |camera: &Camera, i, j, rng: &mut RtRng| {
+            camera.get_ray(i, j, rng).unwrap().direction().unit()
+        };
+        // the middle looks ahead, a quarter turn to the right and left, the poles at the edges
+        assert!((direction(&camera, 200, 100, &mut rng) - forward).length() < 0.02);
+        assert!(
+            (direction(&camera, 300, 100, &mut rng) - Vec3::new(1.0, 0.0, 0.0)).length() < 0.02
+        );
+        assert!(
+            (direction(&camera, 100, 100, &mut rng) - Vec3::new(-1.0, 0.0, 0.0)).length() < 0.02
+        );
+        assert!(direction(&camera, 70, 0, &mut rng).y() > 0.999);
+        assert!(direction(&camera, 330, 199, &mut rng).y() < -0.999);
+
+        // the image circle of the fisheye is 200 pixels wide, at 180 degrees its rim looks
+        // sideways
+        camera.projection = Projection::Fisheye { fov: 180.0 };
+        camera.initialize();
+        assert!((direction(&camera, 200, 100, &mut rng) - forward).length() < 0.02);
+        assert!(direction(&camera, 298, 100, &mut rng).x() > 0.99);
+        assert!(camera.get_ray(0, 0, &mut rng).is_none());
+        assert!(camera.get_ray(301, 100, &mut rng).is_none());
+    }
+
+    #[test]
+    fn test_orientation_roll_and_shift() {
+        let close = |a: Vec3, b: Vec3| (a - b).length() < 1e-12;
+        let mut camera = Camera::new();
+        camera.lookat = Point3::new(-1.0, 0.0, 0.0);
+        camera.initialize();
+        let (u, v, w) = (camera.u, camera.v, camera.w);
+
+        // the same frame given as a quarter turn around y
+        camera.lookat = Point3::new(0.0, 0.0, -1.0);
+        camera.orientation = Some(Mat4::rotation(Vec3::new(0.0, 1.0, 0.0), 90.0));
+        camera.initialize();
+        assert!(close(camera.u, u) && close(camera.v, v) && close(camera.w, w));
+
+        // rolling a quarter turn counter-clockwise, right becomes up
+        camera.roll = 90.0;
+        camera.initialize();
+        assert!(close(camera.u, v) && close(camera.v, -u) && close(camera.w, w));
+
+        // shifting up half the image moves the pixels, not the view direction
+        let pixel00 = camera.pixel00_loc;
+        camera.shift = [0.0, 0.5];
+        camera.initialize();
+        let height = camera.pixel_delta_v.length() * camera.image_height as f64;
+        assert!(close(camera.pixel00_loc, pixel00 + 0.5 * height * camera.v));
+        assert!(close(camera.w, w));
+    }
+
+    #[test]
+    fn test_tilted_focus() {
+        let mut camera = Camera::new();
+        camera.image_width = 100;
+        camera.focus_dist = 4.0;
+        camera.defocus_angle = 10.0;
+        camera.initialize();
+        let sample = camera.pixel00_loc;
+        assert_eq!(camera.focus_point(sample), sample);
+
+        // tilted back, the plane in focus comes closer at the top and recedes at the bottom
+        camera.tilt = [30.0, 0.0];
+        camera.initialize();
+        let depth = |camera: &Camera, i: f64, j: f64| {
+            let sample = camera.pixel00_loc + i * camera.pixel_delta_u + j * camera.pixel_delta_v;
+            -camera.focus_point(sample).z()
+        };
+        assert!((depth(&camera, 49.5, 49.5) - 4.0).abs() < 1e-9);
+        assert!(depth(&camera, 49.5, 0.0) < 3.0);
+        assert!(depth(&camera, 49.5, 99.0) > 6.0);
+        // every point lies on the plane in focus
+        let normal = Vec3::new(0.0, -0.5, 3f64.sqrt() / 2.0);
+        for (i, j) in [(0.0, 0.0), (99.0, 20.0), (30.0, 99.0)] {
+            let sample = camera.pixel00_loc + i * camera.pixel_delta_u + j * camera.pixel_delta_v;
+            let offset = camera.focus_point(sample) - Point3::new(0.0, 0.0, -4.0);
+            assert!(offset.dot(&normal).abs() < 1e-9);
         }
+    }
+
+    #[test]
+    fn test_render_is_reproducible() {
+        let (mut camera, world) = small_scene();
+
+        let first = camera.render(&world, &HittableList::new());
+        let second = camera.render(&world, &HittableList::new());
+
+        assert!(
+            first
+                .pixels()
+                .iter()
+                .zip(second.pixels())
+                .all(|(a, b)| a.x().to_bits() == b.x().to_bits()
+                    && a.y().to_bits() == b.y().to_bits()
+                    && a.z().to_bits() == b.z().to_bits()),
+            "A fixed seed must give a bit-identical image."
+        );
+    }
+
+    #[test]
+    fn test_render_pixels_in_memory() {
+        let (mut camera, _) = small_scene();
+        camera.samples_per_pixel = 1;
+        // an empty world only shows the sky gradient, bluer towards the top
+        let empty: Vec<Box<dyn Hittable>> = Vec::new();
+
+        let framebuffer = camera.render(&empty, &HittableList::new());
+        let top = framebuffer.get(18, 0);
+        let bottom = framebuffer.get(18, 20);
+
+        assert!((top.z() - 1.0).abs() < 1e-12 && (bottom.z() - 1.0).abs() < 1e-12);
+        assert!(
+            top.x() < bottom.x(),
+            "The sky should fade to white at the horizon."
+        );
+    }
+
+    #[test]
+    fn test_render_depends_on_seed() {
+        let (mut camera, world) = small_scene();
+
+        let first = camera.render(&world, &HittableList::new());
+        camera.seed = 8;
+        let second = camera.render(&world, &HittableList::new());
+
+        assert_ne!(
+            first, second,
+            "Another seed should give another noise pattern."
+        );
+    }
 
-        eprintln!("\rDone.        ");
+    #[test]
+    fn test_adaptive_sampling() {
+        let (mut camera, world) = small_scene();
+        camera.defocus_angle = 0.0;
+        camera.samples_per_pixel = 8;
+        camera.noise_threshold = 0.02;
+        camera.max_samples_per_pixel = 64;
+
+        let framebuffer = camera.render(&world, &HittableList::new());
+        let counts = camera.sample_counts();
+        assert_eq!(counts.len(), framebuffer.pixels().len());
+        assert!(counts.iter().all(|&n| n % 8 == 0 && (16..=64).contains(&n)));
+        // the sky converges at the first check, the fuzzy metal sphere needs more
+        assert_eq!(counts[18], 16, "Flat sky.");
+        assert!(counts[20 * 37 + 36] > 16, "Noisy fuzzy metal.");
+
+        let heatmap = camera.sample_heatmap();
+        assert_eq!(heatmap.get(18, 0), Color::new(0.0, 0.0, 1.0));
+        assert_eq!((heatmap.width(), heatmap.height()), (37, 21));
+
+        camera.noise_threshold = 0.0;
+        camera.render(&world, &HittableList::new());
+        assert!(camera.sample_counts().iter().all(|&n| n == 8));
     }
 
-    fn defocus_disk_sample(&self) -> Point3 {
-        // Returns a random point in the camera defocus disk.
-        let p = Vec3::random_in_unit_disk();
-        return self.center + (p.x() * self.defocus_disk_u) + (p.y() * self.defocus_disk_v);
+    #[test]
+    fn test_render_enclosed_light() {
+        let (mut camera, _) = small_scene();
+        camera.samples_per_pixel = 16;
+        camera.defocus_angle = 0.0;
+        camera.lookfrom = Point3::new(0.0, 0.0, 0.0);
+        camera.lookat = Point3::new(0.0, 0.0, -1.0);
+
+        // the camera sits inside a closed sphere, only the light can brighten the image
+        let world: Vec<Box<dyn Hittable>> = vec![
+            Box::new(Sphere::new(
+                Point3::new(0.0, 0.0, 0.0),
+                5.0,
+                Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+            )),
+            Box::new(Sphere::new(
+                Point3::new(0.0, 0.0, -2.0),
+                0.5,
+                Arc::new(DiffuseLight::new(Color::new(4.0, 4.0, 4.0))),
+            )),
+        ];
+
+        camera.background = Background::Solid(Color::new(100.0, 0.0, 0.0));
+        let lit = camera.render(&world, &HittableList::new());
+        let center = lit.get(18, 10);
+        assert_eq!(center, Color::new(4.0, 4.0, 4.0), "Light seen directly.");
+        assert!(
+            lit.pixels().iter().all(|c| c.x() == c.y()),
+            "No background."
+        );
+        assert!(
+            lit.pixels().iter().any(|c| c.x() > 0.0 && c.x() < 4.0),
+            "The walls should get indirect light."
+        );
+
+        camera.background = Background::None;
+        let dark_world: Vec<Box<dyn Hittable>> = vec![Box::new(Sphere::new(
+            Point3::new(0.0, 0.0, -2.0),
+            0.5,
+            Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        ))];
+        let dark = camera.render(&dark_world, &HittableList::new());
+        assert!(dark.pixels().iter().all(|c| *c == Color::default()));
     }
 }
@@ -1,10 +1,14 @@
-use std::fs::File;
-use std::io::Write;
+use std::io::{self, Write};
 
 use crate::{interval::Interval, vec3::Vec3};
 
 pub type Color = Vec3;
 
+/// Relative luminance of a linear Rec. 709 color.
+pub fn luminance(pixel_color: &Color) -> f64 {
+    0.2126 * pixel_color.x() + 0.7152 * pixel_color.y() + 0.0722 * pixel_color.z()
+}
+
 pub fn linear_to_gamma(linear_component: f64) -> f64 {
     if linear_component > 0.0 {
         linear_component.sqrt()
@@ -13,21 +17,205 @@ pub fn linear_to_gamma(linear_component: f64) -> f64 {
     }
 }
 
-pub fn write_color(file: &mut File, pixel_color: &Color) {
-    let r = (pixel_color.x()).clone();
-    let g = (pixel_color.y()).clone();
-    let b = (pixel_color.z()).clone();
+/// Encoding of the display values in [0, 1] into the values stored in an 8 bit image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transfer {
+    /// The piecewise sRGB curve, linear near black
+    Srgb,
+    /// A plain power of 1 / gamma, gamma 2 is what RTOW writes
+    Gamma(f64),
+}
+
+impl Transfer {
+    pub fn encode(&self, linear_component: f64) -> f64 {
+        let x = linear_component.max(0.0);
+        match *self {
+            Transfer::Srgb => {
+                if x <= 0.003_130_8 {
+                    12.92 * x
+                } else {
+                    1.055 * x.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            // the square root of RTOW, exactly
+            Transfer::Gamma(2.0) => linear_to_gamma(x),
+            Transfer::Gamma(gamma) => x.powf(1.0 / gamma),
+        }
+    }
+}
+
+/// Compression of the unbounded radiance of the render into the [0, 1] of a display.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ToneMap {
+    /// Everything above 1 is clipped
+    #[default]
+    Clamp,
+    /// `x / (1 + x)` on every channel
+    Reinhard,
+    /// Stephen Hill's fit of the ACES reference rendering and output transforms, as in the HDR
+    /// shader of the wgpu tutorial
+    Aces,
+    /// John Hable's filmic curve of Uncharted 2, white at 11.2
+    Filmic,
+}
+
+impl ToneMap {
+    pub fn apply(&self, hdr: &Color) -> Color {
+        let per_channel = |f: fn(f64) -> f64| Color::new(f(hdr.x()), f(hdr.y()), f(hdr.z()));
+        let mapped = match self {
+            ToneMap::Clamp => *hdr,
+            ToneMap::Reinhard => per_channel(|x| x / (1.0 + x)),
+            ToneMap::Aces => aces(hdr),
+            ToneMap::Filmic => {
+                const WHITE: f64 = 11.2;
+                per_channel(|x| hable(x) / hable(WHITE))
+            }
+        };
+        let unit = Interval::new(0.0, 1.0);
+        Color::new(
+            unit.clamp(mapped.x()),
+            unit.clamp(mapped.y()),
+            unit.clamp(mapped.z()),
+        )
+    }
+}
+
+/// ACES input matrix, sRGB to the rendering space, fused with the RRT saturation; then a
+/// rational fit of the RRT and ODT curves and the output matrix back to sRGB.
+fn aces(hdr: &Color) -> Color {
+    let v = Color::new(
+        0.597_19 * hdr.x() + 0.354_58 * hdr.y() + 0.048_23 * hdr.z(),
+        0.076_00 * hdr.x() + 0.908_34 * hdr.y() + 0.015_66 * hdr.z(),
+        0.028_40 * hdr.x() + 0.133_83 * hdr.y() + 0.837_77 * hdr.z(),
+    );
+    let curve = |v: f64| {
+        let a = v * (v + 0.024_578_6) - 0.000_090_537;
+        let b = v * (0.983_729 * v + 0.432_951) + 0.238_081;
+        a / b
+    };
+    let c = Color::new(curve(v.x()), curve(v.y()), curve(v.z()));
+    Color::new(
+        1.604_75 * c.x() - 0.531_08 * c.y() - 0.073_67 * c.z(),
+        -0.102_08 * c.x() + 1.108_13 * c.y() - 0.006_05 * c.z(),
+        -0.003_27 * c.x() - 0.072_76 * c.y() + 1.076_02 * c.z(),
+    )
+}
+
+fn hable(x: f64) -> f64 {
+    let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+    ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f
+}
+
+/// How the linear colors of a framebuffer are turned into the bytes of an image: scaled by the
+/// exposure, tone mapped into [0, 1], then encoded by the transfer function. The default
+/// matches RTOW, clipped and with gamma 2.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayTransform {
+    /// Brightness change in stops, every stop doubles the radiance
+    pub exposure: f64,
+    pub tone_map: ToneMap,
+    pub transfer: Transfer,
+}
+
+impl Default for DisplayTransform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DisplayTransform {
+    pub fn new() -> Self {
+        Self {
+            exposure: 0.0,
+            tone_map: ToneMap::Clamp,
+            transfer: Transfer::Gamma(2.0),
+        }
+    }
 
-    // apply a linear to gamma transform for gamma 2
-    let r = linear_to_gamma(r);
-    let g = linear_to_gamma(g);
-    let b = linear_to_gamma(b);
+    /// Display value in [0, 1] of a linear color.
+    pub fn apply(&self, pixel_color: &Color) -> Color {
+        let exposed = 2f64.powf(self.exposure) * *pixel_color;
+        let mapped = self.tone_map.apply(&exposed);
+        Color::new(
+            self.transfer.encode(mapped.x()),
+            self.transfer.encode(mapped.y()),
+            self.transfer.encode(mapped.z()),
+        )
+    }
+}
+
+/// Translate a linear pixel color into [0, 255] byte values for display.
+pub fn color_to_bytes(pixel_color: &Color, display: &DisplayTransform) -> [u8; 3] {
+    let encoded = display.apply(pixel_color);
 
     let intensity = Interval::new(0.0, 0.999);
 
-    let rbyte = (256.0 * intensity.clamp(r)) as u32;
-    let gbyte = (256.0 * intensity.clamp(g)) as u32;
-    let bbyte = (256.0 * intensity.clamp(b)) as u32;
+    let rbyte = (256.0 * intensity.clamp(encoded.x())) as u8;
+    let gbyte = (256.0 * intensity.clamp(encoded.y())) as u8;
+    let bbyte = (256.0 * intensity.clamp(encoded.z())) as u8;
+
+    [rbyte, gbyte, bbyte]
+}
+
+pub fn write_color<W: Write>(
+    out: &mut W,
+    pixel_color: &Color,
+    display: &DisplayTransform,
+) -> io::Result<()> {
+    let [rbyte, gbyte, bbyte] = color_to_bytes(pixel_color, display);
+
+    writeln!(out, "{rbyte} {gbyte} {bbyte}")
+}
 
-    let _ = writeln!(file, "{rbyte} {gbyte} {bbyte}");
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer() {
+        assert_eq!(Transfer::Gamma(2.0).encode(0.25), 0.5);
+        assert!((Transfer::Gamma(2.2).encode(0.5) - 0.5f64.powf(1.0 / 2.2)).abs() < 1e-12);
+        // the two pieces of the sRGB curve meet, and white stays white
+        let knee = 0.003_130_8;
+        assert!((Transfer::Srgb.encode(knee) - Transfer::Srgb.encode(knee + 1e-12)).abs() < 1e-6);
+        assert!((Transfer::Srgb.encode(1.0) - 1.0).abs() < 1e-12);
+        assert_eq!(Transfer::Srgb.encode(-1.0), 0.0);
+    }
+
+    #[test]
+    fn test_tone_maps() {
+        for tone_map in [ToneMap::Reinhard, ToneMap::Aces, ToneMap::Filmic] {
+            // black stays black, brighter stays brighter and nothing leaves [0, 1]
+            let mut previous = tone_map.apply(&Color::default()).x();
+            assert!(previous.abs() < 1e-3, "{tone_map:?}");
+            for i in 1..=100 {
+                let x = 0.1 * (i * i) as f64;
+                let mapped = tone_map.apply(&Color::new(x, x, x)).x();
+                assert!(mapped >= previous && mapped <= 1.0, "{tone_map:?} at {x}");
+                previous = mapped;
+            }
+        }
+        assert_eq!(
+            ToneMap::Clamp.apply(&Color::new(0.5, 2.0, -1.0)),
+            Color::new(0.5, 1.0, 0.0)
+        );
+        assert_eq!(
+            ToneMap::Reinhard.apply(&Color::new(1.0, 3.0, 0.0)).y(),
+            0.75
+        );
+        assert!((ToneMap::Filmic.apply(&Color::new(11.2, 11.2, 11.2)).x() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_exposure() {
+        let display = DisplayTransform {
+            exposure: 2.0,
+            ..DisplayTransform::new()
+        };
+        assert_eq!(display.apply(&Color::new(0.0625, 0.0625, 0.0625)).x(), 0.5);
+        assert_eq!(
+            color_to_bytes(&Color::new(0.25, 0.25, 0.25), &DisplayTransform::new()),
+            [128; 3]
+        );
+    }
 }
@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    color::Color,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::{Isotropic, Material},
+    ray::Ray,
+    rtweekend::mix64,
+    texture::Texture,
+    vec3::Vec3,
+};
+
+/// Volume of constant density filling a closed, convex boundary, like fog or smoke.
+///
+/// A ray travelling a distance d inside scatters with the probability 1 - exp(-density * d).
+pub struct ConstantMedium {
+    boundary: Box<dyn Hittable>,
+    neg_inv_density: f64,
+    phase_function: Arc<dyn Material>,
+    /// Decorrelates the free paths drawn by the media crossed by the same ray
+    salt: u64,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Box<dyn Hittable>, density: f64, albedo: Color) -> Self {
+        Self::with_phase_function(boundary, density, Arc::new(Isotropic::new(albedo)))
+    }
+
+    pub fn textured(boundary: Box<dyn Hittable>, density: f64, tex: Arc<dyn Texture>) -> Self {
+        Self::with_phase_function(boundary, density, Arc::new(Isotropic::textured(tex)))
+    }
+
+    /// Medium scattering with any material, usually an `Isotropic` one.
+    pub fn with_phase_function(
+        boundary: Box<dyn Hittable>,
+        density: f64,
+        phase_function: Arc<dyn Material>,
+    ) -> Self {
+        let bbox = boundary.bounding_box();
+        let salt = [bbox.x, bbox.y, bbox.z]
+            .iter()
+            .flat_map(|axis| [axis.min, axis.max])
+            .fold(mix64(density.to_bits()), |h, bound| {
+                mix64(h ^ bound.to_bits())
+            });
+
+        Self {
+            boundary,
+            neg_inv_density: -1.0 / density,
+            phase_function,
+            salt,
+        }
+    }
+
+    /// Uniform number in (0, 1] drawn from the ray itself.
+    ///
+    /// `hit` has no random generator, but every ray reaching it was sampled at random, so
+    /// hashing its bits gives a fresh number that is still reproducible for a given seed.
+    fn random_from_ray(&self, r: &Ray) -> f64 {
+        let bits = [r.origin(), r.direction()]
+            .iter()
+            .flat_map(|v| [v.x(), v.y(), v.z()])
+            .fold(self.salt, |h, c| mix64(h ^ c.to_bits()));
+        // the top 53 bits give a double in [0, 1), flipped so log() stays finite
+        1.0 - (bits >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let mut rec1 = HitRecord::default();
+        let mut rec2 = HitRecord::default();
+
+        // Entry and exit of the whole line, the entry lies behind the origin of a ray that
+        // starts inside the volume.
+        if !self.boundary.hit(r, Interval::universe(), &mut rec1) {
+            return false;
+        }
+
+        if !self
+            .boundary
+            .hit(r, Interval::new(rec1.t + 0.0001, f64::INFINITY), &mut rec2)
+        {
+            return false;
+        }
+
+        // Only the part of the segment inside the ray interval can scatter.
+        let t_enter = rec1.t.max(ray_t.min).max(0.0);
+        let t_exit = rec2.t.min(ray_t.max);
+        if t_enter >= t_exit {
+            return false;
+        }
+
+        let ray_length = r.direction().length();
+        let distance_inside_boundary = (t_exit - t_enter) * ray_length;
+        let hit_distance = self.neg_inv_density * self.random_from_ray(r).ln();
+
+        if hit_distance > distance_inside_boundary {
+            return false;
+        }
+
+        rec.t = t_enter + hit_distance / ray_length;
+        rec.p = r.at(rec.t);
+
+        // arbitrary
+        rec.normal = Vec3::new(1.0, 0.0, 0.0);
+        // also arbitrary
+        rec.front_face = true;
+        rec.mat = Some(self.phase_function.clone());
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::rtweekend::seeded_rng;
+    use crate::sphere::Sphere;
+    use crate::vec3::Point3;
+
+    fn fog(density: f64) -> ConstantMedium {
+        let boundary = Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        );
+        ConstantMedium::new(Box::new(boundary), density, Color::new(1.0, 1.0, 1.0))
+    }
+
+    /// Fraction of random rays from `origin` scattered by the medium.
+    fn scattered_fraction(medium: &ConstantMedium, origin: Point3) -> f64 {
+        let mut rng = seeded_rng(9);
+        let n = 20_000;
+        let mut count = 0;
+        for _ in 0..n {
+            let target = Vec3::random_unit_vector(&mut rng) * 1e-3;
+            let r = Ray::new(origin, target - origin);
+            let mut rec = HitRecord::default();
+            if medium.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+                assert!(rec.t > 0.001 && rec.p.length() <= 1.0 + 1e-9);
+                count += 1;
+            }
+        }
+        count as f64 / n as f64
+    }
+
+    #[test]
+    fn test_ray_from_outside() {
+        let medium = fog(0.5);
+        // the rays cross the whole diameter
+        let expected = 1.0 - (-0.5 * 2.0f64).exp();
+        let fraction = scattered_fraction(&medium, Point3::new(0.0, 0.0, 5.0));
+        assert!((fraction - expected).abs() < 0.02, "{fraction} {expected}");
+    }
+
+    #[test]
+    fn test_ray_from_inside() {
+        let medium = fog(0.5);
+        // from the center, only the radius is left to cross
+        let expected = 1.0 - (-0.5f64).exp();
+        let fraction = scattered_fraction(&medium, Point3::new(0.0, 0.0, 0.0));
+        assert!((fraction - expected).abs() < 0.02, "{fraction} {expected}");
+
+        // a ray leaving the volume cannot scatter behind its origin
+        let r = Ray::new(Point3::new(0.0, 0.0, 0.999), Vec3::new(0.0, 0.0, 1.0));
+        let mut rec = HitRecord::default();
+        let dense = fog(1e6);
+        assert!(!dense.hit(&r, Interval::new(0.01, f64::INFINITY), &mut rec));
+        assert!(dense.hit(&r, Interval::new(0.0001, f64::INFINITY), &mut rec));
+        assert!(rec.t < 0.001);
+    }
+}
@@ -0,0 +1,269 @@
+//! Edge avoiding à-trous wavelet filter of Dammertz et al. 2010, removing the noise of low
+//! sample count renders with the albedo and normal passes as guides.
+//!
+//! The filter works on the illumination: the image divided by the albedo, so that textures are
+//! not blurred away, and multiplied back at the end. Each pass blurs with a 5x5 B3 spline whose
+//! taps spread twice as far as in the previous pass, weighted down across the edges of the
+//! illumination, the normals and the albedo.
+
+use rayon::prelude::*;
+
+use crate::aov::Aovs;
+use crate::color::{linear_to_gamma, Color};
+use crate::framebuffer::Framebuffer;
+use crate::vec3::Vec3;
+
+/// B3 spline coefficients, the 5x5 kernel is their outer product.
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+/// Albedo component under which the illumination is not divided by it.
+const MIN_ALBEDO: f64 = 1e-3;
+
+/// Parameters of the filter, the sigmas are the differences at which a neighbour's weight
+/// falls to 1/e.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Denoiser {
+    /// Count of passes, the last one reaches 2^iterations pixels away
+    pub iterations: u32,
+    /// Illumination difference, halved after every pass as the noise goes down
+    pub sigma_color: f64,
+    pub sigma_normal: f64,
+    pub sigma_albedo: f64,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Denoiser {
+    pub fn new() -> Self {
+        Self {
+            iterations: 5,
+            sigma_color: 1.0,
+            sigma_normal: 0.3,
+            sigma_albedo: 0.1,
+        }
+    }
+
+    /// Filter `image` guided by the passes rendered along with it.
+    pub fn denoise(&self, image: &Framebuffer, aovs: &Aovs) -> Framebuffer {
+        let width = image.width() as usize;
+        let height = image.height() as usize;
+        assert_eq!(
+            (aovs.width() as usize, aovs.height() as usize),
+            (width, height),
+            "the passes must match the image"
+        );
+
+        let albedo: Vec<Color> = aovs.albedo().iter().map(demodulation).collect();
+        let mut illumination: Vec<Color> = image
+            .pixels()
+            .iter()
+            .zip(&albedo)
+            .map(|(color, albedo)| divide(color, albedo))
+            .collect();
+
+        let mut sigma_color = self.sigma_color;
+        for iteration in 0..self.iterations {
+            let step = 1 << iteration;
+            illumination = (0..width * height)
+                .into_par_iter()
+                .map(|index| {
+                    let (i, j) = (index % width, index / width);
+                    self.filter_pixel(
+                        &illumination,
+                        aovs,
+                        (i, j),
+                        (width, height),
+                        step,
+                        sigma_color,
+                    )
+                })
+                .collect();
+            sigma_color *= 0.5;
+        }
+
+        let mut denoised = Framebuffer::new(image.width(), image.height());
+        for (index, (light, albedo)) in illumination.iter().zip(&albedo).enumerate() {
+            let color = Color::new(
+                light.x() * albedo.x(),
+                light.y() * albedo.y(),
+                light.z() * albedo.z(),
+            );
+            denoised.set((index % width) as u32, (index / width) as u32, color);
+        }
+        denoised
+    }
+
+    fn filter_pixel(
+        &self,
+        illumination: &[Color],
+        aovs: &Aovs,
+        (i, j): (usize, usize),
+        (width, height): (usize, usize),
+        step: usize,
+        sigma_color: f64,
+    ) -> Color {
+        let center = j * width + i;
+        let (color_p, normal_p, albedo_p) = (
+            illumination[center],
+            aovs.normal()[center],
+            aovs.albedo()[center],
+        );
+
+        let mut sum = Color::new(0.0, 0.0, 0.0);
+        let mut total_weight = 0.0;
+        for (dy, ky) in KERNEL.iter().enumerate() {
+            let y = j as isize + (dy as isize - 2) * step as isize;
+            if y < 0 || y >= height as isize {
+                continue;
+            }
+            for (dx, kx) in KERNEL.iter().enumerate() {
+                let x = i as isize + (dx as isize - 2) * step as isize;
+                if x < 0 || x >= width as isize {
+                    continue;
+                }
+                let q = y as usize * width + x as usize;
+                let color_q = illumination[q];
+
+                let exponent = (color_p - color_q).length_squared() / (sigma_color * sigma_color)
+                    + (normal_p - aovs.normal()[q]).length_squared()
+                        / (self.sigma_normal * self.sigma_normal)
+                    + (albedo_p - aovs.albedo()[q]).length_squared()
+                        / (self.sigma_albedo * self.sigma_albedo);
+                let weight = kx * ky * (-exponent).exp();
+
+                sum += weight * color_q;
+                total_weight += weight;
+            }
+        }
+
+        // the center always weighs in, with its own differences at zero
+        sum / total_weight
+    }
+}
+
+/// Albedo the image is divided by, 1 where there is too little to divide by.
+fn demodulation(albedo: &Color) -> Color {
+    let component = |c: f64| if c < MIN_ALBEDO { 1.0 } else { c };
+    Color::new(
+        component(albedo.x()),
+        component(albedo.y()),
+        component(albedo.z()),
+    )
+}
+
+fn divide(color: &Color, by: &Color) -> Color {
+    Color::new(color.x() / by.x(), color.y() / by.y(), color.z() / by.z())
+}
+
+/// Mean squared error of `image` against `reference`, over the gamma encoded values clamped to
+/// [0, 1] the way the images are written out.
+pub fn mse(image: &Framebuffer, reference: &Framebuffer) -> f64 {
+    assert_eq!(
+        (image.width(), image.height()),
+        (reference.width(), reference.height()),
+        "the images must have the same size"
+    );
+    let display = |c: &Color| {
+        let encode = |x: f64| linear_to_gamma(x).min(1.0);
+        Vec3::new(encode(c.x()), encode(c.y()), encode(c.z()))
+    };
+
+    let sum: f64 = image
+        .pixels()
+        .iter()
+        .zip(reference.pixels())
+        .map(|(a, b)| (display(a) - display(b)).length_squared())
+        .sum();
+    sum / (3 * image.pixels().len()).max(1) as f64
+}
+
+/// Peak signal to noise ratio of `image` against `reference` in decibels, from their `mse`;
+/// infinite for identical images.
+pub fn psnr(image: &Framebuffer, reference: &Framebuffer) -> f64 {
+    -10.0 * mse(image, reference).log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::hittable::Hittable;
+    use crate::hittable_list::HittableList;
+    use crate::material::{DiffuseLight, Lambertian};
+    use crate::quad::Quad;
+    use crate::vec3::Point3;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_metrics() {
+        let mut image = Framebuffer::new(2, 1);
+        let reference = image.clone();
+        assert_eq!(mse(&image, &reference), 0.0);
+        assert_eq!(psnr(&image, &reference), f64::INFINITY);
+
+        // every value of the first pixel off by 1, the blue of the second clamped to 1
+        image.set(0, 0, Color::new(1.0, 1.0, 1.0));
+        image.set(1, 0, Color::new(0.0, 0.0, 4.0));
+        assert!((mse(&image, &reference) - 4.0 / 6.0).abs() < 1e-12);
+        assert!((psnr(&image, &reference) - 10.0 * 1.5f64.log10()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_denoise_gets_closer_to_the_reference() {
+        // a lit diffuse floor with a step in albedo, the edge must survive the filter
+        let white = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8)));
+        let red = Arc::new(Lambertian::new(Color::new(0.8, 0.1, 0.1)));
+        let light = Arc::new(DiffuseLight::new(Color::new(4.0, 4.0, 4.0)));
+        let lamp = || {
+            Quad::new(
+                Point3::new(-1.0, 3.0, -1.0),
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, 2.0),
+                light.clone(),
+            )
+        };
+        let world: Vec<Box<dyn Hittable>> = vec![
+            Box::new(Quad::new(
+                Point3::new(-4.0, 0.0, -4.0),
+                Vec3::new(4.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, 8.0),
+                white,
+            )),
+            Box::new(Quad::new(
+                Point3::new(0.0, 0.0, -4.0),
+                Vec3::new(4.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, 8.0),
+                red,
+            )),
+            Box::new(lamp()),
+        ];
+        let mut lights = HittableList::new();
+        lights.add(Box::new(lamp()));
+
+        let mut camera = Camera::new();
+        camera.image_width = 48;
+        camera.aspect_ratio = 1.0;
+        camera.vfov = 60.0;
+        camera.lookfrom = Point3::new(0.0, 2.0, 3.0);
+        camera.lookat = Point3::new(0.0, 0.0, 0.0);
+        camera.max_depth = 4;
+        camera.defocus_angle = 0.0;
+        camera.seed = 3;
+
+        camera.samples_per_pixel = 256;
+        let reference = camera.render(&world, &lights);
+
+        camera.samples_per_pixel = 4;
+        camera.render_aovs = true;
+        let noisy = camera.render(&world, &lights);
+        let denoised = Denoiser::new().denoise(&noisy, camera.aovs().unwrap());
+
+        let before = psnr(&noisy, &reference);
+        let after = psnr(&denoised, &reference);
+        assert!(after > before + 3.0, "{before} dB -> {after} dB");
+    }
+}
@@ -0,0 +1,333 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::{
+    color::{color_to_bytes, write_color, Color, DisplayTransform, Transfer},
+    framebuffer::Framebuffer,
+};
+
+/// File formats a framebuffer can be written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// ASCII PPM (P3), gamma corrected 8 bit
+    PpmAscii,
+    /// Binary PPM (P6), gamma corrected 8 bit
+    Ppm,
+    /// PNG, gamma corrected 8 bit
+    Png,
+    /// Portable float map, linear 32 bit float
+    Pfm,
+}
+
+impl ImageFormat {
+    /// Guess the format from the file extension, `.ppm` is written as binary P6.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "ppm" => Some(ImageFormat::Ppm),
+            "png" => Some(ImageFormat::Png),
+            "pfm" => Some(ImageFormat::Pfm),
+            _ => None,
+        }
+    }
+}
+
+/// Encode the framebuffer into `path`, the 8 bit formats through `display`.
+pub fn write_image<P: AsRef<Path>>(
+    framebuffer: &Framebuffer,
+    path: P,
+    format: ImageFormat,
+    display: &DisplayTransform,
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    encode(framebuffer, format, display, &mut writer)?;
+    writer.flush()
+}
+
+/// Encode the framebuffer into any writer. PFM keeps the linear colors and ignores `display`.
+pub fn encode<W: Write>(
+    framebuffer: &Framebuffer,
+    format: ImageFormat,
+    display: &DisplayTransform,
+    writer: &mut W,
+) -> io::Result<()> {
+    match format {
+        ImageFormat::PpmAscii => encode_ppm_ascii(framebuffer, display, writer),
+        ImageFormat::Ppm => encode_ppm(framebuffer, display, writer),
+        ImageFormat::Png => encode_png(framebuffer, display, writer),
+        ImageFormat::Pfm => encode_pfm(framebuffer, writer),
+    }
+}
+
+fn encode_ppm_ascii<W: Write>(
+    framebuffer: &Framebuffer,
+    display: &DisplayTransform,
+    writer: &mut W,
+) -> io::Result<()> {
+    writeln!(
+        writer,
+        "P3\n{} {}\n255",
+        framebuffer.width(),
+        framebuffer.height()
+    )?;
+    for pixel_color in framebuffer.pixels() {
+        write_color(writer, pixel_color, display)?;
+    }
+    Ok(())
+}
+
+fn encode_ppm<W: Write>(
+    framebuffer: &Framebuffer,
+    display: &DisplayTransform,
+    writer: &mut W,
+) -> io::Result<()> {
+    write!(
+        writer,
+        "P6\n{} {}\n255\n",
+        framebuffer.width(),
+        framebuffer.height()
+    )?;
+    writer.write_all(&rgb8(framebuffer, display))
+}
+
+fn encode_png<W: Write>(
+    framebuffer: &Framebuffer,
+    display: &DisplayTransform,
+    writer: &mut W,
+) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(writer, framebuffer.width(), framebuffer.height());
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    // the bytes are already encoded, tag them with their transfer so viewers decode them right
+    match display.transfer {
+        Transfer::Srgb => encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual),
+        Transfer::Gamma(gamma) => {
+            encoder.set_source_gamma(png::ScaledFloat::new((1.0 / gamma) as f32))
+        }
+    }
+
+    let mut png_writer = encoder.write_header().map_err(io::Error::other)?;
+    png_writer
+        .write_image_data(&rgb8(framebuffer, display))
+        .map_err(io::Error::other)?;
+    png_writer.finish().map_err(io::Error::other)
+}
+
+fn encode_pfm<W: Write>(framebuffer: &Framebuffer, writer: &mut W) -> io::Result<()> {
+    // a negative scale marks little endian data, the rows are stored from the bottom up
+    write!(
+        writer,
+        "PF\n{} {}\n-1.0\n",
+        framebuffer.width(),
+        framebuffer.height()
+    )?;
+    for row in framebuffer.rows().rev() {
+        for pixel_color in row {
+            for component in [pixel_color.x(), pixel_color.y(), pixel_color.z()] {
+                writer.write_all(&(component as f32).to_le_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decode the linear colors of the PFM file `path`, such as a reference render.
+pub fn read_pfm<P: AsRef<Path>>(path: P) -> io::Result<Framebuffer> {
+    decode_pfm(&mut BufReader::new(File::open(path)?))
+}
+
+/// Decode a color PFM from any reader.
+pub fn decode_pfm<R: Read>(reader: &mut R) -> io::Result<Framebuffer> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+    // header tokens are separated by whitespace, a single one before the data
+    let mut token = || -> io::Result<String> {
+        let mut token = Vec::new();
+        let mut byte = [0u8];
+        loop {
+            reader.read_exact(&mut byte)?;
+            if byte[0].is_ascii_whitespace() {
+                if !token.is_empty() {
+                    return String::from_utf8(token).map_err(|_| invalid("bad PFM header"));
+                }
+            } else {
+                token.push(byte[0]);
+            }
+        }
+    };
+
+    if token()? != "PF" {
+        return Err(invalid("not a color PFM"));
+    }
+    let width: u32 = token()?.parse().map_err(|_| invalid("bad PFM width"))?;
+    let height: u32 = token()?.parse().map_err(|_| invalid("bad PFM height"))?;
+    let scale: f64 = token()?.parse().map_err(|_| invalid("bad PFM scale"))?;
+
+    let mut data = vec![0u8; width as usize * height as usize * 3 * 4];
+    reader.read_exact(&mut data)?;
+    let floats: Vec<f64> = data
+        .chunks_exact(4)
+        .map(|chunk| {
+            let bytes = chunk.try_into().unwrap();
+            let value = if scale < 0.0 {
+                f32::from_le_bytes(bytes)
+            } else {
+                f32::from_be_bytes(bytes)
+            };
+            value as f64
+        })
+        .collect();
+
+    let mut framebuffer = Framebuffer::new(width, height);
+    for (index, rgb) in floats.chunks_exact(3).enumerate() {
+        let i = index as u32 % width;
+        let j = height - 1 - index as u32 / width;
+        framebuffer.set(i, j, Color::new(rgb[0], rgb[1], rgb[2]));
+    }
+    Ok(framebuffer)
+}
+
+fn rgb8(framebuffer: &Framebuffer, display: &DisplayTransform) -> Vec<u8> {
+    framebuffer
+        .pixels()
+        .iter()
+        .flat_map(|pixel_color| color_to_bytes(pixel_color, display))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient() -> Framebuffer {
+        let mut framebuffer = Framebuffer::new(3, 2);
+        framebuffer.set(0, 0, Color::new(1.0, 0.0, 0.0));
+        framebuffer.set(1, 0, Color::new(0.0, 1.0, 0.0));
+        framebuffer.set(2, 0, Color::new(0.0, 0.0, 1.0));
+        framebuffer.set(0, 1, Color::new(0.25, 0.25, 0.25));
+        framebuffer.set(2, 1, Color::new(2.0, 2.0, 2.0));
+        framebuffer
+    }
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(ImageFormat::from_path("a/out.PNG"), Some(ImageFormat::Png));
+        assert_eq!(ImageFormat::from_path("out.ppm"), Some(ImageFormat::Ppm));
+        assert_eq!(ImageFormat::from_path("out.pfm"), Some(ImageFormat::Pfm));
+        assert_eq!(ImageFormat::from_path("out"), None);
+    }
+
+    #[test]
+    fn test_encode_ppm() {
+        let mut bytes = Vec::new();
+        encode(
+            &gradient(),
+            ImageFormat::Ppm,
+            &DisplayTransform::new(),
+            &mut bytes,
+        )
+        .unwrap();
+
+        let header = b"P6\n3 2\n255\n";
+        assert_eq!(&bytes[..header.len()], header);
+        let body = &bytes[header.len()..];
+        assert_eq!(body.len(), 3 * 2 * 3);
+        assert_eq!(&body[..3], &[255, 0, 0]);
+        // 0.25 is 0.5 after gamma 2, values above one are clamped
+        assert_eq!(&body[9..12], &[128, 128, 128]);
+        assert_eq!(&body[15..18], &[255, 255, 255]);
+    }
+
+    #[test]
+    fn test_encode_ppm_ascii() {
+        let mut bytes = Vec::new();
+        encode(
+            &gradient(),
+            ImageFormat::PpmAscii,
+            &DisplayTransform::new(),
+            &mut bytes,
+        )
+        .unwrap();
+        let text = String::from_utf8(bytes).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines[..3], ["P3", "3 2", "255"]);
+        assert_eq!(lines[3], "255 0 0");
+        assert_eq!(lines.len(), 3 + 6);
+    }
+
+    #[test]
+    fn test_encode_png() {
+        let mut bytes = Vec::new();
+        encode(
+            &gradient(),
+            ImageFormat::Png,
+            &DisplayTransform::new(),
+            &mut bytes,
+        )
+        .unwrap();
+
+        let decoder = png::Decoder::new(bytes.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).unwrap();
+
+        assert_eq!((info.width, info.height), (3, 2));
+        assert_eq!(&data[3..6], &[0, 255, 0]);
+        assert_eq!(&data[9..12], &[128, 128, 128]);
+        // the default square root curve is tagged with gAMA alone
+        assert!(reader.info().srgb.is_none());
+        assert_eq!(reader.info().source_gamma, Some(png::ScaledFloat::new(0.5)));
+
+        let display = DisplayTransform {
+            transfer: Transfer::Srgb,
+            ..DisplayTransform::new()
+        };
+        let mut bytes = Vec::new();
+        encode(&gradient(), ImageFormat::Png, &display, &mut bytes).unwrap();
+        let reader = png::Decoder::new(bytes.as_slice()).read_info().unwrap();
+        assert!(reader.info().srgb.is_some());
+    }
+
+    #[test]
+    fn test_encode_pfm() {
+        let mut bytes = Vec::new();
+        encode(
+            &gradient(),
+            ImageFormat::Pfm,
+            &DisplayTransform::new(),
+            &mut bytes,
+        )
+        .unwrap();
+
+        let header = b"PF\n3 2\n-1.0\n";
+        assert_eq!(&bytes[..header.len()], header);
+        let floats: Vec<f32> = bytes[header.len()..]
+            .chunks(4)
+            .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+
+        assert_eq!(floats.len(), 3 * 2 * 3);
+        // the bottom row comes first and keeps the unclamped linear values
+        assert_eq!(floats[..3], [0.25, 0.25, 0.25]);
+        assert_eq!(floats[6..9], [2.0, 2.0, 2.0]);
+        assert_eq!(floats[9..12], [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_decode_pfm() {
+        let mut bytes = Vec::new();
+        encode(
+            &gradient(),
+            ImageFormat::Pfm,
+            &DisplayTransform::new(),
+            &mut bytes,
+        )
+        .unwrap();
+        assert_eq!(decode_pfm(&mut bytes.as_slice()).unwrap(), gradient());
+
+        assert!(decode_pfm(&mut b"P6\n3 2\n255\n".as_slice()).is_err());
+        assert!(decode_pfm(&mut b"PF\n3 2\n-1.0\n".as_slice()).is_err());
+    }
+}
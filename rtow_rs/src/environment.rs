@@ -0,0 +1,293 @@
+//! Image based lighting: the radiance arriving from every direction read from an
+//! equirectangular map, such as the Radiance HDR skies of the wgpu tutorial.
+//!
+//! The longitude runs across the map from -x through +z, +x in the middle, and the latitude
+//! down it from +y; a panorama rendered looking along +x with vup +y has the same layout.
+//! Along with the map comes a table drawing directions in proportion to their luminance, so
+//! that the bright sun and windows of a sky are found by the light sampling rather than by
+//! chance.
+
+use std::f64::consts::PI;
+use std::path::Path;
+
+use image::DynamicImage;
+
+use crate::color::{luminance, Color};
+use crate::encoder::read_pfm;
+use crate::rtweekend::{random_double, RtRng};
+use crate::vec3::Vec3;
+
+#[derive(Debug, PartialEq)]
+pub struct EnvironmentMap {
+    width: u32,
+    height: u32,
+    /// Linear radiance, row by row from the top
+    pixels: Vec<Color>,
+    /// Running sum of the row weights, down the map
+    marginal: Vec<f64>,
+    /// Running sum of the pixel weights across every row
+    conditional: Vec<f64>,
+}
+
+impl EnvironmentMap {
+    /// Load a Radiance HDR or PFM map of linear radiance; the 8 bit values of a PNG or JPEG
+    /// are converted to linear as for the textures.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, image::ImageError> {
+        let path = path.as_ref();
+        if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("pfm"))
+        {
+            let image = read_pfm(path).map_err(image::ImageError::IoError)?;
+            return Ok(Self::from_pixels(
+                image.width(),
+                image.height(),
+                image.pixels().to_vec(),
+            ));
+        }
+
+        let image = image::open(path)?;
+        let linear = matches!(
+            image,
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+        );
+        let image = image.into_rgb32f();
+        let to_linear = |value: f32| {
+            if linear {
+                value as f64
+            } else {
+                (value as f64).powf(2.2)
+            }
+        };
+        let pixels = image
+            .pixels()
+            .map(|pixel| {
+                let [r, g, b] = pixel.0;
+                Color::new(to_linear(r), to_linear(g), to_linear(b))
+            })
+            .collect();
+        Ok(Self::from_pixels(image.width(), image.height(), pixels))
+    }
+
+    pub fn from_pixels(width: u32, height: u32, pixels: Vec<Color>) -> Self {
+        assert_eq!(pixels.len(), (width * height) as usize);
+        assert!(width > 0 && height > 0, "the map must not be empty");
+
+        // a pixel is drawn in proportion to its luminance and to the solid angle it covers,
+        // which shrinks toward the poles
+        let mut marginal = Vec::with_capacity(height as usize);
+        let mut conditional = Vec::with_capacity(pixels.len());
+        let mut total = 0.0;
+        for (j, row) in pixels.chunks(width as usize).enumerate() {
+            let sin_theta = (PI * (j as f64 + 0.5) / height as f64).sin();
+            let mut row_total = 0.0;
+            for color in row {
+                row_total += luminance(color).max(0.0) * sin_theta;
+                conditional.push(row_total);
+            }
+            total += row_total;
+            marginal.push(total);
+        }
+
+        Self {
+            width,
+            height,
+            pixels,
+            marginal,
+            conditional,
+        }
+    }
+
+    /// The map with its radiance multiplied by `intensity`.
+    pub fn with_intensity(self, intensity: f64) -> Self {
+        let pixels = self.pixels.iter().map(|color| intensity * *color).collect();
+        Self::from_pixels(self.width, self.height, pixels)
+    }
+
+    /// Whether any direction carries light, the others cannot be sampled.
+    pub fn is_black(&self) -> bool {
+        self.total() <= 0.0
+    }
+
+    /// Radiance arriving from `direction`.
+    pub fn value(&self, direction: &Vec3) -> Color {
+        let (i, j) = self.pixel_of(direction);
+        self.pixels[j * self.width as usize + i]
+    }
+
+    /// Density of `direction` per unit solid angle, when drawn by `sample`.
+    pub fn pdf(&self, direction: &Vec3) -> f64 {
+        let unit = direction.unit();
+        let sin_theta = (1.0 - unit.y() * unit.y()).max(0.0).sqrt();
+        if self.is_black() || sin_theta <= 0.0 {
+            return 0.0;
+        }
+
+        let (i, j) = self.pixel_of(&unit);
+        let index = j * self.width as usize + i;
+        let before = if i > 0 {
+            self.conditional[index - 1]
+        } else {
+            0.0
+        };
+        let weight = self.conditional[index] - before;
+        // the pixels cover [0, 1]^2, which maps to the sphere with a jacobian of
+        // 2 pi^2 sin(theta)
+        let pixel_count = (self.width * self.height) as f64;
+        weight * pixel_count / (self.total() * 2.0 * PI * PI * sin_theta)
+    }
+
+    /// Random direction drawn in proportion to the luminance of the map.
+    pub fn sample(&self, rng: &mut RtRng) -> Vec3 {
+        let width = self.width as usize;
+        let target = random_double(rng) * self.total();
+        let j = self
+            .marginal
+            .partition_point(|&sum| sum <= target)
+            .min(self.height as usize - 1);
+        let row = &self.conditional[j * width..(j + 1) * width];
+        let row_total = row[width - 1];
+        let target = random_double(rng) * row_total;
+        let i = row.partition_point(|&sum| sum <= target).min(width - 1);
+
+        let u = (i as f64 + random_double(rng)) / self.width as f64;
+        let v = (j as f64 + random_double(rng)) / self.height as f64;
+        direction_of(u, v)
+    }
+
+    fn total(&self) -> f64 {
+        self.marginal[self.marginal.len() - 1]
+    }
+
+    /// Column and row of the pixel seen in `direction`.
+    fn pixel_of(&self, direction: &Vec3) -> (usize, usize) {
+        let unit = direction.unit();
+        let u = unit.z().atan2(unit.x()) / (2.0 * PI) + 0.5;
+        let v = unit.y().clamp(-1.0, 1.0).acos() / PI;
+        let i = ((u * self.width as f64) as usize).min(self.width as usize - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height as usize - 1);
+        (i, j)
+    }
+}
+
+/// Unit direction at the map coordinates (u, v) in [0, 1]^2, from the upper left.
+fn direction_of(u: f64, v: f64) -> Vec3 {
+    let longitude = 2.0 * PI * (u - 0.5);
+    let latitude = PI * (0.5 - v);
+    Vec3::new(
+        latitude.cos() * longitude.cos(),
+        latitude.sin(),
+        latitude.cos() * longitude.sin(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::{Pdf, SpherePdf};
+    use crate::rtweekend::seeded_rng;
+
+    /// 8x4 dim sky with a bright spot a little above the horizon, toward +z.
+    fn sky() -> EnvironmentMap {
+        let mut pixels = vec![Color::new(0.1, 0.2, 0.4); 32];
+        pixels[8 + 6] = Color::new(100.0, 90.0, 80.0);
+        EnvironmentMap::from_pixels(8, 4, pixels)
+    }
+
+    #[test]
+    fn test_layout() {
+        let map = sky();
+        assert_eq!(
+            map.value(&Vec3::new(0.0, 0.3, 1.0)),
+            Color::new(100.0, 90.0, 80.0)
+        );
+        assert_eq!(
+            map.value(&Vec3::new(0.0, 0.3, -1.0)),
+            Color::new(0.1, 0.2, 0.4)
+        );
+        for (u, v) in [(0.1, 0.2), (0.5, 0.5), (0.93, 0.8)] {
+            let (i, j) = map.pixel_of(&direction_of(u, v));
+            assert_eq!((i, j), ((u * 8.0) as usize, (v * 4.0) as usize));
+        }
+    }
+
+    #[test]
+    fn test_load_hdr() {
+        // Radiance HDR keeps 8 bit mantissas with a shared exponent, powers of two survive
+        let values = [(0.5, 2.0, 16.0), (0.25, 0.0, 1.0)];
+        let rgb: Vec<image::Rgb<f32>> = values
+            .iter()
+            .map(|&(r, g, b)| image::Rgb([r, g, b]))
+            .collect();
+        let path = std::env::temp_dir().join(format!("rtow_sky_{}.hdr", std::process::id()));
+        image::codecs::hdr::HdrEncoder::new(std::fs::File::create(&path).unwrap())
+            .encode(&rgb, 2, 1)
+            .unwrap();
+        let map = EnvironmentMap::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let map = map.unwrap();
+        assert_eq!(
+            map.value(&Vec3::new(-1.0, 0.0, -0.1)),
+            Color::new(0.5, 2.0, 16.0)
+        );
+        assert_eq!(
+            map.value(&Vec3::new(1.0, 0.0, 0.0)),
+            Color::new(0.25, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn test_importance_sampling() {
+        let map = sky();
+        let mut rng = seeded_rng(8);
+
+        // the density integrates to 1 over the sphere, summed over a grid finer than the map
+        let (columns, rows) = (400, 200);
+        let mut integral = 0.0;
+        for j in 0..rows {
+            for i in 0..columns {
+                let (u, v) = (
+                    (i as f64 + 0.5) / columns as f64,
+                    (j as f64 + 0.5) / rows as f64,
+                );
+                let solid_angle = 2.0 * PI * PI * (PI * v).sin() / (columns * rows) as f64;
+                integral += map.pdf(&direction_of(u, v)) * solid_angle;
+            }
+        }
+        assert!((integral - 1.0).abs() < 1e-3, "{integral}");
+
+        // the irradiance of a horizontal surface facing up, estimated by drawing from the
+        // map, matches the uniform estimate
+        let uniform = SpherePdf;
+        let n = 200_000;
+        let irradiance = |d: &Vec3| luminance(&map.value(d)) * d.unit().y().max(0.0);
+        let mut drawn = 0.0;
+        let mut spot = 0;
+        for _ in 0..n {
+            let d = map.sample(&mut rng);
+            drawn += irradiance(&d) / map.pdf(&d);
+            if map.value(&d).x() > 1.0 {
+                spot += 1;
+            }
+        }
+        let uniform_estimate: f64 = (0..n)
+            .map(|_| {
+                let d = uniform.generate(&mut rng);
+                irradiance(&d) / uniform.value(&d)
+            })
+            .sum::<f64>()
+            / n as f64;
+        let drawn = drawn / n as f64;
+        assert!(
+            (drawn - uniform_estimate).abs() < 0.02 * uniform_estimate,
+            "{drawn} != {uniform_estimate}"
+        );
+        // the spot covers 1/32 of the map but gets most of the samples
+        assert!(spot > n * 9 / 10);
+
+        let black = EnvironmentMap::from_pixels(1, 1, vec![Color::new(0.0, 0.0, 0.0)]);
+        assert!(black.is_black());
+        assert_eq!(black.pdf(&Vec3::new(1.0, 0.0, 0.0)), 0.0);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::color::Color;
+
+/// Linear radiance of every pixel of a rendered image, stored row by row from the top.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Framebuffer {
+    width: u32,
+    height: u32,
+    pixels: Vec<Color>,
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Color::default(); (width * height) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Color of the pixel in column i and row j.
+    pub fn get(&self, i: u32, j: u32) -> Color {
+        self.pixels[self.index(i, j)]
+    }
+
+    pub fn set(&mut self, i: u32, j: u32, pixel_color: Color) {
+        let index = self.index(i, j);
+        self.pixels[index] = pixel_color;
+    }
+
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    /// Mutable access to row j.
+    pub fn row_mut(&mut self, j: u32) -> &mut [Color] {
+        let start = self.index(0, j);
+        &mut self.pixels[start..start + self.width as usize]
+    }
+
+    /// Iterate the rows from top to bottom.
+    pub fn rows(&self) -> impl DoubleEndedIterator<Item = &[Color]> {
+        self.pixels.chunks(self.width.max(1) as usize)
+    }
+
+    fn index(&self, i: u32, j: u32) -> usize {
+        assert!(i < self.width && j < self.height, "pixel out of bounds");
+        (j * self.width + i) as usize
+    }
+}
@@ -1,9 +1,11 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::{
+    aabb::Aabb,
     interval::Interval,
     material::Material,
     ray::Ray,
+    rtweekend::RtRng,
     vec3::{Point3, Vec3},
 };
 
@@ -11,9 +13,14 @@ use crate::{
 pub struct HitRecord {
     pub p: Point3,
     pub normal: Vec3,
-    pub mat: Option<Rc<dyn Material>>,
+    pub mat: Option<Arc<dyn Material>>,
     pub t: f64,
+    /// Surface coordinates of the hit point
+    pub u: f64,
+    pub v: f64,
     pub front_face: bool,
+    /// Index of the hit object among the top level objects of the world
+    pub object_id: usize,
 }
 
 impl HitRecord {
@@ -22,22 +29,45 @@ impl HitRecord {
         // NOTE: the parameter `outward_normal` is assumed to have unit length;
         self.front_face = r.direction().dot(outward_normal) < 0.0;
         if self.front_face {
-            self.normal = outward_normal.clone();
+            self.normal = *outward_normal;
         } else {
-            self.normal = -outward_normal.clone();
+            self.normal = -outward_normal;
         }
+    }
+}
+
+pub trait Hittable: Send + Sync {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool;
+
+    fn bounding_box(&self) -> Aabb;
 
-        println!(
-            "r.direction: {:0.5?}, outward_normal: {:0.5?}, dot: {:0.5} front_face: {}, normal: {:0.5?}",
-            r.direction(),
-            outward_normal,
-            r.direction().dot(outward_normal),
-            self.front_face,
-            self.normal,
-        );
+    /// Density, per unit solid angle, of `direction` among the directions from `origin`
+    /// drawn by `random`. Only objects that can be sampled as lights implement it.
+    fn pdf_value(&self, _origin: &Point3, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    /// Random direction from `origin` toward a point of the object.
+    fn random(&self, _origin: &Point3, _rng: &mut RtRng) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
     }
 }
 
-pub trait Hittable {
-    fn hit<'a>(&'a self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool;
+/// A shared object can be placed in a world as is, next to its instances.
+impl<T: Hittable + ?Sized> Hittable for Arc<T> {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        (**self).hit(r, ray_t, rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        (**self).bounding_box()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        (**self).pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut RtRng) -> Vec3 {
+        (**self).random(origin, rng)
+    }
 }
@@ -1,10 +1,13 @@
 use crate::{
+    aabb::Aabb,
     hittable::{HitRecord, Hittable},
     interval::Interval,
     ray::Ray,
+    rtweekend::{random_int, RtRng},
+    vec3::{Point3, Vec3},
 };
 
-pub trait HittableCollection {
+pub trait HittableCollection: Sync {
     fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool;
 }
 
@@ -14,14 +17,63 @@ impl HittableCollection for Vec<Box<dyn Hittable>> {
         let mut closest_so_far = ray_t.max;
         let mut temp_rec: HitRecord = Default::default();
 
-        for object in self {
+        for (index, object) in self.iter().enumerate() {
             if object.hit(r, Interval::new(ray_t.min, closest_so_far), &mut temp_rec) {
                 hit_anything = true;
                 closest_so_far = temp_rec.t;
                 *rec = temp_rec.clone();
+                rec.object_id = index;
             }
         }
 
         hit_anything
     }
 }
+
+/// A group of objects that is itself one `Hittable`, so it can be placed in another world.
+#[derive(Default)]
+pub struct HittableList {
+    pub objects: Vec<Box<dyn Hittable>>,
+    bbox: Aabb,
+}
+
+impl HittableList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, object: Box<dyn Hittable>) {
+        self.bbox = Aabb::surrounding(&self.bbox, &object.bounding_box());
+        self.objects.push(object);
+    }
+}
+
+impl Hittable for HittableList {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        HittableCollection::hit(&self.objects, r, ray_t, rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    /// Every object is picked with the same probability.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let weight = 1.0 / self.objects.len() as f64;
+        self.objects
+            .iter()
+            .map(|object| weight * object.pdf_value(origin, direction))
+            .sum()
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut RtRng) -> Vec3 {
+        let index = random_int(rng, 0, self.objects.len() as i32 - 1) as usize;
+        self.objects[index].random(origin, rng)
+    }
+}
+
+impl HittableCollection for HittableList {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        HittableCollection::hit(&self.objects, r, ray_t, rec)
+    }
+}
@@ -0,0 +1,374 @@
+//! Instances: wrappers placing a shared object somewhere else in the world.
+//!
+//! The wrapped object is held by an `Arc`, so one mesh or box can be placed many times
+//! without copying its geometry. Rays are moved into the object space of the instance and
+//! the hit point and normal are moved back into the world.
+
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    matrix::Mat4,
+    ray::Ray,
+    rtweekend::{degress_to_radians, RtRng},
+    vec3::{Point3, Vec3},
+};
+
+/// The object moved by `offset`.
+pub struct Translate {
+    object: Arc<dyn Hittable>,
+    offset: Vec3,
+    bbox: Aabb,
+}
+
+impl Translate {
+    pub fn new(object: Arc<dyn Hittable>, offset: Vec3) -> Self {
+        let bbox = object.bounding_box() + offset;
+        Self {
+            object,
+            offset,
+            bbox,
+        }
+    }
+}
+
+impl Hittable for Translate {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        // Move the ray backwards by the offset
+        let offset_r = Ray::with_time(*r.origin() - self.offset, *r.direction(), r.time());
+
+        // Determine whether an intersection exists along the offset ray (and if so, where)
+        if !self.object.hit(&offset_r, ray_t, rec) {
+            return false;
+        }
+
+        // Move the intersection point forwards by the offset
+        rec.p += self.offset;
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.object.pdf_value(&(*origin - self.offset), direction)
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut RtRng) -> Vec3 {
+        self.object.random(&(*origin - self.offset), rng)
+    }
+}
+
+/// The object rotated by `angle` degrees around the y axis, counter-clockwise seen from +y.
+pub struct RotateY {
+    object: Arc<dyn Hittable>,
+    sin_theta: f64,
+    cos_theta: f64,
+    bbox: Aabb,
+}
+
+impl RotateY {
+    pub fn new(object: Arc<dyn Hittable>, angle: f64) -> Self {
+        let (sin_theta, cos_theta) = degress_to_radians(angle).sin_cos();
+        let mut rotate_y = Self {
+            object,
+            sin_theta,
+            cos_theta,
+            bbox: Aabb::empty(),
+        };
+
+        let corners = rotate_y.object.bounding_box().corners();
+        rotate_y.bbox = Aabb::enclosing_points(&corners.map(|corner| rotate_y.to_world(&corner)));
+        rotate_y
+    }
+
+    fn to_object(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v.x() - self.sin_theta * v.z(),
+            v.y(),
+            self.sin_theta * v.x() + self.cos_theta * v.z(),
+        )
+    }
+
+    fn to_world(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v.x() + self.sin_theta * v.z(),
+            v.y(),
+            -self.sin_theta * v.x() + self.cos_theta * v.z(),
+        )
+    }
+}
+
+impl Hittable for RotateY {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        // Transform the ray from world space to object space.
+        let rotated_r = Ray::with_time(
+            self.to_object(r.origin()),
+            self.to_object(r.direction()),
+            r.time(),
+        );
+
+        // Determine whether an intersection exists in object space (and if so, where).
+        if !self.object.hit(&rotated_r, ray_t, rec) {
+            return false;
+        }
+
+        // Transform the intersection from object space back to world space.
+        rec.p = self.to_world(&rec.p);
+        rec.normal = self.to_world(&rec.normal);
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        // rotations keep solid angles
+        self.object
+            .pdf_value(&self.to_object(origin), &self.to_object(direction))
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut RtRng) -> Vec3 {
+        self.to_world(&self.object.random(&self.to_object(origin), rng))
+    }
+}
+
+/// The object moved by any invertible affine transform: rotation around any axis, scaling,
+/// mirroring, shearing and translation, composed in one matrix.
+pub struct Transform {
+    object: Arc<dyn Hittable>,
+    /// Object to world
+    matrix: Mat4,
+    /// World to object
+    inverse: Mat4,
+    /// Turns object normals into world normals
+    normal_matrix: Mat4,
+    bbox: Aabb,
+}
+
+impl Transform {
+    /// Returns `None` when the matrix is not invertible.
+    pub fn new(object: Arc<dyn Hittable>, matrix: Mat4) -> Option<Self> {
+        let inverse = matrix.inverse_affine()?;
+        let corners = object.bounding_box().corners();
+        let bbox = Aabb::enclosing_points(&corners.map(|corner| matrix.transform_point(&corner)));
+
+        Some(Self {
+            object,
+            matrix,
+            inverse,
+            normal_matrix: inverse.transpose(),
+            bbox,
+        })
+    }
+
+    /// Rotation of `angle` degrees around `axis` through the origin.
+    pub fn rotate(object: Arc<dyn Hittable>, axis: Vec3, angle: f64) -> Self {
+        Self::new(object, Mat4::rotation(axis, angle)).expect("rotations are invertible")
+    }
+
+    /// Scaling by `factor` along each axis from the origin, `None` if one factor is zero.
+    pub fn scale(object: Arc<dyn Hittable>, factor: Vec3) -> Option<Self> {
+        Self::new(object, Mat4::scaling(factor))
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        // The direction is not normalized, so t is the same in both spaces.
+        let object_r = Ray::with_time(
+            self.inverse.transform_point(r.origin()),
+            self.inverse.transform_vector(r.direction()),
+            r.time(),
+        );
+
+        if !self.object.hit(&object_r, ray_t, rec) {
+            return false;
+        }
+
+        // Normals follow the inverse transpose, which keeps them facing against the ray.
+        rec.p = self.matrix.transform_point(&rec.p);
+        rec.normal = self.normal_matrix.transform_vector(&rec.normal).unit();
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let object_direction = self.inverse.transform_vector(direction);
+        let object_pdf = self
+            .object
+            .pdf_value(&self.inverse.transform_point(origin), &object_direction);
+
+        // The matrix maps the unit direction w to A w / |A w|, which stretches solid angles
+        // by |det A| / |A w|^3.
+        let stretch = self
+            .matrix
+            .transform_vector(&object_direction.unit())
+            .length();
+        object_pdf * stretch.powi(3) / self.matrix.determinant3().abs()
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut RtRng) -> Vec3 {
+        let object_direction = self
+            .object
+            .random(&self.inverse.transform_point(origin), rng);
+        self.matrix.transform_vector(&object_direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::Lambertian;
+    use crate::quad::{make_box, Quad};
+    use crate::rtweekend::seeded_rng;
+    use crate::sphere::Sphere;
+
+    fn unit_box() -> Arc<dyn Hittable> {
+        Arc::new(make_box(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 1.0),
+            Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        ))
+    }
+
+    fn hit(object: &dyn Hittable, origin: Point3, direction: Vec3) -> Option<HitRecord> {
+        let mut rec = HitRecord::default();
+        let r = Ray::new(origin, direction);
+        object
+            .hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec)
+            .then_some(rec)
+    }
+
+    #[test]
+    fn test_instances_share_geometry() {
+        let geometry = unit_box();
+        let moved = Translate::new(geometry.clone(), Vec3::new(10.0, 0.0, 0.0));
+        let turned = RotateY::new(geometry.clone(), 90.0);
+        assert_eq!(Arc::strong_count(&geometry), 3);
+
+        let rec = hit(
+            &moved,
+            Point3::new(10.5, 0.5, 5.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        )
+        .unwrap();
+        assert!((rec.p - Point3::new(10.5, 0.5, 1.0)).length() < 1e-12);
+        assert!(hit(
+            &moved,
+            Point3::new(0.5, 0.5, 5.0),
+            Vec3::new(0.0, 0.0, -1.0)
+        )
+        .is_none());
+
+        // turned a quarter, the box now spans x in [0, 1] and z in [-1, 0]
+        let bbox = turned.bounding_box();
+        assert!((bbox.z.min + 1.0).abs() < 1e-3 && bbox.z.max.abs() < 1e-3);
+        let rec = hit(
+            &turned,
+            Point3::new(0.5, 0.5, 5.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        )
+        .unwrap();
+        assert!(rec.p.z().abs() < 1e-12);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-12);
+    }
+
+    #[test]
+    fn test_transform_matches_rotate_y() {
+        let geometry = unit_box();
+        let rotate_y = RotateY::new(geometry.clone(), 30.0);
+        let transform = Transform::rotate(geometry, Vec3::new(0.0, 1.0, 0.0), 30.0);
+
+        let mut rng = seeded_rng(2);
+        for _ in 0..200 {
+            let origin = Point3::random(&mut rng, -3.0, 3.0) + Vec3::new(0.0, 0.0, 6.0);
+            let direction = Point3::random(&mut rng, 0.0, 1.0) - origin;
+            match (
+                hit(&rotate_y, origin, direction),
+                hit(&transform, origin, direction),
+            ) {
+                (Some(a), Some(b)) => {
+                    assert!((a.t - b.t).abs() < 1e-9);
+                    assert!((a.normal - b.normal).length() < 1e-9);
+                }
+                (a, b) => assert_eq!(a.is_some(), b.is_some()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_scaled_normals() {
+        let sphere: Arc<dyn Hittable> = Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        ));
+        // an ellipsoid with semi-axes 4, 1, 1
+        let ellipsoid = Transform::scale(sphere, Vec3::new(4.0, 1.0, 1.0)).unwrap();
+
+        let rec = hit(
+            &ellipsoid,
+            Point3::new(10.0, 0.0, 0.0),
+            Vec3::new(-1.0, 0.0, 0.0),
+        )
+        .unwrap();
+        assert!((rec.t - 6.0).abs() < 1e-9);
+
+        // on x^2/16 + y^2 = 1 the normal is along (x/16, y)
+        let x = 2.0;
+        let y = (1.0 - x * x / 16.0f64).sqrt();
+        let rec = hit(
+            &ellipsoid,
+            Point3::new(x, 5.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+        )
+        .unwrap();
+        assert!((rec.p - Point3::new(x, y, 0.0)).length() < 1e-9);
+        assert!((rec.normal - Vec3::new(x / 16.0, y, 0.0).unit()).length() < 1e-9);
+        assert!(rec.front_face);
+    }
+
+    #[test]
+    fn test_transformed_light_pdf() {
+        let quad: Arc<dyn Hittable> = Arc::new(Quad::new(
+            Point3::new(-0.5, 0.0, -0.5),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        ));
+        let matrix = Mat4::translation(Vec3::new(0.3, 2.0, 0.1))
+            * Mat4::rotation(Vec3::new(1.0, 0.0, 0.4), 20.0)
+            * Mat4::scaling(Vec3::new(2.0, 1.0, 0.5));
+        let light = Transform::new(quad, matrix).unwrap();
+
+        // drawing directions from the light and dividing by their density estimates the
+        // solid angle it covers, which is also the fraction of uniform directions hitting it
+        let mut rng = seeded_rng(4);
+        let origin = Point3::new(0.0, 0.0, 0.0);
+        let n = 100_000;
+        let by_light: f64 = (0..n)
+            .map(|_| 1.0 / light.pdf_value(&origin, &light.random(&origin, &mut rng)))
+            .sum::<f64>()
+            / n as f64;
+        let by_sphere = (0..n)
+            .filter(|_| hit(&light, origin, Vec3::random_unit_vector(&mut rng)).is_some())
+            .count() as f64
+            * 4.0
+            * std::f64::consts::PI
+            / n as f64;
+
+        assert!(
+            (by_light - by_sphere).abs() < 0.03 * by_sphere,
+            "{by_light} {by_sphere}"
+        );
+    }
+}
@@ -0,0 +1,593 @@
+//! Integrators turn a camera ray into the color of its sample. The path tracer computes the
+//! full light transport; the others show one part of it, to find which stage of a wrong
+//! looking render is at fault.
+
+use crate::background::Background;
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::hittable_list::{HittableCollection, HittableList};
+use crate::interval::Interval;
+use crate::material::ScatterRecord;
+use crate::pdf::{CosinePdf, EnvironmentPdf, HittablePdf, MixturePdf, Pdf};
+use crate::ray::Ray;
+use crate::rtweekend::{random_double, RtRng};
+use crate::vec3::Vec3;
+
+/// What an integrator sees of the scene being rendered.
+pub struct SceneView<'a> {
+    pub world: &'a dyn HittableCollection,
+    /// Copies of the emissive objects of the world that are sampled directly, may be empty
+    pub lights: &'a HittableList,
+    pub background: &'a Background,
+    /// Most bounces of a path
+    pub max_depth: i32,
+}
+
+impl SceneView<'_> {
+    /// Closest hit of `r` in the world.
+    pub fn hit(&self, r: &Ray) -> Option<HitRecord> {
+        let mut rec: HitRecord = Default::default();
+        self.world
+            .hit(r, Interval::new(0.001, f64::INFINITY), &mut rec)
+            .then_some(rec)
+    }
+}
+
+pub trait Integrator: Send + Sync {
+    /// Color of the sample carried by the camera ray `r`.
+    fn radiance(&self, r: &Ray, scene: &SceneView, rng: &mut RtRng) -> Color;
+}
+
+/// Draw the next direction of a path at a hit whose material has a density: half of the
+/// directions toward the lights and the environment map, weighted against the material
+/// density by the mixture PDF; with neither only from the material. None when the drawn
+/// direction has no density.
+fn sample_scatter(
+    r: &Ray,
+    rec: &HitRecord,
+    surface_pdf: &dyn Pdf,
+    scene: &SceneView,
+    rng: &mut RtRng,
+) -> Option<(Ray, f64)> {
+    let light_pdf = HittablePdf::new(scene.lights, rec.p);
+    let environment_pdf = scene.background.environment().map(EnvironmentPdf::new);
+    let emitters_pdf;
+    let emitters: Option<&dyn Pdf> = match (&environment_pdf, scene.lights.objects.is_empty()) {
+        (None, true) => None,
+        (None, false) => Some(&light_pdf),
+        (Some(environment_pdf), true) => Some(environment_pdf),
+        (Some(environment_pdf), false) => {
+            emitters_pdf = MixturePdf::new(&light_pdf, environment_pdf);
+            Some(&emitters_pdf)
+        }
+    };
+    let mixture_pdf;
+    let p: &dyn Pdf = match emitters {
+        None => surface_pdf,
+        Some(emitters) => {
+            mixture_pdf = MixturePdf::new(emitters, surface_pdf);
+            &mixture_pdf
+        }
+    };
+
+    let scattered =
+        Ray::with_time(rec.p, p.generate(rng), r.time()).with_wavelength(r.wavelength());
+    let pdf_value = p.value(scattered.direction());
+    (pdf_value > 0.0).then_some((scattered, pdf_value))
+}
+
+/// Emitted light or background reaching the origin of `r`, without any further bounce.
+fn incoming_emission(r: &Ray, scene: &SceneView) -> Color {
+    let emission = match scene.hit(r) {
+        None => scene.background.value(r.direction()),
+        Some(rec) => rec.mat.as_ref().map_or(Color::default(), |mat| {
+            mat.emitted(r, &rec, rec.u, rec.v, &rec.p)
+        }),
+    };
+    r.wavelength().weight() * emission
+}
+
+/// Unidirectional path tracing with light sampling at the diffuse bounces, the reference
+/// solution of the rendering equation.
+///
+/// Paths are followed in a loop carrying their throughput, the product of the weights of their
+/// bounces. They end at `max_depth` bounces, which loses the light of the longer paths, or with
+/// Russian roulette: past a minimum depth each bounce survives with a probability following
+/// the throughput and the survivors are weighted up by its inverse, which keeps the estimate
+/// unbiased.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PathTracer {
+    /// Bounces after which the paths play Russian roulette instead of stopping at the
+    /// `max_depth` of the scene, None for a fixed depth
+    pub roulette_depth: Option<i32>,
+}
+
+impl PathTracer {
+    /// Highest survival probability of the roulette, so that paths between perfect mirrors
+    /// end too.
+    const MAX_SURVIVAL: f64 = 0.95;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Paths of any length, ended by Russian roulette after `min_depth` bounces.
+    pub fn with_roulette(min_depth: i32) -> Self {
+        Self {
+            roulette_depth: Some(min_depth),
+        }
+    }
+}
+
+impl Integrator for PathTracer {
+    fn radiance(&self, r: &Ray, scene: &SceneView, rng: &mut RtRng) -> Color {
+        let mut radiance = Color::new(0.0, 0.0, 0.0);
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray =
+            Ray::with_time(*r.origin(), *r.direction(), r.time()).with_wavelength(r.wavelength());
+
+        let mut depth = 0;
+        loop {
+            if self.roulette_depth.is_none() && depth >= scene.max_depth {
+                break;
+            }
+
+            // If the ray hits nothing, add the background color.
+            let Some(rec) = scene.hit(&ray) else {
+                radiance += throughput
+                    * ray.wavelength().weight()
+                    * scene.background.value(ray.direction());
+                break;
+            };
+            let Some(mat) = rec.mat.clone() else {
+                break;
+            };
+
+            // the light reaching a dispersive path takes the colors of its wavelengths
+            radiance += throughput
+                * ray.wavelength().weight()
+                * mat.emitted(&ray, &rec, rec.u, rec.v, &rec.p);
+
+            let mut srec = ScatterRecord::default();
+            if !mat.scatter(&ray, &rec, &mut srec, rng) {
+                break;
+            }
+
+            match srec.pdf.take() {
+                None => {
+                    throughput = throughput * srec.attenuation;
+                    ray = srec.skip_pdf_ray;
+                }
+                Some(surface_pdf) => {
+                    let Some((scattered, pdf_value)) =
+                        sample_scatter(&ray, &rec, surface_pdf.as_ref(), scene, rng)
+                    else {
+                        break;
+                    };
+                    let scattering = mat.scattering(&ray, &rec, &srec, &scattered);
+                    throughput = throughput * scattering / pdf_value;
+                    ray = scattered;
+                }
+            }
+            depth += 1;
+
+            if self
+                .roulette_depth
+                .is_some_and(|min_depth| depth >= min_depth)
+            {
+                // the spectral weight of dispersive paths, which may be negative, stays out of
+                // the throughput
+                let largest = throughput.x().max(throughput.y()).max(throughput.z());
+                let survival = largest.min(Self::MAX_SURVIVAL);
+                if random_double(rng) >= survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+        }
+
+        radiance
+    }
+}
+
+/// Light reaching the eye after at most one diffuse or glossy bounce: the emitters and the
+/// background, their reflections in mirrors and glass, and the direct lighting of the other
+/// surfaces. Missing light here but not in the path tracer is indirect lighting.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DirectLighting;
+
+impl DirectLighting {
+    fn trace(&self, r: &Ray, depth: i32, scene: &SceneView, rng: &mut RtRng) -> Color {
+        if depth <= 0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        let Some(rec) = scene.hit(r) else {
+            return r.wavelength().weight() * scene.background.value(r.direction());
+        };
+        let Some(mat) = rec.mat.clone() else {
+            return Color::new(0.0, 0.0, 0.0);
+        };
+
+        let color_from_emission =
+            r.wavelength().weight() * mat.emitted(r, &rec, rec.u, rec.v, &rec.p);
+
+        let mut srec = ScatterRecord::default();
+        if !mat.scatter(r, &rec, &mut srec, rng) {
+            return color_from_emission;
+        }
+
+        // specular chains are followed until they reach a surface that can be lit
+        let Some(surface_pdf) = srec.pdf.take() else {
+            let color_from_scatter =
+                srec.attenuation * self.trace(&srec.skip_pdf_ray, depth - 1, scene, rng);
+            return color_from_emission + color_from_scatter;
+        };
+
+        let Some((scattered, pdf_value)) =
+            sample_scatter(r, &rec, surface_pdf.as_ref(), scene, rng)
+        else {
+            return color_from_emission;
+        };
+
+        let scattering = mat.scattering(r, &rec, &srec, &scattered);
+        color_from_emission + (scattering * incoming_emission(&scattered, scene)) / pdf_value
+    }
+}
+
+impl Integrator for DirectLighting {
+    fn radiance(&self, r: &Ray, scene: &SceneView, rng: &mut RtRng) -> Color {
+        self.trace(r, scene.max_depth, scene, rng)
+    }
+}
+
+/// Shading normal of the first hit facing the ray, mapped from [-1, 1] to [0, 1]; black for
+/// the background.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Normals;
+
+impl Integrator for Normals {
+    fn radiance(&self, r: &Ray, scene: &SceneView, _rng: &mut RtRng) -> Color {
+        match scene.hit(r) {
+            None => Color::new(0.0, 0.0, 0.0),
+            Some(rec) => 0.5 * (rec.normal + Vec3::new(1.0, 1.0, 1.0)),
+        }
+    }
+}
+
+/// Reflectance of the material at the first hit, black for the background.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Albedo;
+
+impl Integrator for Albedo {
+    fn radiance(&self, r: &Ray, scene: &SceneView, _rng: &mut RtRng) -> Color {
+        scene
+            .hit(r)
+            .and_then(|rec| rec.mat.as_ref().map(|mat| mat.albedo(&rec)))
+            .unwrap_or_default()
+    }
+}
+
+/// Fraction of the hemisphere above the first hit, weighted by the cosine, that is open up
+/// to `distance`: white in the open, darker in the creases. The background is white.
+#[derive(Debug, Clone, Copy)]
+pub struct AmbientOcclusion {
+    pub distance: f64,
+}
+
+impl AmbientOcclusion {
+    pub fn new(distance: f64) -> Self {
+        Self { distance }
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn radiance(&self, r: &Ray, scene: &SceneView, rng: &mut RtRng) -> Color {
+        let Some(rec) = scene.hit(r) else {
+            return Color::new(1.0, 1.0, 1.0);
+        };
+
+        let direction = CosinePdf::new(&rec.normal).generate(rng);
+        let probe = Ray::with_time(rec.p, direction, r.time());
+        let mut blocker: HitRecord = Default::default();
+        if scene
+            .world
+            .hit(&probe, Interval::new(0.001, self.distance), &mut blocker)
+        {
+            Color::new(0.0, 0.0, 0.0)
+        } else {
+            Color::new(1.0, 1.0, 1.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::environment::EnvironmentMap;
+    use crate::hittable::Hittable;
+    use crate::material::{DiffuseLight, Lambertian, Material};
+    use crate::quad::{make_box, Quad};
+    use crate::rtweekend::seeded_rng;
+    use crate::sphere::Sphere;
+    use crate::stats::RunningStats;
+    use crate::vec3::Point3;
+
+    /// Mean of the samples of `integrator` along `r`.
+    fn mean(integrator: &dyn Integrator, r: &Ray, scene: &SceneView, n: usize) -> Color {
+        let mut rng = seeded_rng(9);
+        let sum = (0..n)
+            .map(|_| integrator.radiance(r, scene, &mut rng))
+            .fold(Color::default(), |sum, c| sum + c);
+        sum / n as f64
+    }
+
+    #[test]
+    fn test_direct_lighting_misses_only_indirect_light() {
+        // a floor under a lamp: a path can only bounce once before leaving, so direct lighting
+        // is the whole transport; a wall next to it adds indirect light to the floor
+        let white = Arc::new(Lambertian::new(Color::new(0.7, 0.7, 0.7)));
+        let lamp = || -> Box<dyn Hittable> {
+            Box::new(Quad::new(
+                Point3::new(-1.0, 2.0, -1.0),
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, 2.0),
+                Arc::new(DiffuseLight::new(Color::new(5.0, 5.0, 5.0))),
+            ))
+        };
+        let floor = || -> Box<dyn Hittable> {
+            Box::new(Quad::new(
+                Point3::new(-5.0, 0.0, -5.0),
+                Vec3::new(10.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, 10.0),
+                white.clone(),
+            ))
+        };
+        let wall = Box::new(Quad::new(
+            Point3::new(1.2, 0.0, -5.0),
+            Vec3::new(0.0, 0.0, 10.0),
+            Vec3::new(0.0, 3.0, 0.0),
+            white.clone(),
+        ));
+        let mut lights = HittableList::new();
+        lights.add(lamp());
+        let background = Background::None;
+        let r = Ray::new(Point3::new(0.0, 1.0, 3.0), Vec3::new(0.0, -1.0, -3.0));
+        let n = 20_000;
+
+        let open: Vec<Box<dyn Hittable>> = vec![floor(), lamp()];
+        let scene = SceneView {
+            world: &open,
+            lights: &lights,
+            background: &background,
+            max_depth: 10,
+        };
+        let direct = mean(&DirectLighting, &r, &scene, n);
+        let path = mean(&PathTracer::new(), &r, &scene, n);
+        assert!(
+            (direct - path).length() < 0.02 * path.length(),
+            "{direct:?} {path:?}"
+        );
+
+        let walled: Vec<Box<dyn Hittable>> = vec![floor(), wall, lamp()];
+        let scene = SceneView {
+            world: &walled,
+            ..scene
+        };
+        let direct = mean(&DirectLighting, &r, &scene, n);
+        let path = mean(&PathTracer::new(), &r, &scene, n);
+        assert!(direct.x() < 0.95 * path.x(), "{direct:?} {path:?}");
+    }
+
+    #[test]
+    fn test_russian_roulette_is_unbiased() {
+        // inside a closed white box lit by a small lamp, where light bounces around for long
+        let white = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8)));
+        let lamp = || -> Box<dyn Hittable> {
+            Box::new(Quad::new(
+                Point3::new(-0.2, 0.99, -0.2),
+                Vec3::new(0.4, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, 0.4),
+                Arc::new(DiffuseLight::new(Color::new(10.0, 10.0, 10.0))),
+            ))
+        };
+        let walls = make_box(
+            Point3::new(-1.0, -1.0, -1.0),
+            Point3::new(1.0, 1.0, 1.0),
+            white,
+        );
+        let world: Vec<Box<dyn Hittable>> = vec![Box::new(walls), lamp()];
+        let mut lights = HittableList::new();
+        lights.add(lamp());
+        let background = Background::None;
+        let r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.3, -1.0, 0.2));
+
+        let estimate = |integrator: PathTracer, max_depth: i32| {
+            let scene = SceneView {
+                world: &world,
+                lights: &lights,
+                background: &background,
+                max_depth,
+            };
+            let mut rng = seeded_rng(23);
+            let mut stats = RunningStats::new();
+            for _ in 0..100_000 {
+                stats.push(integrator.radiance(&r, &scene, &mut rng).x());
+            }
+            stats
+        };
+
+        let deep = estimate(PathTracer::new(), 200);
+        let roulette = estimate(PathTracer::with_roulette(3), 0);
+        let shallow = estimate(PathTracer::new(), 3);
+
+        // the roulette agrees with the deep paths within the noise, short paths miss light
+        let sigma = (deep.standard_error().powi(2) + roulette.standard_error().powi(2)).sqrt();
+        assert!(
+            (roulette.mean() - deep.mean()).abs() < 4.0 * sigma,
+            "roulette {} deep {} sigma {sigma}",
+            roulette.mean(),
+            deep.mean()
+        );
+        assert!(
+            shallow.mean() < deep.mean() - 4.0 * sigma,
+            "shallow {} deep {}",
+            shallow.mean(),
+            deep.mean()
+        );
+    }
+
+    #[test]
+    fn test_visualizers() {
+        let world: Vec<Box<dyn Hittable>> = vec![Box::new(Sphere::new(
+            Point3::new(0.0, 0.0, -2.0),
+            1.0,
+            Arc::new(Lambertian::new(Color::new(0.2, 0.4, 0.6))),
+        ))];
+        let lights = HittableList::new();
+        let background = Background::default();
+        let scene = SceneView {
+            world: &world,
+            lights: &lights,
+            background: &background,
+            max_depth: 10,
+        };
+        let mut rng = seeded_rng(1);
+        let hit = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let miss = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+
+        let normal = Normals.radiance(&hit, &scene, &mut rng);
+        assert!((normal - Color::new(0.5, 0.5, 1.0)).length() < 1e-9);
+        assert_eq!(Normals.radiance(&miss, &scene, &mut rng), Color::default());
+        assert_eq!(
+            Albedo.radiance(&hit, &scene, &mut rng),
+            Color::new(0.2, 0.4, 0.6)
+        );
+
+        // nothing but the sphere itself around: every probe of the front is open
+        let ao = AmbientOcclusion::new(10.0);
+        assert_eq!(mean(&ao, &hit, &scene, 100), Color::new(1.0, 1.0, 1.0));
+        // inside the sphere every probe is blocked
+        let inside = Ray::new(Point3::new(0.0, 0.0, -2.0), Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(mean(&ao, &inside, &scene, 100), Color::default());
+    }
+
+    #[test]
+    fn test_environment_sampling_is_unbiased() {
+        // a gray floor under a dim sky with a small sun, 30 degrees above the horizon
+        let (width, height) = (32, 16);
+        let mut pixels = vec![Color::new(0.2, 0.3, 0.5); width * height];
+        pixels[5 * width + 20] = Color::new(400.0, 380.0, 350.0);
+        let map = EnvironmentMap::from_pixels(width as u32, height as u32, pixels);
+
+        // irradiance of the floor by quadrature over the upper half of the map
+        let (columns, rows) = (640, 160);
+        let mut irradiance = Color::default();
+        for j in 0..rows {
+            for i in 0..columns {
+                let u = (i as f64 + 0.5) / columns as f64;
+                let theta = PI / 2.0 * (j as f64 + 0.5) / rows as f64;
+                let direction = Vec3::new(
+                    theta.sin() * (2.0 * PI * u).cos(),
+                    theta.cos(),
+                    theta.sin() * (2.0 * PI * u).sin(),
+                );
+                let solid_angle = 2.0 * PI * PI / 2.0 * theta.sin() / (columns * rows) as f64;
+                irradiance += map.value(&direction) * theta.cos() * solid_angle;
+            }
+        }
+        let expected = 0.5 / PI * irradiance.x();
+
+        let world: Vec<Box<dyn Hittable>> = vec![Box::new(Quad::new(
+            Point3::new(-1000.0, 0.0, -1000.0),
+            Vec3::new(2000.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 2000.0),
+            Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        ))];
+        let lights = HittableList::new();
+        let background = Background::Environment(Arc::new(map));
+        let scene = SceneView {
+            world: &world,
+            lights: &lights,
+            background: &background,
+            max_depth: 2,
+        };
+        let r = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+
+        let mut rng = seeded_rng(31);
+        let mut stats = RunningStats::new();
+        for _ in 0..20_000 {
+            stats.push(PathTracer::new().radiance(&r, &scene, &mut rng).x());
+        }
+        assert!(
+            (stats.mean() - expected).abs() < 4.0 * stats.standard_error(),
+            "{} != {expected}",
+            stats.mean()
+        );
+        // the sun is found by the sampling rather than by chance, which leaves ten times the
+        // noise
+        assert!(stats.standard_error() < 0.02 * expected);
+    }
+
+    #[test]
+    fn test_light_sampling_is_unbiased() {
+        let white: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let lamp: Arc<dyn Material> = Arc::new(DiffuseLight::new(Color::new(4.0, 4.0, 4.0)));
+        let panel = || {
+            Quad::new(
+                Point3::new(-0.5, 1.0, -0.5),
+                Vec3::new(0.0, 0.0, 1.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                lamp.clone(),
+            )
+        };
+        let bulb = || Sphere::new(Point3::new(1.0, 0.8, 0.5), 0.3, lamp.clone());
+
+        let world: Vec<Box<dyn Hittable>> = vec![
+            Box::new(Quad::new(
+                Point3::new(-5.0, 0.0, -5.0),
+                Vec3::new(0.0, 0.0, 10.0),
+                Vec3::new(10.0, 0.0, 0.0),
+                white,
+            )),
+            Box::new(panel()),
+            Box::new(bulb()),
+        ];
+        let mut lights = HittableList::new();
+        lights.add(Box::new(panel()));
+        lights.add(Box::new(bulb()));
+
+        let background = Background::None;
+
+        // mean radiance of the floor seen from above, with and without sampling the lights
+        let estimate = |target: Point3, lights: &HittableList| {
+            let mut rng = seeded_rng(17);
+            let n = 200_000;
+            let mut sum = Color::default();
+            for _ in 0..n {
+                let origin = target + Vec3::new(0.0, 0.5, 0.2);
+                let r = Ray::new(origin, target - origin);
+                let scene = SceneView {
+                    world: &world,
+                    lights,
+                    background: &background,
+                    max_depth: 2,
+                };
+                sum += PathTracer::new().radiance(&r, &scene, &mut rng);
+            }
+            sum.x() / n as f64
+        };
+
+        for target in [Point3::new(0.0, 0.0, 0.0), Point3::new(1.5, 0.0, -0.7)] {
+            let reference = estimate(target, &HittableList::new());
+            let sampled = estimate(target, &lights);
+            assert!(
+                (sampled - reference).abs() < 0.02 * reference,
+                "{target:?}: light sampling {sampled}, brute force {reference}"
+            );
+        }
+    }
+}
@@ -1,5 +1,4 @@
-use std::f64::INFINITY;
-
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Interval {
     pub min: f64,
     pub max: f64,
@@ -10,12 +9,17 @@ impl Interval {
         Interval { min, max }
     }
 
+    /// Create the interval tightly enclosing the two input intervals.
+    pub fn enclosing(a: &Interval, b: &Interval) -> Self {
+        Interval::new(a.min.min(b.min), a.max.max(b.max))
+    }
+
     pub fn empty() -> Self {
-        Interval::new(INFINITY, -INFINITY)
+        Interval::new(f64::INFINITY, -f64::INFINITY)
     }
 
     pub fn universe() -> Self {
-        Interval::new(-INFINITY, INFINITY)
+        Interval::new(-f64::INFINITY, f64::INFINITY)
     }
 
     pub fn size(&self) -> f64 {
@@ -30,6 +34,11 @@ impl Interval {
         self.min < x && x < self.max
     }
 
+    pub fn expand(&self, delta: f64) -> Interval {
+        let padding = delta / 2.0;
+        Interval::new(self.min - padding, self.max + padding)
+    }
+
     pub fn clamp(&self, x: f64) -> f64 {
         if x < self.min {
             return self.min;
@@ -39,6 +48,6 @@ impl Interval {
             return self.max;
         }
 
-        return x;
+        x
     }
 }
@@ -0,0 +1,35 @@
+pub mod aabb;
+pub mod aov;
+pub mod aperture;
+pub mod background;
+pub mod bvh;
+pub mod camera;
+pub mod color;
+pub mod constant_medium;
+pub mod denoise;
+pub mod encoder;
+pub mod environment;
+pub mod framebuffer;
+pub mod hittable;
+pub mod hittable_list;
+pub mod instance;
+pub mod integrator;
+pub mod interval;
+pub mod material;
+pub mod matrix;
+pub mod mesh;
+pub mod microfacet;
+pub mod onb;
+pub mod pdf;
+pub mod perlin;
+pub mod quad;
+pub mod ray;
+pub mod rtweekend;
+pub mod scene;
+pub mod scenes;
+pub mod spectrum;
+pub mod sphere;
+pub mod stats;
+pub mod texture;
+pub mod triangle;
+pub mod vec3;
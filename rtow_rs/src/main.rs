@@ -1,109 +1,558 @@
-use std::rc::Rc;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
 use std::time::Instant;
 
-use camera::Camera;
-use color::Color;
-use hittable::Hittable;
-use material::{Dielectric, Lambertian, Material, Metal};
-use rtweekend::{random_double, random_double_range};
-use sphere::Sphere;
-use vec3::{Point3, Vec3};
-
-mod camera;
-mod color;
-mod hittable;
-mod hittable_list;
-mod interval;
-mod material;
-mod ray;
-mod rtweekend;
-mod sphere;
-mod vec3;
-
-fn main() {
-    let mut camera = Camera::new();
-    let mut world: Vec<Box<dyn Hittable>> = Vec::new();
-
-    let ground_material = Rc::new(Lambertian {
-        albedo: Color::new(0.5, 0.5, 0.5),
-    });
-    world.push(Box::new(Sphere::new(
-        Point3::new(0.0, -1000.0, 0.0),
-        1000.0,
-        ground_material,
-    )));
-
-    // for a in -11..11 {
-    //     for b in -11..11 {
-    //         let choose_mat = random_double();
-    //         let center = Point3::new(
-    //             (a as f64) + 0.9 * random_double(),
-    //             0.2,
-    //             (b as f64) + 0.9 * random_double(),
-    //         );
-
-    //         if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
-    //             let sphere_material: Rc<dyn Material>;
-    //             if choose_mat < 0.8 {
-    //                 let albedo: Color = Color::random_random() * Color::random_random();
-    //                 sphere_material = Rc::new(Lambertian { albedo });
-    //             } else if choose_mat < 0.95 {
-    //                 let albedo = Color::random(0.5, 1.0);
-    //                 let fuzz = random_double_range(0.0, 0.5);
-    //                 sphere_material = Rc::new(Metal { albedo, fuzz });
-    //             } else {
-    //                 sphere_material = Rc::new(Dielectric {
-    //                     refraction_index: 1.5,
-    //                 });
-    //             }
-    //             world.push(Box::new(Sphere::new(center, 0.2, sphere_material)));
-    //         }
-    //     }
-    // }
-
-    let material1 = Rc::new(Dielectric {
-        refraction_index: 1.5,
-    });
-    world.push(Box::new(Sphere::new(
-        Point3::new(0.0, 1.0, 0.0),
-        1.0,
-        material1,
-    )));
-
-    // let material2 = Rc::new(Lambertian {
-    //     albedo: Color::new(0.4, 0.2, 0.1),
-    // });
-    // world.push(Box::new(Sphere::new(
-    //     Point3::new(-4.0, 1.0, 0.0),
-    //     1.0,
-    //     material2,
-    // )));
-
-    // let material3 = Rc::new(Metal {
-    //     albedo: Color::new(0.7, 0.6, 0.5),
-    //     fuzz: 0.0,
-    // });
-    // world.push(Box::new(Sphere::new(
-    //     Point3::new(4.0, 1.0, 0.0),
-    //     1.0,
-    //     material3,
-    // )));
-
-    camera.aspect_ratio = 16.0 / 9.0;
-    camera.image_width = 1200;
-    camera.samples_per_pixel = 16;
-    camera.max_depth = 8;
-    camera.vfov = 20.0;
-    camera.lookfrom = Point3::new(12.0, 2.0, 3.0);
-    camera.lookat = Point3::new(0.0, 0.0, 0.0);
-    camera.vup = Vec3::new(0.0, 1.0, 0.0);
-
-    camera.defocus_angle = 0.6;
-    camera.focus_dist = 10.0;
+use clap::{Parser, ValueEnum};
+
+use rtow_rs::aov::Aov;
+use rtow_rs::background::Background;
+use rtow_rs::bvh::{Bvh, SplitStrategy};
+use rtow_rs::color::{DisplayTransform, ToneMap, Transfer};
+use rtow_rs::denoise::{psnr, Denoiser};
+use rtow_rs::encoder::{read_pfm, write_image, ImageFormat};
+use rtow_rs::environment::EnvironmentMap;
+use rtow_rs::hittable::Hittable;
+use rtow_rs::hittable_list::HittableList;
+use rtow_rs::integrator::{
+    Albedo, AmbientOcclusion, DirectLighting, Integrator, Normals, PathTracer,
+};
+use rtow_rs::scene::{load_scene, Scene};
+use rtow_rs::scenes;
+use rtow_rs::vec3::Vec3;
+
+/// Render a scene with the CPU ray tracer of Ray Tracing in One Weekend.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// Scene description file (TOML), takes precedence over --demo
+    #[arg(short, long)]
+    scene: Option<PathBuf>,
+
+    /// Built-in demo scene rendered when no scene file is given
+    #[arg(short, long, value_enum, default_value_t = Demo::Cover)]
+    demo: Demo,
+
+    /// Output image path
+    #[arg(short, long, default_value = "output.ppm")]
+    output: PathBuf,
+
+    /// Output image format, guessed from the output extension when omitted
+    #[arg(short, long, value_enum)]
+    format: Option<Format>,
+
+    /// Exposure change of the image in stops, before tone mapping
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    exposure: f64,
+
+    /// How the radiance above 1 is brought into the displayable range
+    #[arg(long, value_enum, default_value_t = ToneMapping::Clamp)]
+    tone_map: ToneMapping,
+
+    /// Transfer function of the 8 bit formats: `srgb`, or the gamma of a plain power curve
+    #[arg(long, value_parser = parse_transfer, default_value = "2")]
+    transfer: Transfer,
+
+    /// Also write the linear image as PFM, to encode it again later with --from-pfm
+    #[arg(long)]
+    hdr_output: Option<PathBuf>,
+
+    /// Encode this linear PFM image into the output instead of rendering a scene
+    #[arg(long)]
+    from_pfm: Option<PathBuf>,
+
+    /// Rendered image width in pixels
+    #[arg(short = 'w', long, value_parser = clap::value_parser!(u32).range(1..))]
+    image_width: Option<u32>,
+
+    /// Ratio of image width over height, such as 1.5 or 16/9
+    #[arg(short, long, value_parser = parse_ratio)]
+    aspect_ratio: Option<f64>,
+
+    /// Count of random samples for each pixel
+    #[arg(long, visible_alias = "spp", value_parser = clap::value_parser!(u32).range(1..))]
+    samples_per_pixel: Option<u32>,
+
+    /// Sample pixels adaptively until the standard error of their luminance, relative to their
+    /// mean, gets under this threshold; --spp is then the size of a batch of samples
+    #[arg(long, value_parser = parse_noise_threshold)]
+    noise_threshold: Option<f64>,
+
+    /// Most samples an adaptive pixel may take
+    #[arg(long, visible_alias = "max-spp")]
+    max_samples_per_pixel: Option<u32>,
+
+    /// Also write a heatmap of the samples taken by every pixel, in the output format
+    #[arg(long)]
+    spp_heatmap: Option<PathBuf>,
+
+    /// First hit passes written next to the output as linear PFM, <stem>.<pass>.pfm
+    #[arg(long, value_enum, value_delimiter = ',')]
+    aov: Vec<Pass>,
+
+    /// Also write the passes normalized for viewing in the output format, as
+    /// <stem>.<pass>.preview.<extension>
+    #[arg(long, requires = "aov")]
+    aov_preview: bool,
+
+    /// Filter the noise out of the image, guided by its albedo and normal passes
+    #[arg(long)]
+    denoise: bool,
+
+    /// High sample count render (PFM) to print the PSNR of the image against
+    #[arg(long)]
+    reference: Option<PathBuf>,
+
+    /// What the samples compute, the other integrators than `path` help debugging a render
+    #[arg(long, value_enum, default_value_t = IntegratorKind::Path)]
+    integrator: IntegratorKind,
+
+    /// Reach of the ambient occlusion probes, a tenth of the scene size when omitted
+    #[arg(long, value_parser = parse_distance)]
+    ao_distance: Option<f64>,
+
+    /// Maximum number of ray bounces into scene
+    #[arg(long, value_parser = clap::value_parser!(i32).range(0..))]
+    max_depth: Option<i32>,
+
+    /// End the paths by Russian roulette after this many bounces, instead of at --max-depth
+    #[arg(long, value_name = "MIN_DEPTH")]
+    russian_roulette: Option<u32>,
+
+    /// Seed of the render, also used to generate the random demo scenes
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Number of worker threads, 0 uses one thread per core
+    #[arg(short = 'j', long)]
+    threads: Option<usize>,
+
+    /// How the BVH splits its nodes
+    #[arg(long, value_enum, default_value_t = Split::Sah)]
+    bvh: Split,
+
+    /// Only sample the materials, not the lights of the scene: a slow but simple reference
+    #[arg(long)]
+    no_light_sampling: bool,
+
+    /// Light the scene with this equirectangular HDR or PFM map in place of its background
+    #[arg(long)]
+    environment: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum Demo {
+    /// The RTOW final scene with the random sphere grid
+    Cover,
+    /// The RTOW final scene with motion blurred bouncing spheres, RTNW chapter 2
+    BouncingSpheres,
+    /// The three material spheres of RTOW chapter 11
+    Materials,
+    /// Five colored quads of RTNW chapter 6
+    Quads,
+    /// Two spheres with a checker texture, RTNW chapter 4
+    CheckeredSpheres,
+    /// Perlin noise marble spheres, RTNW chapter 5
+    PerlinSpheres,
+    /// Marble spheres lit by area lights in the dark, RTNW chapter 7
+    SimpleLight,
+    /// The Cornell box, RTNW chapter 8
+    CornellBox,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum Format {
+    /// ASCII PPM (P3)
+    PpmAscii,
+    /// Binary PPM (P6)
+    Ppm,
+    Png,
+    /// Linear float PFM
+    Pfm,
+}
+
+impl From<Format> for ImageFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::PpmAscii => ImageFormat::PpmAscii,
+            Format::Ppm => ImageFormat::Ppm,
+            Format::Png => ImageFormat::Png,
+            Format::Pfm => ImageFormat::Pfm,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum ToneMapping {
+    /// Clip the values above 1
+    Clamp,
+    /// x / (1 + x)
+    Reinhard,
+    /// Fit of the ACES filmic curve
+    Aces,
+    /// Hable's Uncharted 2 curve
+    Filmic,
+}
+
+impl From<ToneMapping> for ToneMap {
+    fn from(tone_mapping: ToneMapping) -> Self {
+        match tone_mapping {
+            ToneMapping::Clamp => ToneMap::Clamp,
+            ToneMapping::Reinhard => ToneMap::Reinhard,
+            ToneMapping::Aces => ToneMap::Aces,
+            ToneMapping::Filmic => ToneMap::Filmic,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum IntegratorKind {
+    /// Full path tracing
+    Path,
+    /// Light after at most one diffuse bounce
+    Direct,
+    /// Shading normals of the first hit
+    Normals,
+    /// Reflectance of the first hit
+    Albedo,
+    /// Ambient occlusion of the first hit
+    Ao,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum Pass {
+    /// Reflectance of the surfaces
+    Albedo,
+    /// Shading normals, mapped to colors
+    Normal,
+    /// Distance to the camera, relative to the farthest surface
+    Depth,
+    /// A color for each object of the scene
+    ObjectId,
+    /// A color for each material
+    MaterialId,
+    /// Every pass above
+    All,
+}
+
+impl Pass {
+    fn aovs(self) -> &'static [Aov] {
+        match self {
+            Pass::Albedo => &[Aov::Albedo],
+            Pass::Normal => &[Aov::Normal],
+            Pass::Depth => &[Aov::Depth],
+            Pass::ObjectId => &[Aov::ObjectId],
+            Pass::MaterialId => &[Aov::MaterialId],
+            Pass::All => &Aov::ALL,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum Split {
+    /// Median split along a random axis (RTNW 3.9)
+    Random,
+    /// Median split along the longest axis (RTNW 3.10)
+    Longest,
+    /// Surface area heuristic
+    Sah,
+}
+
+impl From<Split> for SplitStrategy {
+    fn from(split: Split) -> Self {
+        match split {
+            Split::Random => SplitStrategy::RandomAxis,
+            Split::Longest => SplitStrategy::LongestAxis,
+            Split::Sah => SplitStrategy::Sah,
+        }
+    }
+}
+
+fn parse_ratio(s: &str) -> Result<f64, String> {
+    let ratio = match s.split_once(['/', ':']) {
+        Some((w, h)) => {
+            let w: f64 = w
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid width `{w}`"))?;
+            let h: f64 = h
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid height `{h}`"))?;
+            w / h
+        }
+        None => s.parse().map_err(|_| format!("invalid ratio `{s}`"))?,
+    };
+
+    if ratio.is_finite() && ratio > 0.0 {
+        Ok(ratio)
+    } else {
+        Err(format!("ratio must be positive, got `{s}`"))
+    }
+}
+
+fn parse_noise_threshold(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(threshold) if threshold.is_finite() && threshold >= 0.0 => Ok(threshold),
+        _ => Err(format!("expected a threshold of at least 0, got `{s}`")),
+    }
+}
+
+fn parse_distance(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(distance) if distance.is_finite() && distance > 0.0 => Ok(distance),
+        _ => Err(format!("expected a positive distance, got `{s}`")),
+    }
+}
+
+fn parse_transfer(s: &str) -> Result<Transfer, String> {
+    if s.eq_ignore_ascii_case("srgb") {
+        return Ok(Transfer::Srgb);
+    }
+    match s.parse::<f64>() {
+        Ok(gamma) if gamma.is_finite() && gamma > 0.0 => Ok(Transfer::Gamma(gamma)),
+        _ => Err(format!("expected `srgb` or a positive gamma, got `{s}`")),
+    }
+}
+
+/// `output` with the name of an AOV in place of its extension, the data is always PFM and the
+/// preview keeps the extension of the output.
+fn aov_path(output: &Path, aov: Aov, preview: bool) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match (preview, output.extension()) {
+        (false, _) => format!("{stem}.{}.pfm", aov.name()),
+        (true, Some(extension)) => format!(
+            "{stem}.{}.preview.{}",
+            aov.name(),
+            extension.to_string_lossy()
+        ),
+        (true, None) => format!("{stem}.{}.preview", aov.name()),
+    };
+    output.with_file_name(name)
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let format = match args.format {
+        Some(format) => format.into(),
+        None => match ImageFormat::from_path(&args.output) {
+            Some(format) => format,
+            None => {
+                eprintln!(
+                    "cannot guess the image format of {}, use --format",
+                    args.output.display()
+                );
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+
+    let display = DisplayTransform {
+        exposure: args.exposure,
+        tone_map: args.tone_map.into(),
+        transfer: args.transfer,
+    };
+
+    if let Some(path) = &args.from_pfm {
+        let framebuffer = match read_pfm(path) {
+            Ok(framebuffer) => framebuffer,
+            Err(err) => {
+                eprintln!("cannot read {}: {err}", path.display());
+                return ExitCode::FAILURE;
+            }
+        };
+        if let Err(err) = write_image(&framebuffer, &args.output, format, &display) {
+            eprintln!("cannot write {}: {err}", args.output.display());
+            return ExitCode::FAILURE;
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let scene = match &args.scene {
+        Some(path) => match load_scene(path) {
+            Ok(scene) => scene,
+            Err(err) => {
+                eprintln!("{}: {err}", path.display());
+                return ExitCode::FAILURE;
+            }
+        },
+        None => {
+            let seed = args.seed.unwrap_or(0);
+            match args.demo {
+                Demo::Cover => scenes::cover(seed),
+                Demo::BouncingSpheres => scenes::bouncing_spheres(seed),
+                Demo::Materials => scenes::materials(seed),
+                Demo::Quads => scenes::quads(seed),
+                Demo::CheckeredSpheres => scenes::checkered_spheres(seed),
+                Demo::PerlinSpheres => scenes::perlin_spheres(seed),
+                Demo::SimpleLight => scenes::simple_light(seed),
+                Demo::CornellBox => scenes::cornell_box(seed),
+            }
+        }
+    };
+
+    let Scene {
+        mut camera,
+        objects,
+        mut lights,
+    } = scene;
+    if let Some(image_width) = args.image_width {
+        camera.image_width = image_width;
+    }
+    if let Some(aspect_ratio) = args.aspect_ratio {
+        camera.aspect_ratio = aspect_ratio;
+    }
+    if let Some(samples_per_pixel) = args.samples_per_pixel {
+        camera.samples_per_pixel = samples_per_pixel;
+    }
+    if let Some(noise_threshold) = args.noise_threshold {
+        camera.noise_threshold = noise_threshold;
+    }
+    if let Some(max_samples_per_pixel) = args.max_samples_per_pixel {
+        camera.max_samples_per_pixel = max_samples_per_pixel;
+    }
+    if let Some(max_depth) = args.max_depth {
+        camera.max_depth = max_depth;
+    }
+    if let Some(seed) = args.seed {
+        camera.seed = seed;
+    }
+    if let Some(threads) = args.threads {
+        camera.threads = threads;
+    }
+    // the overrides may undo what the scene loader checked
+    if camera.noise_threshold > 0.0 && camera.max_samples_per_pixel < camera.samples_per_pixel {
+        eprintln!(
+            "--max-samples-per-pixel must be at least --samples-per-pixel, got {} < {}",
+            camera.max_samples_per_pixel, camera.samples_per_pixel
+        );
+        return ExitCode::FAILURE;
+    }
+
+    if let Some(path) = &args.environment {
+        match EnvironmentMap::load(path) {
+            Ok(map) => camera.background = Background::Environment(Arc::new(map)),
+            Err(err) => {
+                eprintln!("cannot load {}: {err}", path.display());
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    camera.render_aovs = args.denoise || !args.aov.is_empty();
+
+    if args.no_light_sampling {
+        lights = HittableList::new();
+    }
+
+    let world = Bvh::new(objects, args.bvh.into());
+
+    // the path tracer of the scene is kept unless the roulette is asked for
+    let integrator: Option<Box<dyn Integrator>> = match args.integrator {
+        IntegratorKind::Path => args
+            .russian_roulette
+            .map(|min_depth| Box::new(PathTracer::with_roulette(min_depth as i32)) as _),
+        IntegratorKind::Direct => Some(Box::new(DirectLighting)),
+        IntegratorKind::Normals => Some(Box::new(Normals)),
+        IntegratorKind::Albedo => Some(Box::new(Albedo)),
+        IntegratorKind::Ao => {
+            let distance = args.ao_distance.unwrap_or_else(|| {
+                let bbox = world.bounding_box();
+                let diagonal = Vec3::new(bbox.x.size(), bbox.y.size(), bbox.z.size());
+                0.1 * diagonal.length()
+            });
+            Some(Box::new(AmbientOcclusion::new(distance)))
+        }
+    };
+    if let Some(integrator) = integrator {
+        camera.integrator = integrator;
+    }
+
+    let reference = match &args.reference {
+        Some(path) => match read_pfm(path) {
+            Ok(reference) => Some(reference),
+            Err(err) => {
+                eprintln!("cannot read {}: {err}", path.display());
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
 
     let start = Instant::now();
-    camera.render(&world);
+    let mut framebuffer = camera.render(&world, &lights);
     let duration = start.elapsed();
 
+    if let Some(reference) = &reference {
+        if (reference.width(), reference.height()) != (framebuffer.width(), framebuffer.height()) {
+            eprintln!("the reference is not the size of the image");
+            return ExitCode::FAILURE;
+        }
+        println!("psnr: {:.2} dB", psnr(&framebuffer, reference));
+    }
+    if args.denoise {
+        let aovs = camera
+            .aovs()
+            .expect("the passes are rendered for the denoiser");
+        framebuffer = Denoiser::new().denoise(&framebuffer, aovs);
+        if let Some(reference) = &reference {
+            println!("denoised psnr: {:.2} dB", psnr(&framebuffer, reference));
+        }
+    }
+
+    if let Err(err) = write_image(&framebuffer, &args.output, format, &display) {
+        eprintln!("cannot write {}: {err}", args.output.display());
+        return ExitCode::FAILURE;
+    }
+    if let Some(path) = &args.hdr_output {
+        if let Err(err) = write_image(&framebuffer, path, ImageFormat::Pfm, &display) {
+            eprintln!("cannot write {}: {err}", path.display());
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Some(path) = &args.spp_heatmap {
+        if let Err(err) = write_image(
+            &camera.sample_heatmap(),
+            path,
+            format,
+            &DisplayTransform::new(),
+        ) {
+            eprintln!("cannot write {}: {err}", path.display());
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Some(aovs) = camera.aovs() {
+        let mut written = Vec::new();
+        for &aov in args.aov.iter().flat_map(|pass| pass.aovs()) {
+            if written.contains(&aov) {
+                continue;
+            }
+            written.push(aov);
+            let path = aov_path(&args.output, aov, false);
+            if let Err(err) = write_image(
+                &aovs.data(aov),
+                &path,
+                ImageFormat::Pfm,
+                &DisplayTransform::new(),
+            ) {
+                eprintln!("cannot write {}: {err}", path.display());
+                return ExitCode::FAILURE;
+            }
+            if args.aov_preview {
+                let path = aov_path(&args.output, aov, true);
+                if let Err(err) =
+                    write_image(&aovs.preview(aov), &path, format, &DisplayTransform::new())
+                {
+                    eprintln!("cannot write {}: {err}", path.display());
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+    }
+
     println!("escape time: {} ms", duration.as_millis());
+    ExitCode::SUCCESS
 }
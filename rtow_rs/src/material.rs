@@ -1,109 +1,669 @@
+use std::f64::consts::PI;
 use std::fmt::Debug;
+use std::sync::Arc;
 
-use crate::{color::Color, hittable::HitRecord, ray::Ray, rtweekend::random_double, vec3::Vec3};
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    microfacet::{fresnel_conductor, Ggx, GgxPdf},
+    onb::Onb,
+    pdf::{CosinePdf, Pdf, SpherePdf},
+    ray::Ray,
+    rtweekend::{random_double, RtRng},
+    spectrum::{Dispersion, SpectralSample, Wavelength, LAMBDA_D, SPECTRAL_SAMPLES},
+    texture::{SolidColor, Texture},
+    vec3::{Point3, Vec3},
+};
 
-pub trait Material: Debug {
+/// How a material scatters an incoming ray.
+#[derive(Default)]
+pub struct ScatterRecord {
+    pub attenuation: Color,
+    /// Density of the scattered directions; `None` for the specular materials, whose single
+    /// direction cannot be importance sampled, they follow `skip_pdf_ray` instead.
+    pub pdf: Option<Box<dyn Pdf>>,
+    pub skip_pdf_ray: Ray,
+}
+
+pub trait Material: Debug + Send + Sync {
+    /// Light given off by the surface at (u, v, p), nothing by default.
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecord, _u: f64, _v: f64, _p: &Point3) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    /// Reflectance of the surface at the hit, for the albedo pass; black for the materials
+    /// that do not scatter.
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    /// Returns false when the ray is absorbed.
     fn scatter(
+        &self,
+        _r_in: &Ray,
+        _rec: &HitRecord,
+        _srec: &mut ScatterRecord,
+        _rng: &mut RtRng,
+    ) -> bool {
+        false
+    }
+
+    /// Density of the material scattering `r_in` into `scattered`, the BRDF times the cosine
+    /// divided by the attenuation.
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        0.0
+    }
+
+    /// BSDF times the cosine of `scattered`, what the surface sends back along `r_in` of the
+    /// light arriving along `scattered`. The default is the attenuation scaled by
+    /// `scattering_pdf`; materials whose color depends on the directions override it.
+    fn scattering(
         &self,
         r_in: &Ray,
-        rec: &mut HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool;
+        rec: &HitRecord,
+        srec: &ScatterRecord,
+        scattered: &Ray,
+    ) -> Color {
+        srec.attenuation * self.scattering_pdf(r_in, rec, scattered)
+    }
+
+    /// Number of the material in its scene, reported by the material id pass; 0 when the
+    /// material was not numbered.
+    fn id(&self) -> u32 {
+        0
+    }
+}
+
+/// A material under the number its scene gave it, such as its index in the material table,
+/// so that the material id pass matches across renders of the scene.
+#[derive(Debug)]
+pub struct Numbered {
+    id: u32,
+    mat: Arc<dyn Material>,
+}
+
+impl Numbered {
+    pub fn new(id: u32, mat: Arc<dyn Material>) -> Self {
+        Self { id, mat }
+    }
+}
+
+impl Material for Numbered {
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: &Point3) -> Color {
+        self.mat.emitted(r_in, rec, u, v, p)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.mat.albedo(rec)
+    }
+
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        srec: &mut ScatterRecord,
+        rng: &mut RtRng,
+    ) -> bool {
+        self.mat.scatter(r_in, rec, srec, rng)
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.mat.scattering_pdf(r_in, rec, scattered)
+    }
+
+    fn scattering(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        srec: &ScatterRecord,
+        scattered: &Ray,
+    ) -> Color {
+        self.mat.scattering(r_in, rec, srec, scattered)
+    }
+
+    fn id(&self) -> u32 {
+        self.id
+    }
 }
 
 #[derive(Debug)]
 pub struct Lambertian {
-    pub albedo: Color,
+    pub tex: Arc<dyn Texture>,
+}
+
+impl Lambertian {
+    pub fn new(albedo: Color) -> Self {
+        Self::textured(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn textured(tex: Arc<dyn Texture>) -> Self {
+        Self { tex }
+    }
 }
 
 impl Material for Lambertian {
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.tex.value(rec.u, rec.v, &rec.p)
+    }
+
     fn scatter(
         &self,
-        r_in: &Ray,
-        rec: &mut HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
+        _r_in: &Ray,
+        rec: &HitRecord,
+        srec: &mut ScatterRecord,
+        _rng: &mut RtRng,
     ) -> bool {
-        let mut scatter_direction = rec.normal + Vec3::random_unit_vector();
-        if scatter_direction.neal_zero() {
-            scatter_direction = rec.normal;
-        }
+        srec.attenuation = self.tex.value(rec.u, rec.v, &rec.p);
+        srec.pdf = Some(Box::new(CosinePdf::new(&rec.normal)));
+        true
+    }
 
-        *scattered = Ray::new(rec.p, scatter_direction);
-        *attenuation = self.albedo;
-        return true;
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let cos_theta = rec.normal.dot(&scattered.direction().unit());
+        if cos_theta < 0.0 {
+            0.0
+        } else {
+            cos_theta / PI
+        }
     }
 }
 
 #[derive(Debug)]
 pub struct Dielectric {
+    /// Index of refraction at the d line, of every wavelength without a dispersion
     pub refraction_index: f64,
+    pub dispersion: Option<Dispersion>,
 }
 
 impl Dielectric {
+    pub fn new(refraction_index: f64) -> Self {
+        Self {
+            refraction_index,
+            dispersion: None,
+        }
+    }
+
+    /// Glass whose index depends on the wavelength, it splits white light into a rainbow.
+    pub fn dispersive(dispersion: Dispersion) -> Self {
+        Self {
+            refraction_index: dispersion.index(LAMBDA_D),
+            dispersion: Some(dispersion),
+        }
+    }
+
     pub fn reflectance(&self, cosine: f64) -> f64 {
-        let r0: f64 = (1.0 - self.refraction_index) / (1.0 + self.refraction_index);
-        let r0 = r0 * r0;
-        return r0 + (1.0 - r0) * (1.0 - cosine).powi(5);
+        schlick(cosine, self.refraction_index)
+    }
+}
+
+/// Schlick's approximation of the Fresnel reflectance of a dielectric.
+fn schlick(cosine: f64, refraction_index: f64) -> f64 {
+    let r0: f64 = (1.0 - refraction_index) / (1.0 + refraction_index);
+    let r0 = r0 * r0;
+    r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
+}
+
+/// Share of the light reflected by a dielectric of `refraction_index`, all of it past the
+/// critical angle.
+fn reflectance(cosine: f64, refraction_index: f64, front_face: bool) -> f64 {
+    let ri = if front_face {
+        1.0 / refraction_index
+    } else {
+        refraction_index
+    };
+    if ri * ri * (1.0 - cosine * cosine) > 1.0 {
+        1.0
+    } else {
+        schlick(cosine, refraction_index)
     }
 }
 
 impl Material for Dielectric {
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
+
     fn scatter(
         &self,
         r_in: &Ray,
-        rec: &mut HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
+        rec: &HitRecord,
+        srec: &mut ScatterRecord,
+        rng: &mut RtRng,
     ) -> bool {
-        *attenuation = Color::new(1.0, 1.0, 1.0);
-        let ri: f64;
-        if rec.front_face {
-            ri = 1.0 / self.refraction_index;
+        srec.attenuation = Color::new(1.0, 1.0, 1.0);
+        srec.pdf = None;
+
+        // a dispersive glass splits the colors of the path, the hero wavelength bends it
+        let spectral = match (self.dispersion, r_in.wavelength()) {
+            (Some(dispersion), Wavelength::Hero(lambda)) => {
+                Some((dispersion, SpectralSample::new(lambda)))
+            }
+            (Some(dispersion), Wavelength::Spectral(sample)) => Some((dispersion, sample)),
+            _ => None,
+        };
+        let refraction_index = match spectral {
+            Some((dispersion, sample)) => dispersion.index(sample.hero()),
+            None => self.refraction_index,
+        };
+        let ri: f64 = if rec.front_face {
+            1.0 / refraction_index
         } else {
-            ri = self.refraction_index;
-        }
+            refraction_index
+        };
 
         let unit_direction: Vec3 = r_in.direction().unit();
         let cos_theta: f64 = (-unit_direction).dot(&rec.normal).min(1.0);
         let sin_theta: f64 = (1.0 - cos_theta * cos_theta).sqrt();
 
-        println!("ri: {ri}, cos_theta: {cos_theta}, sin_theta: {sin_theta}");
-
         let cannot_refract: bool = ri * sin_theta > 1.0;
-        let direction: Vec3;
-
-        if cannot_refract || self.reflectance(cos_theta) > random_double() {
-            direction = unit_direction.reflect(&rec.normal);
+        let reflects = cannot_refract || schlick(cos_theta, refraction_index) > random_double(rng);
+        let direction: Vec3 = if reflects {
+            unit_direction.reflect(&rec.normal)
         } else {
-            direction = unit_direction.refract(&rec.normal, ri);
-        }
+            unit_direction.refract(&rec.normal, ri)
+        };
+
+        // each wavelength reflects along the hero with its own reflectance, and refracts
+        // elsewhere but for the hero; the choice scatters the light in proportion to its density
+        let wavelength = match spectral {
+            None => r_in.wavelength(),
+            Some((dispersion, sample)) => {
+                let reflectance = sample
+                    .lambdas
+                    .map(|lambda| reflectance(cos_theta, dispersion.index(lambda), rec.front_face));
+                let event = if reflects {
+                    reflectance
+                } else {
+                    let mut refracted = [0.0; SPECTRAL_SAMPLES];
+                    refracted[0] = 1.0 - reflectance[0];
+                    refracted
+                };
+                Wavelength::Spectral(sample.scatter(event, event))
+            }
+        };
 
-        *scattered = Ray::new(rec.p, direction);
+        srec.skip_pdf_ray =
+            Ray::with_time(rec.p, direction, r_in.time()).with_wavelength(wavelength);
 
-        return true;
+        true
     }
 }
 
 #[derive(Debug)]
 pub struct Metal {
-    pub albedo: Color,
+    pub tex: Arc<dyn Texture>,
     pub fuzz: f64,
 }
 
+impl Metal {
+    pub fn new(albedo: Color, fuzz: f64) -> Self {
+        Self::textured(Arc::new(SolidColor::new(albedo)), fuzz)
+    }
+
+    pub fn textured(tex: Arc<dyn Texture>, fuzz: f64) -> Self {
+        Self { tex, fuzz }
+    }
+}
+
 impl Material for Metal {
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.tex.value(rec.u, rec.v, &rec.p)
+    }
+
     fn scatter(
         &self,
         r_in: &Ray,
-        rec: &mut HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
+        rec: &HitRecord,
+        srec: &mut ScatterRecord,
+        rng: &mut RtRng,
     ) -> bool {
         let mut reflected: Vec3 = r_in.direction().reflect(&rec.normal);
-        reflected = reflected.unit() + (self.fuzz * Vec3::random_unit_vector());
-        *scattered = Ray::new(rec.p, reflected);
-        *attenuation = self.albedo;
-        scattered.direction().dot(&rec.normal) > 0.0
+        reflected = reflected.unit() + (self.fuzz * Vec3::random_unit_vector(rng));
+
+        srec.attenuation = self.tex.value(rec.u, rec.v, &rec.p);
+        srec.pdf = None;
+        srec.skip_pdf_ray =
+            Ray::with_time(rec.p, reflected, r_in.time()).with_wavelength(r_in.wavelength());
+        // fuzzed below the surface, absorbed
+        reflected.dot(&rec.normal) > 0.0
+    }
+}
+
+/// Rough metal with a GGX microfacet BRDF and the Fresnel reflectance of its complex index of
+/// refraction `eta + i k`, given for the red, green and blue wavelengths.
+#[derive(Debug)]
+pub struct Conductor {
+    pub eta: Color,
+    pub k: Color,
+    pub ggx: Ggx,
+}
+
+impl Conductor {
+    /// `roughness` in [0, 1], 0 is a mirror.
+    pub fn new(eta: Color, k: Color, roughness: f64) -> Self {
+        Self {
+            eta,
+            k,
+            ggx: Ggx::from_roughness(roughness),
+        }
+    }
+
+    /// One of the metals `aluminium`, `copper`, `gold` or `silver`.
+    pub fn named(metal: &str, roughness: f64) -> Option<Self> {
+        let (eta, k) = match metal {
+            "aluminium" => ([1.657, 0.880, 0.521], [9.224, 6.270, 4.837]),
+            "copper" => ([0.200, 0.924, 1.102], [3.912, 2.452, 2.142]),
+            "gold" => ([0.143, 0.374, 1.442], [3.983, 2.385, 1.603]),
+            "silver" => ([0.155, 0.117, 0.138], [4.828, 3.122, 2.147]),
+            _ => return None,
+        };
+        Some(Self::new(
+            Color::new(eta[0], eta[1], eta[2]),
+            Color::new(k[0], k[1], k[2]),
+            roughness,
+        ))
+    }
+
+    pub fn fresnel(&self, cos_theta: f64) -> Color {
+        Color::new(
+            fresnel_conductor(cos_theta, self.eta.x(), self.k.x()),
+            fresnel_conductor(cos_theta, self.eta.y(), self.k.y()),
+            fresnel_conductor(cos_theta, self.eta.z(), self.k.z()),
+        )
+    }
+}
+
+impl Material for Conductor {
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        self.fresnel(1.0)
+    }
+
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        srec: &mut ScatterRecord,
+        _rng: &mut RtRng,
+    ) -> bool {
+        // the color at normal incidence, the actual reflectance depends on the directions
+        srec.attenuation = self.fresnel(1.0);
+        srec.pdf = Some(Box::new(GgxPdf::new(
+            &rec.normal,
+            &-r_in.direction(),
+            self.ggx,
+        )));
+        true
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let uvw = Onb::new(&rec.normal);
+        let wo = uvw.to_local(&-r_in.direction().unit());
+        let wi = uvw.to_local(&scattered.direction().unit());
+        self.ggx.reflection_pdf(&wo, &wi)
+    }
+
+    fn scattering(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        _srec: &ScatterRecord,
+        scattered: &Ray,
+    ) -> Color {
+        let uvw = Onb::new(&rec.normal);
+        let wo = uvw.to_local(&-r_in.direction().unit());
+        let wi = uvw.to_local(&scattered.direction().unit());
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        let h = (wo + wi).unit();
+        let d = self.ggx.d(&h);
+        let g = self.ggx.g2(&wo, &wi);
+        self.fresnel(wo.dot(&h)) * (d * g / (4.0 * wo.z()))
+    }
+}
+
+/// Area light: emits its texture color from its front face and absorbs every ray.
+#[derive(Debug)]
+pub struct DiffuseLight {
+    pub tex: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Color) -> Self {
+        Self::textured(Arc::new(SolidColor::new(emit)))
+    }
+
+    pub fn textured(tex: Arc<dyn Texture>) -> Self {
+        Self { tex }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn emitted(&self, _r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: &Point3) -> Color {
+        if !rec.front_face {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        self.tex.value(u, v, p)
+    }
+}
+
+/// Phase function of a participating medium, scatters evenly in every direction.
+#[derive(Debug)]
+pub struct Isotropic {
+    pub tex: Arc<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Color) -> Self {
+        Self::textured(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn textured(tex: Arc<dyn Texture>) -> Self {
+        Self { tex }
+    }
+}
+
+impl Material for Isotropic {
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.tex.value(rec.u, rec.v, &rec.p)
+    }
+
+    fn scatter(
+        &self,
+        _r_in: &Ray,
+        rec: &HitRecord,
+        srec: &mut ScatterRecord,
+        _rng: &mut RtRng,
+    ) -> bool {
+        srec.attenuation = self.tex.value(rec.u, rec.v, &rec.p);
+        srec.pdf = Some(Box::new(SpherePdf));
+        true
+    }
+
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtweekend::seeded_rng;
+    use crate::spectrum::wavelength_weight;
+
+    #[test]
+    fn test_lambertian_pdf_matches_scattering_pdf() {
+        let mut rng = seeded_rng(1);
+        let mat = Lambertian::new(Color::new(0.5, 0.5, 0.5));
+        let rec = HitRecord {
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            front_face: true,
+            ..Default::default()
+        };
+        let r_in = Ray::new(Point3::new(0.0, 1.0, 1.0), Vec3::new(0.0, -1.0, -1.0));
+
+        let mut srec = ScatterRecord::default();
+        assert!(mat.scatter(&r_in, &rec, &mut srec, &mut rng));
+        let pdf = srec.pdf.unwrap();
+        for _ in 0..100 {
+            let scattered = Ray::new(rec.p, pdf.generate(&mut rng));
+            let expected = mat.scattering_pdf(&r_in, &rec, &scattered);
+            assert!((pdf.value(scattered.direction()) - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_conductor_white_furnace() {
+        // a perfect reflector under a uniform white sky: single scattering loses the energy of
+        // the masked reflections at high roughness but can never create any
+        let mut rng = seeded_rng(2);
+        let rec = HitRecord {
+            normal: Vec3::new(0.0, 0.0, 1.0),
+            front_face: true,
+            ..Default::default()
+        };
+        for roughness in [0.1, 0.5, 1.0] {
+            let mat = Conductor::new(
+                Color::new(1.0, 1.0, 1.0),
+                Color::new(1e6, 1e6, 1e6),
+                roughness,
+            );
+            for theta in [0.0f64, 60.0] {
+                let theta = theta.to_radians();
+                let r_in = Ray::new(
+                    Point3::new(theta.sin(), 0.0, theta.cos()),
+                    Vec3::new(-theta.sin(), 0.0, -theta.cos()),
+                );
+                let mut srec = ScatterRecord::default();
+                assert!(mat.scatter(&r_in, &rec, &mut srec, &mut rng));
+                let pdf = srec.pdf.take().unwrap();
+
+                let n = 100_000;
+                let mut albedo = 0.0;
+                for _ in 0..n {
+                    let scattered = Ray::new(rec.p, pdf.generate(&mut rng));
+                    let value = pdf.value(scattered.direction());
+                    if value > 0.0 {
+                        let weight = mat.scattering(&r_in, &rec, &srec, &scattered).x() / value;
+                        assert!(weight <= 1.0 + 1e-9, "{weight}");
+                        albedo += weight;
+                        let expected = mat.scattering_pdf(&r_in, &rec, &scattered);
+                        assert!((value - expected).abs() < 1e-9 * value.max(1.0));
+                    }
+                }
+                albedo /= n as f64;
+
+                assert!(albedo <= 1.0 + 1e-9);
+                if roughness <= 0.1 {
+                    assert!(albedo > 0.99, "A smooth metal is a mirror, got {albedo}.");
+                }
+            }
+        }
+
+        // real gold is red tinted at normal incidence and turns white at grazing angles
+        let gold = Conductor::named("gold", 0.2).unwrap();
+        let normal = gold.fresnel(1.0);
+        assert!(normal.x() > normal.z());
+        assert!(gold.fresnel(0.01).z() > normal.z());
+        assert!(Conductor::named("unobtainium", 0.2).is_none());
+    }
+
+    #[test]
+    fn test_dispersive_dielectric() {
+        let mut rng = seeded_rng(4);
+        let glass = Dielectric::dispersive(Dispersion::named("bk7").unwrap());
+        assert!((glass.refraction_index - 1.5168).abs() < 1e-4);
+        let rec = HitRecord {
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            front_face: true,
+            ..Default::default()
+        };
+        let r_in = || Ray::new(Point3::new(-1.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.0));
+        // most rays at 45 degrees refract into the glass
+        let refracted = |wavelength: Wavelength, rng: &mut RtRng| loop {
+            let mut srec = ScatterRecord::default();
+            assert!(glass.scatter(&r_in().with_wavelength(wavelength), &rec, &mut srec, rng));
+            if srec.skip_pdf_ray.direction().y() < 0.0 {
+                return srec;
+            }
+        };
+
+        // the blue bends more toward the normal, and the path keeps its hero wavelength only
+        let blue = refracted(Wavelength::Hero(450.0), &mut rng);
+        let red = refracted(Wavelength::Hero(650.0), &mut rng);
+        let slope = |srec: &ScatterRecord| {
+            let d = srec.skip_pdf_ray.direction();
+            d.x() / -d.y()
+        };
+        assert!(slope(&blue) < slope(&red));
+        let Wavelength::Spectral(sample) = blue.skip_pdf_ray.wavelength() else {
+            panic!("the colors must be split");
+        };
+        assert_eq!(sample.hero(), 450.0);
+        assert_eq!(sample.pdf[1..], [0.0; SPECTRAL_SAMPLES - 1]);
+        assert_eq!(blue.attenuation, Color::new(1.0, 1.0, 1.0));
+        assert_eq!(
+            blue.skip_pdf_ray.wavelength().weight(),
+            wavelength_weight(450.0)
+        );
+
+        // split colors go on with the hero, rays not from a camera see the d line
+        let again = refracted(blue.skip_pdf_ray.wavelength(), &mut rng);
+        assert_eq!(slope(&again), slope(&blue));
+        assert_eq!(
+            again.skip_pdf_ray.wavelength().weight(),
+            wavelength_weight(450.0)
+        );
+        // a reflection keeps every wavelength
+        let reflected = loop {
+            let mut srec = ScatterRecord::default();
+            let r = r_in().with_wavelength(Wavelength::Hero(450.0));
+            glass.scatter(&r, &rec, &mut srec, &mut rng);
+            if srec.skip_pdf_ray.direction().y() > 0.0 {
+                break srec;
+            }
+        };
+        let Wavelength::Spectral(sample) = reflected.skip_pdf_ray.wavelength() else {
+            panic!("the colors must be split");
+        };
+        assert!(sample.pdf.iter().all(|&pdf| pdf > 0.0));
+        let plain = refracted(Wavelength::Unsampled, &mut rng);
+        let bk7 = Dielectric::new(glass.refraction_index);
+        let expected = loop {
+            let mut srec = ScatterRecord::default();
+            bk7.scatter(&r_in(), &rec, &mut srec, &mut rng);
+            if srec.skip_pdf_ray.direction().y() < 0.0 {
+                break srec;
+            }
+        };
+        assert_eq!(slope(&plain), slope(&expected));
+        assert_eq!(plain.skip_pdf_ray.wavelength(), Wavelength::Unsampled);
+    }
+
+    #[test]
+    fn test_diffuse_light_front_face_only() {
+        let light = DiffuseLight::new(Color::new(4.0, 4.0, 4.0));
+        let r_in = Ray::default();
+        let mut rec = HitRecord {
+            front_face: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            light.emitted(&r_in, &rec, 0.0, 0.0, &rec.p),
+            Color::new(4.0, 4.0, 4.0)
+        );
+        rec.front_face = false;
+        assert_eq!(
+            light.emitted(&r_in, &rec, 0.0, 0.0, &rec.p),
+            Color::new(0.0, 0.0, 0.0)
+        );
+        assert!(!light.scatter(
+            &r_in,
+            &rec,
+            &mut ScatterRecord::default(),
+            &mut seeded_rng(0)
+        ));
     }
 }
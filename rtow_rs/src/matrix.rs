@@ -0,0 +1,227 @@
+use std::ops::Mul;
+
+use crate::{
+    rtweekend::degress_to_radians,
+    vec3::{Point3, Vec3},
+};
+
+/// 4x4 matrix of an affine transform in homogeneous coordinates, row major; points are
+/// column vectors multiplied on the right.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    pub m: [[f64; 4]; 4],
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Mat4 {
+    pub fn identity() -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+        Self { m }
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        let mut t = Self::identity();
+        for i in 0..3 {
+            t.m[i][3] = offset[i];
+        }
+        t
+    }
+
+    /// Scale by factor[i] along each axis, a negative factor mirrors.
+    pub fn scaling(factor: Vec3) -> Self {
+        let mut s = Self::identity();
+        for i in 0..3 {
+            s.m[i][i] = factor[i];
+        }
+        s
+    }
+
+    /// Counter-clockwise rotation of `angle` degrees around `axis`, looking down the axis.
+    pub fn rotation(axis: Vec3, angle: f64) -> Self {
+        let a = axis.unit();
+        let (sin, cos) = degress_to_radians(angle).sin_cos();
+        let c1 = 1.0 - cos;
+        let (x, y, z) = (a.x(), a.y(), a.z());
+
+        // Rodrigues' rotation formula
+        let mut r = Self::identity();
+        r.m[0][..3].copy_from_slice(&[
+            cos + x * x * c1,
+            x * y * c1 - z * sin,
+            x * z * c1 + y * sin,
+        ]);
+        r.m[1][..3].copy_from_slice(&[
+            y * x * c1 + z * sin,
+            cos + y * y * c1,
+            y * z * c1 - x * sin,
+        ]);
+        r.m[2][..3].copy_from_slice(&[
+            z * x * c1 - y * sin,
+            z * y * c1 + x * sin,
+            cos + z * z * c1,
+        ]);
+        r
+    }
+
+    /// Rotation of the unit quaternion w + xi + yj + zk, normalized first.
+    pub fn from_quaternion(w: f64, x: f64, y: f64, z: f64) -> Self {
+        let n = (w * w + x * x + y * y + z * z).sqrt();
+        let (w, x, y, z) = (w / n, x / n, y / n, z / n);
+
+        let mut r = Self::identity();
+        r.m[0][..3].copy_from_slice(&[
+            1.0 - 2.0 * (y * y + z * z),
+            2.0 * (x * y - w * z),
+            2.0 * (x * z + w * y),
+        ]);
+        r.m[1][..3].copy_from_slice(&[
+            2.0 * (x * y + w * z),
+            1.0 - 2.0 * (x * x + z * z),
+            2.0 * (y * z - w * x),
+        ]);
+        r.m[2][..3].copy_from_slice(&[
+            2.0 * (x * z - w * y),
+            2.0 * (y * z + w * x),
+            1.0 - 2.0 * (x * x + y * y),
+        ]);
+        r
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut t = Self::identity();
+        for i in 0..4 {
+            for j in 0..4 {
+                t.m[i][j] = self.m[j][i];
+            }
+        }
+        t
+    }
+
+    /// Determinant of the linear 3x3 part.
+    pub fn determinant3(&self) -> f64 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    /// Inverse of an affine transform, `None` when it flattens space.
+    pub fn inverse_affine(&self) -> Option<Self> {
+        let det = self.determinant3();
+        if det.abs() < 1e-12 {
+            return None;
+        }
+
+        let m = &self.m;
+        let mut inv = Self::identity();
+        // adjugate of the linear part over the determinant
+        for i in 0..3 {
+            for j in 0..3 {
+                let (r0, r1) = ((j + 1) % 3, (j + 2) % 3);
+                let (c0, c1) = ((i + 1) % 3, (i + 2) % 3);
+                inv.m[i][j] = (m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]) / det;
+            }
+        }
+        // the inverse translation undoes the translation in the inverted frame
+        let t = Vec3::new(m[0][3], m[1][3], m[2][3]);
+        let inv_t = inv.transform_vector(&t);
+        for i in 0..3 {
+            inv.m[i][3] = -inv_t[i];
+        }
+        Some(inv)
+    }
+
+    pub fn transform_point(&self, p: &Point3) -> Point3 {
+        self.transform_vector(p) + Vec3::new(self.m[0][3], self.m[1][3], self.m[2][3])
+    }
+
+    /// Apply the linear part only, for directions.
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        )
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    /// `a * b` applies b first, then a.
+    fn mul(self, rhs: Mat4) -> Mat4 {
+        let mut product = [[0.0; 4]; 4];
+        for (i, row) in product.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Mat4 { m: product }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-9, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn test_rotation() {
+        let quarter = Mat4::rotation(Vec3::new(0.0, 0.0, 2.0), 90.0);
+        assert_close(
+            quarter.transform_vector(&Vec3::new(1.0, 0.0, 0.0)),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+
+        // a third of a turn around the diagonal cycles the axes
+        let third = Mat4::rotation(Vec3::new(1.0, 1.0, 1.0), 120.0);
+        assert_close(
+            third.transform_vector(&Vec3::new(1.0, 0.0, 0.0)),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        assert!((third.determinant3() - 1.0).abs() < 1e-12);
+
+        // the quaternion of the same rotation, cos(60) + sin(60) (i + j + k) / sqrt(3)
+        let (sin, cos) = 60f64.to_radians().sin_cos();
+        let s = sin / 3f64.sqrt();
+        let q = Mat4::from_quaternion(cos, s, s, s);
+        for (a, b) in q.m.iter().flatten().zip(third.m.iter().flatten()) {
+            assert!((a - b).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_inverse_and_composition() {
+        let m = Mat4::translation(Vec3::new(1.0, -2.0, 3.0))
+            * Mat4::rotation(Vec3::new(0.3, 1.0, -0.2), 37.0)
+            * Mat4::scaling(Vec3::new(2.0, 0.5, -1.0));
+        let inv = m.inverse_affine().unwrap();
+        let p = Point3::new(0.7, -1.1, 4.0);
+
+        assert_close(inv.transform_point(&m.transform_point(&p)), p);
+        assert_close((m * inv).transform_point(&p), p);
+        // scale first, then translate
+        let scale_then_move =
+            Mat4::translation(Vec3::new(1.0, 0.0, 0.0)) * Mat4::scaling(Vec3::new(2.0, 2.0, 2.0));
+        assert_close(
+            scale_then_move.transform_point(&Point3::new(1.0, 1.0, 1.0)),
+            Point3::new(3.0, 2.0, 2.0),
+        );
+
+        assert!(Mat4::scaling(Vec3::new(1.0, 0.0, 1.0))
+            .inverse_affine()
+            .is_none());
+    }
+}
@@ -0,0 +1,139 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    bvh::{Bvh, SplitStrategy},
+    color::Color,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::{Lambertian, Material, Numbered},
+    ray::Ray,
+    triangle::Triangle,
+    vec3::{Point3, Vec3},
+};
+
+/// Triangles of a Wavefront OBJ file, held in their own BVH.
+pub struct TriangleMesh {
+    triangles: Bvh,
+}
+
+impl TriangleMesh {
+    /// Load every model of the OBJ file at `path`.
+    ///
+    /// Faces with an MTL material become Lambertian with its diffuse color (Kd), numbered like
+    /// `default_mat` for the material id pass; the others use `default_mat`. Vertex normals of the file give smooth shading, faces without them are flat.
+    pub fn load_obj<P: AsRef<Path>>(
+        path: P,
+        default_mat: Arc<dyn Material>,
+    ) -> Result<Self, tobj::LoadError> {
+        let (models, materials) = tobj::load_obj(
+            path.as_ref(),
+            &tobj::LoadOptions {
+                triangulate: true,
+                single_index: true,
+                ..Default::default()
+            },
+        )?;
+
+        // A missing or broken MTL file leaves the faces with the default material.
+        let id = default_mat.id();
+        let materials: Vec<Arc<dyn Material>> = materials
+            .unwrap_or_default()
+            .iter()
+            .map(|m| match m.diffuse {
+                Some([r, g, b]) => {
+                    let lambertian =
+                        Arc::new(Lambertian::new(Color::new(r as f64, g as f64, b as f64)));
+                    Arc::new(Numbered::new(id, lambertian)) as Arc<dyn Material>
+                }
+                None => default_mat.clone(),
+            })
+            .collect();
+
+        let mut triangles: Vec<Box<dyn Hittable>> = Vec::new();
+        for model in &models {
+            let mesh = &model.mesh;
+            let mat = mesh
+                .material_id
+                .and_then(|id| materials.get(id))
+                .unwrap_or(&default_mat);
+
+            let position = |index: u32| {
+                let i = 3 * index as usize;
+                Point3::new(
+                    mesh.positions[i] as f64,
+                    mesh.positions[i + 1] as f64,
+                    mesh.positions[i + 2] as f64,
+                )
+            };
+            let normal = |index: u32| {
+                let i = 3 * index as usize;
+                Vec3::new(
+                    mesh.normals[i] as f64,
+                    mesh.normals[i + 1] as f64,
+                    mesh.normals[i + 2] as f64,
+                )
+            };
+
+            for face in mesh.indices.chunks_exact(3) {
+                let vertices = [position(face[0]), position(face[1]), position(face[2])];
+                let triangle = if mesh.normals.is_empty() {
+                    let [a, b, c] = vertices;
+                    Triangle::new(a, b, c, mat.clone())
+                } else {
+                    let normals = [normal(face[0]), normal(face[1]), normal(face[2])];
+                    Triangle::with_normals(vertices, normals, mat.clone())
+                };
+                triangles.push(Box::new(triangle));
+            }
+        }
+
+        Ok(Self {
+            triangles: Bvh::new(triangles, SplitStrategy::Sah),
+        })
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        self.triangles.hit(r, ray_t, rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.triangles.bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CUBE_OBJ: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../phong/res/cube.obj");
+
+    #[test]
+    fn test_load_cube() {
+        let default_mat: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(1.0, 0.0, 0.0)));
+        let mesh = TriangleMesh::load_obj(CUBE_OBJ, default_mat).unwrap();
+
+        assert!(mesh.triangle_count() > 12);
+        let bbox = mesh.bounding_box();
+        assert!((bbox.x.min + 1.0).abs() < 1e-3 && (bbox.x.max - 1.0).abs() < 1e-3);
+
+        let r = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut rec: HitRecord = Default::default();
+        assert!(mesh.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+        assert!((rec.t - 4.0).abs() < 1e-6);
+        assert!(rec.front_face);
+        assert!(rec.normal.dot(&Vec3::new(0.0, 0.0, 1.0)) > 0.99);
+
+        // Kd 0.8 of the MTL file, not the red default; tobj reads it as an f32
+        let kd = 0.8f32 as f64;
+        let mat = rec.mat.clone().unwrap();
+        assert_eq!(mat.albedo(&rec), Color::new(kd, kd, kd));
+    }
+}
@@ -0,0 +1,233 @@
+//! GGX (Trowbridge-Reitz) microfacet distribution with Smith masking, the sampling of its
+//! visible normals after Heitz 2018, and the Fresnel reflectance of conductors.
+//!
+//! Directions are in the local shading frame, where the macro surface normal is +z and both
+//! the outgoing `wo` and incoming `wi` directions point away from the surface.
+
+use std::f64::consts::PI;
+
+use crate::{
+    onb::Onb,
+    pdf::Pdf,
+    rtweekend::{random_double, RtRng},
+    vec3::Vec3,
+};
+
+/// Isotropic GGX distribution of micro normals.
+#[derive(Debug, Clone, Copy)]
+pub struct Ggx {
+    alpha: f64,
+}
+
+impl Ggx {
+    /// Smallest slope spread, a perfect mirror would need a Dirac distribution.
+    const MIN_ALPHA: f64 = 1e-3;
+
+    /// Perceptual `roughness` in [0, 1], the slope spread alpha is its square.
+    pub fn from_roughness(roughness: f64) -> Self {
+        Self::new(roughness * roughness)
+    }
+
+    pub fn new(alpha: f64) -> Self {
+        Self {
+            alpha: alpha.max(Self::MIN_ALPHA),
+        }
+    }
+
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+
+    /// Density of micro normals `h` per unit projected area of the macro surface.
+    pub fn d(&self, h: &Vec3) -> f64 {
+        if h.z() <= 0.0 {
+            return 0.0;
+        }
+        let a2 = self.alpha * self.alpha;
+        let t = h.x() * h.x() + h.y() * h.y() + h.z() * h.z() * a2;
+        a2 / (PI * t * t)
+    }
+
+    /// Smith auxiliary function, the masked micro area seen from `w` relative to its
+    /// projection on the macro surface.
+    pub fn lambda(&self, w: &Vec3) -> f64 {
+        let cos2 = w.z() * w.z();
+        if cos2 <= 0.0 {
+            return f64::INFINITY;
+        }
+        let tan2 = (1.0 - cos2).max(0.0) / cos2;
+        0.5 * ((1.0 + self.alpha * self.alpha * tan2).sqrt() - 1.0)
+    }
+
+    /// Fraction of the micro surface visible from `w`.
+    pub fn g1(&self, w: &Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Height correlated masking and shadowing of the pair of directions.
+    pub fn g2(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Draw a micro normal visible from `wo`, with a density of `g1(wo) (wo.h) d(h) / wo.z`.
+    pub fn sample_visible_normal(&self, wo: &Vec3, rng: &mut RtRng) -> Vec3 {
+        // stretch the view so that the distribution becomes a hemisphere of unit roughness
+        let vh = Vec3::new(self.alpha * wo.x(), self.alpha * wo.y(), wo.z()).unit();
+
+        let len2 = vh.x() * vh.x() + vh.y() * vh.y();
+        let t1 = if len2 > 0.0 {
+            Vec3::new(-vh.y(), vh.x(), 0.0) / len2.sqrt()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = vh.cross(t1);
+
+        // uniform point on the disk, squeezed onto the part of it facing the view
+        let r = random_double(rng).sqrt();
+        let phi = 2.0 * PI * random_double(rng);
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z());
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+
+        Vec3::new(self.alpha * nh.x(), self.alpha * nh.y(), nh.z().max(0.0)).unit()
+    }
+
+    /// Density of the direction `wi` reflected about a visible normal drawn for `wo`.
+    pub fn reflection_pdf(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return 0.0;
+        }
+        let h = (*wo + *wi).unit();
+        self.g1(wo) * self.d(&h) / (4.0 * wo.z())
+    }
+}
+
+/// Unpolarized Fresnel reflectance of a conductor of complex index of refraction `eta + i k`,
+/// relative to the outside medium, at the cosine `cos_theta` of the incident angle.
+pub fn fresnel_conductor(cos_theta: f64, eta: f64, k: f64) -> f64 {
+    let cos2 = cos_theta.clamp(0.0, 1.0).powi(2);
+    let sin2 = 1.0 - cos2;
+    let eta2 = eta * eta;
+    let k2 = k * k;
+
+    let t0 = eta2 - k2 - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+
+    let t1 = a2_plus_b2 + cos2;
+    let t2 = 2.0 * cos2.sqrt() * a;
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+
+    0.5 * (rs + rp)
+}
+
+/// Reflections about the GGX normals visible from `wo`, in world space around a normal.
+#[derive(Debug)]
+pub struct GgxPdf {
+    uvw: Onb,
+    /// Outgoing direction in the local frame
+    wo: Vec3,
+    ggx: Ggx,
+}
+
+impl GgxPdf {
+    /// `wo` points away from the surface of normal `normal`.
+    pub fn new(normal: &Vec3, wo: &Vec3, ggx: Ggx) -> Self {
+        let uvw = Onb::new(normal);
+        let wo = uvw.to_local(&wo.unit());
+        Self { uvw, wo, ggx }
+    }
+}
+
+impl Pdf for GgxPdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        let wi = self.uvw.to_local(&direction.unit());
+        self.ggx.reflection_pdf(&self.wo, &wi)
+    }
+
+    fn generate(&self, rng: &mut RtRng) -> Vec3 {
+        let h = self.ggx.sample_visible_normal(&self.wo, rng);
+        self.uvw.transform(&(-self.wo).reflect(&h))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtweekend::seeded_rng;
+
+    fn direction(theta_degrees: f64) -> Vec3 {
+        let theta = theta_degrees.to_radians();
+        Vec3::new(theta.sin(), 0.0, theta.cos())
+    }
+
+    #[test]
+    fn test_ggx_white_furnace() {
+        // the visible normals of every view cover exactly its projected area: the integral of
+        // g1(wo) d(h) / (4 wo.z) over all the reflected directions, below the surface too, is 1
+        let mut rng = seeded_rng(11);
+        let n = 400_000;
+        for alpha in [0.2, 0.5, 1.0] {
+            let ggx = Ggx::new(alpha);
+            for theta in [0.0, 45.0, 80.0] {
+                let wo = direction(theta);
+                let sum: f64 = (0..n)
+                    .map(|_| {
+                        let wi = Vec3::random_unit_vector(&mut rng);
+                        let h = (wo + wi).unit();
+                        let value = ggx.g1(&wo) * ggx.d(&h) / (4.0 * wo.z());
+                        value * 4.0 * PI
+                    })
+                    .sum();
+                let furnace = sum / n as f64;
+                assert!(
+                    (furnace - 1.0).abs() < 0.03,
+                    "alpha {alpha}, theta {theta}: {furnace}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_visible_normals_match_their_pdf() {
+        let mut rng = seeded_rng(12);
+        let ggx = Ggx::from_roughness(0.6);
+        let pdf = GgxPdf::new(&Vec3::new(0.0, 1.0, 0.0), &Vec3::new(0.3, 1.0, 0.2), ggx);
+
+        // the mean of a test function over the drawn directions, some of them below the
+        // surface, agrees with its integral against the density
+        let f = |d: &Vec3| d.y().max(0.0).powi(3);
+        let n = 200_000;
+        let sampled: f64 = (0..n).map(|_| f(&pdf.generate(&mut rng))).sum::<f64>() / n as f64;
+        let uniform: f64 = (0..n)
+            .map(|_| {
+                let d = Vec3::random_unit_vector(&mut rng);
+                f(&d) * pdf.value(&d) * 4.0 * PI
+            })
+            .sum::<f64>()
+            / n as f64;
+
+        assert!(
+            (sampled - uniform).abs() < 0.02 * uniform,
+            "{sampled} {uniform}"
+        );
+    }
+
+    #[test]
+    fn test_fresnel_conductor() {
+        // a dielectric at normal incidence: ((eta - 1) / (eta + 1))^2
+        assert!((fresnel_conductor(1.0, 1.5, 0.0) - 0.04).abs() < 1e-12);
+        // gold is more reflective in red than in blue, and every metal is a mirror at grazing
+        assert!(fresnel_conductor(1.0, 0.143, 3.983) > fresnel_conductor(1.0, 1.442, 1.603));
+        assert!((fresnel_conductor(0.0, 0.143, 3.983) - 1.0).abs() < 1e-12);
+        for i in 0..=10 {
+            let f = fresnel_conductor(i as f64 / 10.0, 1.657, 9.224);
+            assert!((0.0..=1.0).contains(&f));
+        }
+    }
+}
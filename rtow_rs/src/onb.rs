@@ -0,0 +1,74 @@
+use crate::vec3::Vec3;
+
+/// Orthonormal basis whose w axis is a given direction.
+#[derive(Debug, Clone, Copy)]
+pub struct Onb {
+    axis: [Vec3; 3],
+}
+
+impl Onb {
+    pub fn new(n: &Vec3) -> Self {
+        let w = n.unit();
+        let a = if w.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = w.cross(a).unit();
+        let u = w.cross(v);
+
+        Self { axis: [u, v, w] }
+    }
+
+    pub fn u(&self) -> Vec3 {
+        self.axis[0]
+    }
+
+    pub fn v(&self) -> Vec3 {
+        self.axis[1]
+    }
+
+    pub fn w(&self) -> Vec3 {
+        self.axis[2]
+    }
+
+    /// Transform from basis coordinates to local space.
+    pub fn transform(&self, v: &Vec3) -> Vec3 {
+        v[0] * self.axis[0] + v[1] * self.axis[1] + v[2] * self.axis[2]
+    }
+
+    /// Transform from local space to basis coordinates, the inverse of `transform`.
+    pub fn to_local(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(
+            v.dot(&self.axis[0]),
+            v.dot(&self.axis[1]),
+            v.dot(&self.axis[2]),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_onb_is_orthonormal() {
+        for n in [
+            Vec3::new(0.0, 0.0, 2.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(-0.3, 0.8, 0.1),
+        ] {
+            let uvw = Onb::new(&n);
+            let (u, v, w) = (uvw.u(), uvw.v(), uvw.w());
+
+            assert!((w - n.unit()).length() < 1e-12);
+            for axis in [u, v, w] {
+                assert!((axis.length() - 1.0).abs() < 1e-12);
+            }
+            assert!(u.dot(&v).abs() < 1e-12 && v.dot(&w).abs() < 1e-12 && w.dot(&u).abs() < 1e-12);
+            assert!((uvw.transform(&Vec3::new(0.0, 0.0, 1.0)) - w).length() < 1e-12);
+            let p = Vec3::new(0.3, -2.0, 0.7);
+            assert!((uvw.to_local(&uvw.transform(&p)) - p).length() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::f64::consts::PI;
+
+use crate::{
+    environment::EnvironmentMap,
+    hittable::Hittable,
+    onb::Onb,
+    rtweekend::{random_double, RtRng},
+    vec3::{Point3, Vec3},
+};
+
+/// Probability density over directions, with a way to draw directions from it.
+pub trait Pdf {
+    /// Density of `direction`, per unit solid angle.
+    fn value(&self, direction: &Vec3) -> f64;
+
+    fn generate(&self, rng: &mut RtRng) -> Vec3;
+}
+
+/// Uniform density over all directions.
+#[derive(Debug, Default)]
+pub struct SpherePdf;
+
+impl Pdf for SpherePdf {
+    fn value(&self, _direction: &Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn generate(&self, rng: &mut RtRng) -> Vec3 {
+        Vec3::random_unit_vector(rng)
+    }
+}
+
+/// Density proportional to the cosine with a normal, zero below its hemisphere.
+#[derive(Debug)]
+pub struct CosinePdf {
+    uvw: Onb,
+}
+
+impl CosinePdf {
+    pub fn new(w: &Vec3) -> Self {
+        Self { uvw: Onb::new(w) }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        let cosine_theta = direction.unit().dot(&self.uvw.w());
+        (cosine_theta / PI).max(0.0)
+    }
+
+    fn generate(&self, rng: &mut RtRng) -> Vec3 {
+        self.uvw.transform(&Vec3::random_cosine_direction(rng))
+    }
+}
+
+/// Directions from `origin` toward the surface of some objects, usually the lights.
+pub struct HittablePdf<'a> {
+    objects: &'a dyn Hittable,
+    origin: Point3,
+}
+
+impl<'a> HittablePdf<'a> {
+    pub fn new(objects: &'a dyn Hittable, origin: Point3) -> Self {
+        Self { objects, origin }
+    }
+}
+
+impl Pdf for HittablePdf<'_> {
+    fn value(&self, direction: &Vec3) -> f64 {
+        self.objects.pdf_value(&self.origin, direction)
+    }
+
+    fn generate(&self, rng: &mut RtRng) -> Vec3 {
+        self.objects.random(&self.origin, rng)
+    }
+}
+
+/// Directions toward the bright parts of an environment map.
+pub struct EnvironmentPdf<'a> {
+    map: &'a EnvironmentMap,
+}
+
+impl<'a> EnvironmentPdf<'a> {
+    pub fn new(map: &'a EnvironmentMap) -> Self {
+        Self { map }
+    }
+}
+
+impl Pdf for EnvironmentPdf<'_> {
+    fn value(&self, direction: &Vec3) -> f64 {
+        self.map.pdf(direction)
+    }
+
+    fn generate(&self, rng: &mut RtRng) -> Vec3 {
+        self.map.sample(rng)
+    }
+}
+
+/// Even mix of two densities. Drawing from it and dividing by its value weights each
+/// strategy with the balance heuristic, a one-sample multiple importance sampling.
+pub struct MixturePdf<'a> {
+    p: [&'a dyn Pdf; 2],
+}
+
+impl<'a> MixturePdf<'a> {
+    pub fn new(p0: &'a dyn Pdf, p1: &'a dyn Pdf) -> Self {
+        Self { p: [p0, p1] }
+    }
+}
+
+impl Pdf for MixturePdf<'_> {
+    fn value(&self, direction: &Vec3) -> f64 {
+        0.5 * self.p[0].value(direction) + 0.5 * self.p[1].value(direction)
+    }
+
+    fn generate(&self, rng: &mut RtRng) -> Vec3 {
+        if random_double(rng) < 0.5 {
+            self.p[0].generate(rng)
+        } else {
+            self.p[1].generate(rng)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtweekend::seeded_rng;
+
+    /// Monte Carlo estimate of the integral of the density over the sphere of directions,
+    /// which is 1 for a normalized density.
+    fn integral(pdf: &dyn Pdf, rng: &mut RtRng) -> f64 {
+        let n = 200_000;
+        let uniform = SpherePdf;
+        let sum: f64 = (0..n)
+            .map(|_| {
+                let d = uniform.generate(rng);
+                pdf.value(&d) / uniform.value(&d)
+            })
+            .sum();
+        sum / n as f64
+    }
+
+    #[test]
+    fn test_pdfs_are_normalized() {
+        let mut rng = seeded_rng(3);
+        let cosine = CosinePdf::new(&Vec3::new(0.2, 1.0, -0.4));
+        let mixture = MixturePdf::new(&cosine, &SpherePdf);
+
+        assert!((integral(&SpherePdf, &mut rng) - 1.0).abs() < 1e-12);
+        assert!((integral(&cosine, &mut rng) - 1.0).abs() < 0.01);
+        assert!((integral(&mixture, &mut rng) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_cosine_pdf_generates_upper_hemisphere() {
+        let mut rng = seeded_rng(5);
+        let normal = Vec3::new(-1.0, 0.5, 0.0);
+        let cosine = CosinePdf::new(&normal);
+
+        for _ in 0..1000 {
+            let d = cosine.generate(&mut rng);
+            assert!(d.dot(&normal) >= 0.0);
+            assert!((d.length() - 1.0).abs() < 1e-9);
+            assert!(cosine.value(&d) >= 0.0);
+        }
+        assert_eq!(cosine.value(&-normal), 0.0);
+    }
+}
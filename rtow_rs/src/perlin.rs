@@ -0,0 +1,134 @@
+use crate::{
+    rtweekend::{random_int, RtRng},
+    vec3::{Point3, Vec3},
+};
+
+const POINT_COUNT: usize = 256;
+
+/// Perlin gradient noise: random unit vectors on the lattice points, hashed by three
+/// permutation tables, blended with a Hermite smoothed trilinear interpolation.
+#[derive(Debug)]
+pub struct Perlin {
+    randvec: [Vec3; POINT_COUNT],
+    perm_x: [usize; POINT_COUNT],
+    perm_y: [usize; POINT_COUNT],
+    perm_z: [usize; POINT_COUNT],
+}
+
+impl Perlin {
+    pub fn new(rng: &mut RtRng) -> Self {
+        let mut randvec = [Vec3::default(); POINT_COUNT];
+        for v in randvec.iter_mut() {
+            *v = Vec3::random(rng, -1.0, 1.0).unit();
+        }
+
+        Self {
+            randvec,
+            perm_x: Self::generate_perm(rng),
+            perm_y: Self::generate_perm(rng),
+            perm_z: Self::generate_perm(rng),
+        }
+    }
+
+    /// Noise value in [-1, 1] at point p.
+    pub fn noise(&self, p: &Point3) -> f64 {
+        let u = p.x() - p.x().floor();
+        let v = p.y() - p.y().floor();
+        let w = p.z() - p.z().floor();
+
+        let i = p.x().floor() as i64;
+        let j = p.y().floor() as i64;
+        let k = p.z().floor() as i64;
+
+        let mut c = [[[Vec3::default(); 2]; 2]; 2];
+        for (di, plane) in c.iter_mut().enumerate() {
+            for (dj, line) in plane.iter_mut().enumerate() {
+                for (dk, corner) in line.iter_mut().enumerate() {
+                    let index = self.perm_x[((i + di as i64) & 255) as usize]
+                        ^ self.perm_y[((j + dj as i64) & 255) as usize]
+                        ^ self.perm_z[((k + dk as i64) & 255) as usize];
+                    *corner = self.randvec[index];
+                }
+            }
+        }
+
+        Self::perlin_interp(&c, u, v, w)
+    }
+
+    /// Sum of `depth` octaves of noise, each at twice the frequency and half the weight.
+    pub fn turb(&self, p: &Point3, depth: u32) -> f64 {
+        let mut accum = 0.0;
+        let mut temp_p = *p;
+        let mut weight = 1.0;
+
+        for _ in 0..depth {
+            accum += weight * self.noise(&temp_p);
+            weight *= 0.5;
+            temp_p *= 2.0;
+        }
+
+        accum.abs()
+    }
+
+    fn generate_perm(rng: &mut RtRng) -> [usize; POINT_COUNT] {
+        let mut p = [0; POINT_COUNT];
+        for (i, value) in p.iter_mut().enumerate() {
+            *value = i;
+        }
+
+        // Fisher-Yates shuffle
+        for i in (1..POINT_COUNT).rev() {
+            let target = random_int(rng, 0, i as i32) as usize;
+            p.swap(i, target);
+        }
+
+        p
+    }
+
+    fn perlin_interp(c: &[[[Vec3; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
+        let uu = u * u * (3.0 - 2.0 * u);
+        let vv = v * v * (3.0 - 2.0 * v);
+        let ww = w * w * (3.0 - 2.0 * w);
+
+        let mut accum = 0.0;
+        for (i, plane) in c.iter().enumerate() {
+            for (j, line) in plane.iter().enumerate() {
+                for (k, corner) in line.iter().enumerate() {
+                    let (fi, fj, fk) = (i as f64, j as f64, k as f64);
+                    let weight_v = Vec3::new(u - fi, v - fj, w - fk);
+                    accum += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                        * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                        * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                        * corner.dot(&weight_v);
+                }
+            }
+        }
+
+        accum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtweekend::{random_double_range, seeded_rng};
+
+    #[test]
+    fn test_noise_range_and_lattice() {
+        let mut rng = seeded_rng(11);
+        let perlin = Perlin::new(&mut rng);
+
+        // the gradients vanish at their own lattice point
+        assert_eq!(perlin.noise(&Point3::new(3.0, -2.0, 7.0)), 0.0);
+
+        for _ in 0..1000 {
+            let p = Point3::random(&mut rng, -50.0, 50.0);
+            let n = perlin.noise(&p);
+            assert!((-1.0..=1.0).contains(&n), "noise {n} out of range at {p:?}");
+
+            // continuous: a tiny step only changes the value a little
+            let step = Vec3::new(random_double_range(&mut rng, -1e-6, 1e-6), 0.0, 0.0);
+            assert!((perlin.noise(&(p + step)) - n).abs() < 1e-4);
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    hittable_list::HittableList,
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    rtweekend::{random_double, RtRng},
+    vec3::{Point3, Vec3},
+};
+
+/// The plane spanned by the edges u and v from the corner Q, shared by the planar primitives.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Plane {
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    /// n / (n . n), turns a point in the plane into its (alpha, beta) plane coordinates
+    w: Vec3,
+    normal: Vec3,
+    d: f64,
+}
+
+impl Plane {
+    pub(crate) fn new(q: Point3, u: Vec3, v: Vec3) -> Self {
+        let n = u.cross(v);
+        let normal = n.unit();
+        Self {
+            q,
+            u,
+            v,
+            w: n / n.dot(&n),
+            normal,
+            d: normal.dot(&q),
+        }
+    }
+
+    pub(crate) fn normal(&self) -> Vec3 {
+        self.normal
+    }
+
+    /// Area of the parallelogram spanned by u and v.
+    pub(crate) fn area(&self) -> f64 {
+        self.u.cross(self.v).length()
+    }
+
+    /// The point Q + alpha * u + beta * v.
+    pub(crate) fn point(&self, alpha: f64, beta: f64) -> Point3 {
+        self.q + alpha * self.u + beta * self.v
+    }
+
+    /// Density per solid angle of the points spread uniformly over `area` of the plane, for the
+    /// point hit at `t` along `direction`.
+    pub(crate) fn solid_angle_pdf(&self, t: f64, direction: &Vec3, area: f64) -> f64 {
+        let distance_squared = t * t * direction.length_squared();
+        let cosine = (direction.dot(&self.normal) / direction.length()).abs();
+
+        distance_squared / (cosine * area)
+    }
+
+    /// Returns the ray parameter t and the plane coordinates (alpha, beta) of the hit point,
+    /// i.e. the hit point is Q + alpha * u + beta * v.
+    pub(crate) fn intersect(&self, r: &Ray, ray_t: &Interval) -> Option<(f64, f64, f64)> {
+        let denom = self.normal.dot(r.direction());
+
+        // Not hit if the ray is parallel to the plane.
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        // The hit point parameter t must lie inside the ray interval.
+        let t = (self.d - self.normal.dot(r.origin())) / denom;
+        if !ray_t.contains(t) {
+            return None;
+        }
+
+        let planar_hitpt_vector = r.at(t) - self.q;
+        let alpha = self.w.dot(&planar_hitpt_vector.cross(self.v));
+        let beta = self.w.dot(&self.u.cross(planar_hitpt_vector));
+
+        Some((t, alpha, beta))
+    }
+}
+
+/// Parallelogram with the corner Q and the edges u and v.
+#[derive(Debug)]
+pub struct Quad {
+    plane: Plane,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Quad {
+    pub fn new(q: Point3, u: Vec3, v: Vec3, mat: Arc<dyn Material>) -> Self {
+        // Compute the bounding box of all four vertices.
+        let bbox_diagonal1 = Aabb::from_points(q, q + u + v);
+        let bbox_diagonal2 = Aabb::from_points(q + u, q + v);
+
+        Self {
+            plane: Plane::new(q, u, v),
+            mat,
+            bbox: Aabb::surrounding(&bbox_diagonal1, &bbox_diagonal2),
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let Some((t, alpha, beta)) = self.plane.intersect(r, &ray_t) else {
+            return false;
+        };
+
+        // The hit point lies inside the quad when both plane coordinates are in [0, 1].
+        let unit_interval = Interval::new(0.0, 1.0);
+        if !unit_interval.contains(alpha) || !unit_interval.contains(beta) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = r.at(t);
+        rec.u = alpha;
+        rec.v = beta;
+        rec.mat = Some(self.mat.clone());
+        rec.set_face_normal(r, &self.plane.normal());
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let mut rec = HitRecord::default();
+        let ray = Ray::new(*origin, *direction);
+        if !self.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return 0.0;
+        }
+
+        self.plane
+            .solid_angle_pdf(rec.t, direction, self.plane.area())
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut RtRng) -> Vec3 {
+        let p = self.plane.point(random_double(rng), random_double(rng));
+        p - *origin
+    }
+}
+
+/// Returns the 3D box (six sides) that contains the two opposite vertices a & b.
+pub fn make_box(a: Point3, b: Point3, mat: Arc<dyn Material>) -> HittableList {
+    let mut sides = HittableList::new();
+
+    // Construct the two opposite vertices with the minimum and maximum coordinates.
+    let min = Point3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z()));
+    let max = Point3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z()));
+
+    let dx = Vec3::new(max.x() - min.x(), 0.0, 0.0);
+    let dy = Vec3::new(0.0, max.y() - min.y(), 0.0);
+    let dz = Vec3::new(0.0, 0.0, max.z() - min.z());
+
+    // Each side is spanned so that u x v points out of the box, its outward normal.
+    // front
+    sides.add(Box::new(Quad::new(
+        Point3::new(min.x(), min.y(), max.z()),
+        dx,
+        dy,
+        mat.clone(),
+    )));
+    // right
+    sides.add(Box::new(Quad::new(
+        Point3::new(max.x(), min.y(), max.z()),
+        -dz,
+        dy,
+        mat.clone(),
+    )));
+    // back
+    sides.add(Box::new(Quad::new(
+        Point3::new(max.x(), min.y(), min.z()),
+        -dx,
+        dy,
+        mat.clone(),
+    )));
+    // left
+    sides.add(Box::new(Quad::new(
+        Point3::new(min.x(), min.y(), min.z()),
+        dz,
+        dy,
+        mat.clone(),
+    )));
+    // top
+    sides.add(Box::new(Quad::new(
+        Point3::new(min.x(), max.y(), max.z()),
+        dx,
+        -dz,
+        mat.clone(),
+    )));
+    // bottom
+    sides.add(Box::new(Quad::new(
+        Point3::new(min.x(), min.y(), min.z()),
+        dx,
+        dz,
+        mat,
+    )));
+
+    sides
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::Lambertian;
+
+    fn gray() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))
+    }
+
+    #[test]
+    fn test_quad_hit() {
+        let quad = Quad::new(
+            Point3::new(-1.0, -1.0, -2.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 4.0, 0.0),
+            gray(),
+        );
+        let ray_t = Interval::new(0.001, f64::INFINITY);
+        let mut rec: HitRecord = Default::default();
+
+        let r = Ray::new(Point3::new(0.5, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(quad.hit(&r, ray_t, &mut rec));
+        assert_eq!(rec.t, 2.0);
+        assert_eq!(rec.p, Point3::new(0.5, 0.0, -2.0));
+        assert_eq!((rec.u, rec.v), (0.75, 0.25));
+        // u x v points to +z, towards the ray origin
+        assert!(rec.front_face);
+        assert_eq!(rec.normal, Vec3::new(0.0, 0.0, 1.0));
+
+        let r = Ray::new(Point3::new(0.5, 0.0, -4.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(quad.hit(&r, ray_t, &mut rec));
+        assert!(!rec.front_face);
+        assert_eq!(rec.normal, Vec3::new(0.0, 0.0, -1.0));
+
+        let outside = Ray::new(Point3::new(1.5, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!quad.hit(&outside, ray_t, &mut rec));
+
+        let parallel = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(!quad.hit(&parallel, ray_t, &mut rec));
+    }
+
+    #[test]
+    fn test_box_normals_point_outward() {
+        let cube = make_box(
+            Point3::new(1.0, 1.0, 1.0),
+            Point3::new(-1.0, -1.0, -1.0),
+            gray(),
+        );
+        let ray_t = Interval::new(0.001, f64::INFINITY);
+
+        let axes = [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        ];
+        for axis in axes {
+            for dir in [axis, -axis] {
+                // from outside, the ray meets the face whose normal points back at it
+                let r = Ray::new(3.0 * dir, -dir);
+                let mut rec: HitRecord = Default::default();
+                assert!(cube.hit(&r, ray_t, &mut rec));
+                assert_eq!(rec.t, 2.0);
+                assert!(rec.front_face, "face {dir:?} should face outward");
+                assert_eq!(rec.normal, dir);
+
+                // from the center, every face is hit from behind
+                let r = Ray::new(Point3::new(0.0, 0.0, 0.0), dir);
+                assert!(cube.hit(&r, ray_t, &mut rec));
+                assert!(!rec.front_face, "face {dir:?} should face outward");
+                assert_eq!(rec.normal, -dir);
+            }
+        }
+    }
+}
@@ -1,19 +1,35 @@
+use crate::spectrum::Wavelength;
 use crate::vec3::{Point3, Vec3};
 
 #[derive(Default, Debug)]
 pub struct Ray {
     orig: Point3,
     dir: Vec3,
+    /// Moment the ray is sent within the camera shutter interval
+    tm: f64,
+    wavelength: Wavelength,
 }
 
 impl Ray {
+    /// Ray at time 0.
     pub fn new(origin: Point3, direction: Vec3) -> Self {
+        Self::with_time(origin, direction, 0.0)
+    }
+
+    pub fn with_time(origin: Point3, direction: Vec3, time: f64) -> Self {
         Self {
             orig: origin,
             dir: direction,
+            tm: time,
+            wavelength: Wavelength::Unsampled,
         }
     }
 
+    pub fn with_wavelength(mut self, wavelength: Wavelength) -> Self {
+        self.wavelength = wavelength;
+        self
+    }
+
     pub fn origin(&self) -> &Point3 {
         &self.orig
     }
@@ -22,7 +38,15 @@ impl Ray {
         &self.dir
     }
 
+    pub fn time(&self) -> f64 {
+        self.tm
+    }
+
+    pub fn wavelength(&self) -> Wavelength {
+        self.wavelength
+    }
+
     pub fn at(&self, t: f64) -> Point3 {
-        &self.orig + &(&self.dir * t)
+        self.orig + self.dir * t
     }
 }
@@ -1,17 +1,72 @@
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64Mcg;
 
+/// Random generator threaded through every sampling routine of the renderer.
+///
+/// PCG is small, fast and gives the same sequence on every platform for a given seed.
+pub type RtRng = Pcg64Mcg;
 
 pub fn degress_to_radians(degress: f64) -> f64 {
     degress * std::f64::consts::PI / 180.0
 }
 
+/// Create a random generator from a seed.
+pub fn seeded_rng(seed: u64) -> RtRng {
+    RtRng::seed_from_u64(seed)
+}
+
+/// splitmix64 finalizer, spreads close inputs over the whole 64 bit space.
+pub fn mix64(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Derive an independent seed for pixel (i, j) from the render seed.
+pub fn pixel_seed(seed: u64, i: u32, j: u32) -> u64 {
+    mix64(seed ^ (((j as u64) << 32) | i as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+}
+
+/// Random generator of pixel (i, j), independent of the thread and tile rendering it.
+pub fn pixel_rng(seed: u64, i: u32, j: u32) -> RtRng {
+    seeded_rng(pixel_seed(seed, i, j))
+}
 
-pub fn random_double() -> f64
-{
-    let mut rng = rand::thread_rng();
+pub fn random_double(rng: &mut RtRng) -> f64 {
     rng.gen::<f64>()
 }
 
-pub fn random_double_range(min: f64, max: f64) -> f64 {
-    min + (max - min) * random_double()
+pub fn random_double_range(rng: &mut RtRng, min: f64, max: f64) -> f64 {
+    min + (max - min) * random_double(rng)
+}
+
+/// Returns a random integer in [min, max].
+pub fn random_int(rng: &mut RtRng, min: i32, max: i32) -> i32 {
+    random_double_range(rng, min as f64, (max + 1) as f64) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_rng_is_reproducible() {
+        let mut a = seeded_rng(42);
+        let mut b = seeded_rng(42);
+        for _ in 0..16 {
+            assert_eq!(
+                random_double(&mut a).to_bits(),
+                random_double(&mut b).to_bits()
+            );
+        }
+    }
+
+    #[test]
+    fn test_pixel_rng_differs_between_pixels() {
+        let a = random_double(&mut pixel_rng(42, 3, 5));
+        let b = random_double(&mut pixel_rng(42, 5, 3));
+        let c = random_double(&mut pixel_rng(43, 3, 5));
+        assert_ne!(a, b);
+        assert_ne!(a, c);
+    }
 }
@@ -0,0 +1,1680 @@
+//! Text scene description in TOML and the loader building the camera and world from it.
+//!
+//! ```toml
+//! [camera]
+//! image_width = 400
+//! lookfrom = [13.0, 2.0, 3.0]
+//!
+//! [textures.checker]
+//! type = "checker"
+//! scale = 0.32
+//! even = [0.2, 0.3, 0.1]
+//! odd = [0.9, 0.9, 0.9]
+//!
+//! [materials.ground]
+//! type = "lambertian"
+//! texture = "checker"
+//!
+//! [[objects]]
+//! type = "sphere"
+//! center = [0.0, -1000.0, 0.0]
+//! radius = 1000.0
+//! material = "ground"
+//!
+//! [materials.lamp]
+//! type = "diffuse_light"
+//! emit = [4.0, 4.0, 4.0]
+//!
+//! # `light` also samples the object directly, use it for the small bright emitters
+//! [[objects]]
+//! type = "quad"
+//! q = [3.0, 1.0, -2.0]
+//! u = [2.0, 0.0, 0.0]
+//! v = [0.0, 2.0, 0.0]
+//! material = "lamp"
+//! light = true
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::{
+    aperture::{Aperture, ApertureMask},
+    background::Background,
+    camera::{Camera, Projection},
+    constant_medium::ConstantMedium,
+    environment::EnvironmentMap,
+    hittable::Hittable,
+    hittable_list::HittableList,
+    instance::{RotateY, Transform, Translate},
+    integrator::PathTracer,
+    material::{
+        Conductor, Dielectric, DiffuseLight, Isotropic, Lambertian, Material, Metal, Numbered,
+    },
+    matrix::Mat4,
+    mesh::TriangleMesh,
+    quad::{make_box, Quad},
+    rtweekend::{seeded_rng, RtRng},
+    spectrum::Dispersion,
+    sphere::Sphere,
+    texture::{CheckerTexture, ImageTexture, NoiseTexture, SolidColor, Texture},
+    triangle::Triangle,
+    vec3::Vec3,
+};
+
+/// A camera with the world it looks at.
+pub struct Scene {
+    pub camera: Camera,
+    pub objects: Vec<Box<dyn Hittable>>,
+    /// Copies of the emissive objects the renderer samples directly
+    pub lights: HittableList,
+}
+
+/// Error of loading a scene, `line` is 1-based when the problem can be located in the text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneError {
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl SceneError {
+    fn at(text: &str, span: Range<usize>, message: String) -> Self {
+        Self {
+            line: Some(line_of(text, span.start)),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {line}: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDesc {
+    #[serde(default)]
+    camera: CameraDesc,
+    #[serde(default)]
+    textures: BTreeMap<String, Spanned<TextureDesc>>,
+    #[serde(default)]
+    materials: BTreeMap<String, Spanned<MaterialDesc>>,
+    #[serde(default)]
+    objects: Vec<Spanned<ObjectDesc>>,
+}
+
+/// Every public field of `Camera`, unset ones keep the `Camera::new` defaults.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct CameraDesc {
+    aspect_ratio: Option<Spanned<f64>>,
+    image_width: Option<Spanned<u32>>,
+    samples_per_pixel: Option<Spanned<u32>>,
+    noise_threshold: Option<Spanned<f64>>,
+    max_samples_per_pixel: Option<Spanned<u32>>,
+    max_depth: Option<Spanned<i32>>,
+    projection: Option<Spanned<ProjectionDesc>>,
+    /// Bounces after which the path tracer plays Russian roulette instead of stopping at
+    /// `max_depth`
+    russian_roulette: Option<u32>,
+    vfov: Option<Spanned<f64>>,
+    lookfrom: Option<Spanned<[f64; 3]>>,
+    lookat: Option<Spanned<[f64; 3]>>,
+    vup: Option<Spanned<[f64; 3]>>,
+    roll: Option<f64>,
+    /// Rows of the camera to world rotation, in place of lookat and vup
+    orientation: Option<Spanned<[[f64; 3]; 3]>>,
+    /// The same rotation as a quaternion [w, x, y, z]
+    quaternion: Option<Spanned<[f64; 4]>>,
+    defocus_angle: Option<Spanned<f64>>,
+    focus_dist: Option<Spanned<f64>>,
+    aperture: Option<Spanned<ApertureDesc>>,
+    shift: Option<[f64; 2]>,
+    tilt: Option<Spanned<[f64; 2]>>,
+    background: Option<Spanned<BackgroundDesc>>,
+    shutter_open: Option<f64>,
+    shutter_close: Option<Spanned<f64>>,
+    seed: Option<u64>,
+    threads: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProjectionDesc {
+    /// perspective, orthographic, fisheye or equirectangular
+    #[serde(rename = "type")]
+    kind: Spanned<String>,
+    /// Image height in world units of an orthographic camera
+    height: Option<Spanned<f64>>,
+    /// Field of view in degrees of a fisheye
+    fov: Option<Spanned<f64>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApertureDesc {
+    /// disk, polygon or image
+    #[serde(rename = "type")]
+    kind: Spanned<String>,
+    /// Count of sides of a polygon
+    blades: Option<Spanned<u32>>,
+    /// Degrees from the right to the first corner of a polygon
+    rotation: Option<f64>,
+    /// Grayscale mask of an image aperture
+    path: Option<Spanned<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BackgroundDesc {
+    #[serde(rename = "type")]
+    kind: Spanned<String>,
+    /// Color of a solid background
+    color: Option<Spanned<[f64; 3]>>,
+    /// Ends of a gradient background
+    bottom: Option<Spanned<[f64; 3]>>,
+    top: Option<Spanned<[f64; 3]>>,
+    /// Equirectangular HDR or PFM map of an environment background
+    path: Option<Spanned<String>>,
+    /// Factor of the radiance of the map
+    intensity: Option<Spanned<f64>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DispersionDesc {
+    /// cauchy, sellmeier, or one of the glasses bk7, fused_silica and diamond
+    #[serde(rename = "type")]
+    kind: Spanned<String>,
+    /// Cauchy coefficients, `b` is also the three Sellmeier numerators
+    a: Option<f64>,
+    b: Option<Coefficients>,
+    /// Sellmeier denominators
+    c: Option<[f64; 3]>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(untagged)]
+enum Coefficients {
+    One(f64),
+    Three([f64; 3]),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TextureDesc {
+    #[serde(rename = "type")]
+    kind: Spanned<String>,
+    /// Color of a solid texture
+    color: Option<Spanned<[f64; 3]>>,
+    /// Cell size of a checker, frequency of a noise
+    scale: Option<Spanned<f64>>,
+    even: Option<Spanned<[f64; 3]>>,
+    odd: Option<Spanned<[f64; 3]>>,
+    /// PNG or JPEG file of an image texture, relative to the scene file
+    path: Option<Spanned<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialDesc {
+    #[serde(rename = "type")]
+    kind: Spanned<String>,
+    albedo: Option<Spanned<[f64; 3]>>,
+    /// Radiance of a diffuse light
+    emit: Option<Spanned<[f64; 3]>>,
+    /// Name of a texture used instead of `albedo` or `emit`
+    texture: Option<Spanned<String>>,
+    fuzz: Option<Spanned<f64>>,
+    refraction_index: Option<Spanned<f64>>,
+    /// Wavelength dependent index of a dielectric, instead of `refraction_index`
+    dispersion: Option<Spanned<DispersionDesc>>,
+    /// Microfacet roughness of a conductor, 0 is a mirror
+    roughness: Option<Spanned<f64>>,
+    /// Known metal of a conductor: aluminium, copper, gold or silver
+    metal: Option<Spanned<String>>,
+    /// Complex index of refraction `eta + i k` of a conductor, per color channel
+    eta: Option<[f64; 3]>,
+    k: Option<[f64; 3]>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ObjectDesc {
+    #[serde(rename = "type")]
+    kind: Spanned<String>,
+    center: Option<[f64; 3]>,
+    /// Center of a moving sphere at the time 1, it starts from `center` at the time 0
+    center2: Option<[f64; 3]>,
+    radius: Option<Spanned<f64>>,
+    /// Corner and edges of a quad
+    q: Option<[f64; 3]>,
+    u: Option<[f64; 3]>,
+    v: Option<[f64; 3]>,
+    /// Vertices of a triangle, a and b are also the opposite corners of a box
+    a: Option<[f64; 3]>,
+    b: Option<[f64; 3]>,
+    c: Option<[f64; 3]>,
+    /// OBJ file of a mesh, relative to the scene file
+    path: Option<Spanned<String>>,
+    /// For a mesh, the material of the faces without an MTL material
+    material: Spanned<String>,
+    /// Also add the object to the lights sampled by the renderer
+    light: Option<Spanned<bool>>,
+    /// Fill the object with a constant medium of this density instead of a surface
+    density: Option<Spanned<f64>>,
+    /// Placement of the object, applied in this order: scale, rotate, translate
+    scale: Option<Spanned<[f64; 3]>>,
+    /// Rotation in degrees, counter-clockwise around `rotate_axis` (default +y)
+    rotate: Option<f64>,
+    rotate_axis: Option<Spanned<[f64; 3]>>,
+    translate: Option<[f64; 3]>,
+}
+
+/// Read and build the scene stored in `path`.
+pub fn load_scene<P: AsRef<Path>>(path: P) -> Result<Scene, SceneError> {
+    let text = fs::read_to_string(path.as_ref()).map_err(|err| SceneError {
+        line: None,
+        message: format!("cannot read {}: {err}", path.as_ref().display()),
+    })?;
+    let base_dir = path.as_ref().parent().unwrap_or(Path::new("."));
+    build_scene(&text, base_dir)
+}
+
+/// Build the scene described by the TOML `text`, relative file paths start from the working
+/// directory.
+pub fn parse_scene(text: &str) -> Result<Scene, SceneError> {
+    build_scene(text, Path::new("."))
+}
+
+fn build_scene(text: &str, base_dir: &Path) -> Result<Scene, SceneError> {
+    let desc: SceneDesc = toml::from_str(text).map_err(|err| SceneError {
+        line: err.span().map(|span| line_of(text, span.start)),
+        message: err.message().to_string(),
+    })?;
+
+    let camera = build_camera(text, base_dir, &desc.camera)?;
+
+    // noise textures are seeded from the camera so a scene renders the same every time
+    let mut rng = seeded_rng(camera.seed);
+    let mut textures: BTreeMap<&str, Arc<dyn Texture>> = BTreeMap::new();
+    for (name, tex) in &desc.textures {
+        textures.insert(
+            name.as_str(),
+            build_texture(text, base_dir, name, tex, &mut rng)?,
+        );
+    }
+
+    // numbered by their place in the table, which the camera and the image size do not change
+    let mut materials: BTreeMap<&str, Arc<dyn Material>> = BTreeMap::new();
+    for (index, (name, mat)) in desc.materials.iter().enumerate() {
+        let mat = build_material(text, name, mat, &textures)?;
+        materials.insert(
+            name.as_str(),
+            Arc::new(Numbered::new(index as u32 + 1, mat)),
+        );
+    }
+
+    let mut meshes = BTreeMap::new();
+    let mut objects: Vec<Box<dyn Hittable>> = Vec::new();
+    let mut lights = HittableList::new();
+    for object in &desc.objects {
+        objects.push(build_object(
+            text,
+            base_dir,
+            object,
+            &materials,
+            &mut meshes,
+        )?);
+
+        let Some(light) = &object.get_ref().light else {
+            continue;
+        };
+        if !*light.get_ref() {
+            continue;
+        }
+        // meshes and volumes cannot be sampled, they would make the light density wrong, and
+        // a moving sphere is only sampled where it is at time 0
+        let desc = object.get_ref();
+        if desc.kind.get_ref() == "mesh" || desc.density.is_some() || desc.center2.is_some() {
+            return Err(SceneError::at(
+                text,
+                light.span(),
+                "a mesh, a volume or a moving sphere cannot be a light".to_string(),
+            ));
+        }
+        lights.add(build_object(
+            text,
+            base_dir,
+            object,
+            &materials,
+            &mut meshes,
+        )?);
+    }
+
+    Ok(Scene {
+        camera,
+        objects,
+        lights,
+    })
+}
+
+fn build_camera(text: &str, base_dir: &Path, desc: &CameraDesc) -> Result<Camera, SceneError> {
+    let mut camera = Camera::new();
+
+    if let Some(aspect_ratio) = &desc.aspect_ratio {
+        camera.aspect_ratio = positive(text, aspect_ratio, "aspect_ratio")?;
+    }
+    if let Some(image_width) = &desc.image_width {
+        if *image_width.get_ref() == 0 {
+            return Err(SceneError::at(
+                text,
+                image_width.span(),
+                "image_width must be at least 1".to_string(),
+            ));
+        }
+        camera.image_width = *image_width.get_ref();
+    }
+    if let Some(samples_per_pixel) = &desc.samples_per_pixel {
+        if *samples_per_pixel.get_ref() == 0 {
+            return Err(SceneError::at(
+                text,
+                samples_per_pixel.span(),
+                "samples_per_pixel must be at least 1".to_string(),
+            ));
+        }
+        camera.samples_per_pixel = *samples_per_pixel.get_ref();
+    }
+    if let Some(noise_threshold) = &desc.noise_threshold {
+        let value = *noise_threshold.get_ref();
+        if value.is_nan() || value < 0.0 {
+            return Err(SceneError::at(
+                text,
+                noise_threshold.span(),
+                format!("noise_threshold must not be negative, got {value}"),
+            ));
+        }
+        camera.noise_threshold = value;
+    }
+    if let Some(max_samples_per_pixel) = &desc.max_samples_per_pixel {
+        camera.max_samples_per_pixel = *max_samples_per_pixel.get_ref();
+    }
+    if camera.noise_threshold > 0.0 && camera.max_samples_per_pixel < camera.samples_per_pixel {
+        let span = desc.max_samples_per_pixel.as_ref().map(|max| max.span());
+        return Err(SceneError {
+            line: span.map(|span| line_of(text, span.start)),
+            message: format!(
+                "max_samples_per_pixel must be at least samples_per_pixel, got {} < {}",
+                camera.max_samples_per_pixel, camera.samples_per_pixel
+            ),
+        });
+    }
+    if let Some(max_depth) = &desc.max_depth {
+        let value = *max_depth.get_ref();
+        if value < 0 {
+            return Err(SceneError::at(
+                text,
+                max_depth.span(),
+                format!("max_depth must not be negative, got {value}"),
+            ));
+        }
+        camera.max_depth = value;
+    }
+    if let Some(projection) = &desc.projection {
+        camera.projection = build_projection(text, projection)?;
+    }
+    if let Some(min_depth) = desc.russian_roulette {
+        camera.integrator = Box::new(PathTracer::with_roulette(min_depth as i32));
+    }
+    if let Some(vfov) = &desc.vfov {
+        let value = *vfov.get_ref();
+        if value <= 0.0 || value >= 180.0 {
+            return Err(SceneError::at(
+                text,
+                vfov.span(),
+                format!("vfov must be in (0, 180) degrees, got {value}"),
+            ));
+        }
+        camera.vfov = value;
+    }
+    if let Some(lookfrom) = &desc.lookfrom {
+        camera.lookfrom = to_vec3(*lookfrom.get_ref());
+    }
+    if let Some(lookat) = &desc.lookat {
+        camera.lookat = to_vec3(*lookat.get_ref());
+    }
+    if let Some(vup) = &desc.vup {
+        camera.vup = to_vec3(*vup.get_ref());
+    }
+    if let Some(roll) = desc.roll {
+        camera.roll = roll;
+    }
+    match (&desc.orientation, &desc.quaternion) {
+        (Some(_), Some(quaternion)) => {
+            return Err(SceneError::at(
+                text,
+                quaternion.span(),
+                "give either orientation or quaternion, not both".to_string(),
+            ))
+        }
+        (Some(orientation), None) => {
+            let mut rotation = Mat4::identity();
+            for (row, values) in rotation.m.iter_mut().zip(orientation.get_ref()) {
+                row[..3].copy_from_slice(values);
+            }
+            // the rows of a rotation are orthonormal, its inverse is its transpose
+            let product = rotation * rotation.transpose();
+            let orthonormal = (0..3).all(|i| {
+                (0..3).all(|j| (product.m[i][j] - if i == j { 1.0 } else { 0.0 }).abs() < 1e-3)
+            });
+            if !orthonormal || rotation.determinant3() < 0.0 {
+                return Err(SceneError::at(
+                    text,
+                    orientation.span(),
+                    "orientation must be a rotation matrix".to_string(),
+                ));
+            }
+            camera.orientation = Some(rotation);
+        }
+        (None, Some(quaternion)) => {
+            let [w, x, y, z] = *quaternion.get_ref();
+            if w * w + x * x + y * y + z * z < 1e-12 {
+                return Err(SceneError::at(
+                    text,
+                    quaternion.span(),
+                    "quaternion must not be zero".to_string(),
+                ));
+            }
+            camera.orientation = Some(Mat4::from_quaternion(w, x, y, z));
+        }
+        (None, None) => {}
+    }
+    if camera.orientation.is_none() {
+        // lookat and vup only frame the view when no rotation replaces them
+        let view = camera.lookat - camera.lookfrom;
+        if view.length_squared() < 1e-16 {
+            let span = desc.lookat.as_ref().or(desc.lookfrom.as_ref());
+            return Err(SceneError {
+                line: span.map(|point| line_of(text, point.span().start)),
+                message: "lookfrom and lookat must be different points".to_string(),
+            });
+        }
+        let vup = camera.vup;
+        if vup.length_squared() < 1e-16 || vup.unit().cross(view.unit()).length_squared() < 1e-16 {
+            let span = desc
+                .vup
+                .as_ref()
+                .or(desc.lookat.as_ref())
+                .or(desc.lookfrom.as_ref());
+            return Err(SceneError {
+                line: span.map(|vector| line_of(text, vector.span().start)),
+                message: format!(
+                    "vup must not be parallel to the view direction, got [{}, {}, {}]",
+                    vup.x(),
+                    vup.y(),
+                    vup.z()
+                ),
+            });
+        }
+    }
+    if let Some(defocus_angle) = &desc.defocus_angle {
+        let value = *defocus_angle.get_ref();
+        if value.is_nan() || value < 0.0 {
+            return Err(SceneError::at(
+                text,
+                defocus_angle.span(),
+                format!("defocus_angle must not be negative, got {value}"),
+            ));
+        }
+        camera.defocus_angle = value;
+    }
+    if let Some(focus_dist) = &desc.focus_dist {
+        camera.focus_dist = positive(text, focus_dist, "focus_dist")?;
+    }
+    if let Some(aperture) = &desc.aperture {
+        camera.aperture = build_aperture(text, base_dir, aperture)?;
+    }
+    if let Some(shift) = desc.shift {
+        camera.shift = shift;
+    }
+    if let Some(tilt) = &desc.tilt {
+        let [x, y] = *tilt.get_ref();
+        if x.abs() >= 90.0 || y.abs() >= 90.0 {
+            return Err(SceneError::at(
+                text,
+                tilt.span(),
+                format!("tilt must be in (-90, 90) degrees, got [{x}, {y}]"),
+            ));
+        }
+        camera.tilt = [x, y];
+    }
+    if let Some(background) = &desc.background {
+        camera.background = build_background(text, base_dir, background)?;
+    }
+    if let Some(shutter_open) = desc.shutter_open {
+        camera.shutter_open = shutter_open;
+    }
+    if let Some(shutter_close) = &desc.shutter_close {
+        camera.shutter_close = *shutter_close.get_ref();
+    }
+    if camera.shutter_close < camera.shutter_open {
+        let span = desc.shutter_close.as_ref().map(|close| close.span());
+        return Err(SceneError {
+            line: span.map(|span| line_of(text, span.start)),
+            message: format!(
+                "shutter_close must not be before shutter_open, got [{}, {}]",
+                camera.shutter_open, camera.shutter_close
+            ),
+        });
+    }
+    if let Some(seed) = desc.seed {
+        camera.seed = seed;
+    }
+    if let Some(threads) = desc.threads {
+        camera.threads = threads;
+    }
+
+    Ok(camera)
+}
+
+fn build_projection(text: &str, desc: &Spanned<ProjectionDesc>) -> Result<Projection, SceneError> {
+    let span = desc.span();
+    let desc = desc.get_ref();
+    let require = |field: &Option<Spanned<f64>>, field_name: &str| match field {
+        Some(value) => positive(text, value, field_name),
+        None => Err(SceneError::at(
+            text,
+            span.clone(),
+            format!("{} projection needs `{field_name}`", desc.kind.get_ref()),
+        )),
+    };
+
+    match desc.kind.get_ref().as_str() {
+        "perspective" => Ok(Projection::Perspective),
+        "orthographic" => Ok(Projection::Orthographic {
+            height: require(&desc.height, "height")?,
+        }),
+        "fisheye" => {
+            let fov = require(&desc.fov, "fov")?;
+            if fov > 360.0 {
+                let span = desc.fov.as_ref().unwrap().span();
+                return Err(SceneError::at(
+                    text,
+                    span,
+                    format!("fov must be at most 360 degrees, got {fov}"),
+                ));
+            }
+            Ok(Projection::Fisheye { fov })
+        }
+        "equirectangular" => Ok(Projection::Equirectangular),
+        other => Err(SceneError::at(
+            text,
+            desc.kind.span(),
+            format!(
+                "unknown projection type `{other}`, expected perspective, orthographic, fisheye \
+                 or equirectangular"
+            ),
+        )),
+    }
+}
+
+fn build_aperture(
+    text: &str,
+    base_dir: &Path,
+    desc: &Spanned<ApertureDesc>,
+) -> Result<Aperture, SceneError> {
+    let span = desc.span();
+    let desc = desc.get_ref();
+    let require = |field_name: &str| {
+        SceneError::at(
+            text,
+            span.clone(),
+            format!("{} aperture needs `{field_name}`", desc.kind.get_ref()),
+        )
+    };
+
+    match desc.kind.get_ref().as_str() {
+        "disk" => Ok(Aperture::Disk),
+        "polygon" => {
+            let blades = desc.blades.as_ref().ok_or_else(|| require("blades"))?;
+            if *blades.get_ref() < 3 {
+                return Err(SceneError::at(
+                    text,
+                    blades.span(),
+                    format!("blades must be at least 3, got {}", blades.get_ref()),
+                ));
+            }
+            Ok(Aperture::Polygon {
+                blades: *blades.get_ref(),
+                rotation: desc.rotation.unwrap_or(0.0),
+            })
+        }
+        "image" => {
+            let path = desc.path.as_ref().ok_or_else(|| require("path"))?;
+            let mask = ApertureMask::load(base_dir.join(path.get_ref())).map_err(|err| {
+                SceneError::at(
+                    text,
+                    path.span(),
+                    format!("cannot load image `{}`: {err}", path.get_ref()),
+                )
+            })?;
+            Ok(Aperture::Mask(Arc::new(mask)))
+        }
+        other => Err(SceneError::at(
+            text,
+            desc.kind.span(),
+            format!("unknown aperture type `{other}`, expected disk, polygon or image"),
+        )),
+    }
+}
+
+fn build_background(
+    text: &str,
+    base_dir: &Path,
+    desc: &Spanned<BackgroundDesc>,
+) -> Result<Background, SceneError> {
+    let span = desc.span();
+    let desc = desc.get_ref();
+    let require = |field: &Option<Spanned<[f64; 3]>>, field_name: &str| match field {
+        Some(value) => color(text, value),
+        None => Err(SceneError::at(
+            text,
+            span.clone(),
+            format!("background needs `{field_name}`"),
+        )),
+    };
+
+    match desc.kind.get_ref().as_str() {
+        "none" => Ok(Background::None),
+        "solid" => Ok(Background::Solid(require(&desc.color, "color")?)),
+        "gradient" => Ok(Background::Gradient {
+            bottom: require(&desc.bottom, "bottom")?,
+            top: require(&desc.top, "top")?,
+        }),
+        "environment" => {
+            let path = desc.path.as_ref().ok_or_else(|| {
+                SceneError::at(text, span.clone(), "background needs `path`".to_string())
+            })?;
+            let map = EnvironmentMap::load(base_dir.join(path.get_ref())).map_err(|err| {
+                SceneError::at(
+                    text,
+                    path.span(),
+                    format!("cannot load image `{}`: {err}", path.get_ref()),
+                )
+            })?;
+            let map = match &desc.intensity {
+                Some(intensity) => map.with_intensity(positive(text, intensity, "intensity")?),
+                None => map,
+            };
+            Ok(Background::Environment(Arc::new(map)))
+        }
+        other => Err(SceneError::at(
+            text,
+            desc.kind.span(),
+            format!(
+                "unknown background type `{other}`, expected none, solid, gradient or \
+                 environment"
+            ),
+        )),
+    }
+}
+
+fn build_dispersion(text: &str, desc: &Spanned<DispersionDesc>) -> Result<Dispersion, SceneError> {
+    let span = desc.span();
+    let desc = desc.get_ref();
+    let require = |field_name: &str| {
+        SceneError::at(
+            text,
+            span.clone(),
+            format!("{} dispersion needs `{field_name}`", desc.kind.get_ref()),
+        )
+    };
+
+    match (desc.kind.get_ref().as_str(), desc.a, desc.b, desc.c) {
+        ("cauchy", Some(a), Some(Coefficients::One(b)), None) => Ok(Dispersion::Cauchy { a, b }),
+        ("cauchy", ..) => Err(require("a` and `b")),
+        ("sellmeier", None, Some(Coefficients::Three(b)), Some(c)) => {
+            Ok(Dispersion::Sellmeier { b, c })
+        }
+        ("sellmeier", ..) => Err(require("b` and `c` triples, and no `a")),
+        (glass, ..) => Dispersion::named(glass).ok_or_else(|| {
+            SceneError::at(
+                text,
+                desc.kind.span(),
+                format!(
+                    "unknown dispersion type `{glass}`, expected cauchy, sellmeier, bk7, \
+                     fused_silica or diamond"
+                ),
+            )
+        }),
+    }
+}
+
+fn build_texture(
+    text: &str,
+    base_dir: &Path,
+    name: &str,
+    desc: &Spanned<TextureDesc>,
+    rng: &mut RtRng,
+) -> Result<Arc<dyn Texture>, SceneError> {
+    let span = desc.span();
+    let desc = desc.get_ref();
+    let require = |field_name: &str| {
+        SceneError::at(
+            text,
+            span.clone(),
+            format!("texture `{name}` needs `{field_name}`"),
+        )
+    };
+
+    let tex: Arc<dyn Texture> = match desc.kind.get_ref().as_str() {
+        "solid" => {
+            let albedo = desc.color.as_ref().ok_or_else(|| require("color"))?;
+            Arc::new(SolidColor::new(color(text, albedo)?))
+        }
+        "checker" => {
+            let scale = desc.scale.as_ref().ok_or_else(|| require("scale"))?;
+            let even = desc.even.as_ref().ok_or_else(|| require("even"))?;
+            let odd = desc.odd.as_ref().ok_or_else(|| require("odd"))?;
+            Arc::new(CheckerTexture::from_colors(
+                positive(text, scale, "scale")?,
+                color(text, even)?,
+                color(text, odd)?,
+            ))
+        }
+        "image" => {
+            let path = desc.path.as_ref().ok_or_else(|| require("path"))?;
+            let image = ImageTexture::load(base_dir.join(path.get_ref())).map_err(|err| {
+                SceneError::at(
+                    text,
+                    path.span(),
+                    format!("cannot load image `{}`: {err}", path.get_ref()),
+                )
+            })?;
+            Arc::new(image)
+        }
+        "noise" => {
+            let scale = match &desc.scale {
+                Some(scale) => positive(text, scale, "scale")?,
+                None => 1.0,
+            };
+            Arc::new(NoiseTexture::new(scale, rng))
+        }
+        other => {
+            return Err(SceneError::at(
+                text,
+                desc.kind.span(),
+                format!("unknown texture type `{other}`, expected solid, checker, image or noise"),
+            ))
+        }
+    };
+
+    Ok(tex)
+}
+
+fn build_material(
+    text: &str,
+    name: &str,
+    desc: &Spanned<MaterialDesc>,
+    textures: &BTreeMap<&str, Arc<dyn Texture>>,
+) -> Result<Arc<dyn Material>, SceneError> {
+    let span = desc.span();
+    let desc = desc.get_ref();
+    let require = |field_name: &str| {
+        SceneError::at(
+            text,
+            span.clone(),
+            format!("material `{name}` needs `{field_name}`"),
+        )
+    };
+    // either a named texture or a plain color
+    let texture_or = |plain: &Option<Spanned<[f64; 3]>>,
+                      plain_name: &str|
+     -> Result<Arc<dyn Texture>, SceneError> {
+        match (&desc.texture, plain) {
+            (Some(tex_name), _) => textures
+                .get(tex_name.get_ref().as_str())
+                .cloned()
+                .ok_or_else(|| {
+                    SceneError::at(
+                        text,
+                        tex_name.span(),
+                        format!("unknown texture `{}`", tex_name.get_ref()),
+                    )
+                }),
+            (None, Some(value)) => Ok(Arc::new(SolidColor::new(color(text, value)?))),
+            (None, None) => Err(require(&format!("{plain_name}` or `texture"))),
+        }
+    };
+    let albedo_texture = || texture_or(&desc.albedo, "albedo");
+
+    let mat: Arc<dyn Material> = match desc.kind.get_ref().as_str() {
+        "lambertian" => Arc::new(Lambertian::textured(albedo_texture()?)),
+        "metal" => {
+            let fuzz = match &desc.fuzz {
+                Some(fuzz) => in_unit_range(text, fuzz, "fuzz")?,
+                None => 0.0,
+            };
+            Arc::new(Metal::textured(albedo_texture()?, fuzz))
+        }
+        "conductor" => {
+            let roughness = match &desc.roughness {
+                Some(roughness) => in_unit_range(text, roughness, "roughness")?,
+                None => 0.0,
+            };
+            match (&desc.metal, desc.eta, desc.k) {
+                (Some(metal), None, None) => Conductor::named(metal.get_ref(), roughness)
+                    .map(Arc::new)
+                    .ok_or_else(|| {
+                        SceneError::at(
+                            text,
+                            metal.span(),
+                            format!(
+                                "unknown metal `{}`, expected aluminium, copper, gold or silver",
+                                metal.get_ref()
+                            ),
+                        )
+                    })?,
+                (None, Some(eta), Some(k)) => {
+                    Arc::new(Conductor::new(to_vec3(eta), to_vec3(k), roughness))
+                }
+                _ => return Err(require("metal` or both `eta` and `k")),
+            }
+        }
+        "diffuse_light" => Arc::new(DiffuseLight::textured(texture_or(&desc.emit, "emit")?)),
+        "isotropic" => Arc::new(Isotropic::textured(albedo_texture()?)),
+        "dielectric" => match (&desc.refraction_index, &desc.dispersion) {
+            (Some(refraction_index), None) => Arc::new(Dielectric::new(positive(
+                text,
+                refraction_index,
+                "refraction_index",
+            )?)),
+            (None, Some(dispersion)) => {
+                Arc::new(Dielectric::dispersive(build_dispersion(text, dispersion)?))
+            }
+            _ => return Err(require("refraction_index` or `dispersion")),
+        },
+        other => {
+            return Err(SceneError::at(
+                text,
+                desc.kind.span(),
+                format!(
+                    "unknown material type `{other}`, expected lambertian, metal, conductor, \
+                     dielectric, diffuse_light or isotropic"
+                ),
+            ))
+        }
+    };
+
+    Ok(mat)
+}
+
+/// Meshes already loaded, by path and default material, shared by all the objects using them.
+type MeshCache = BTreeMap<(String, String), Arc<TriangleMesh>>;
+
+fn build_object(
+    text: &str,
+    base_dir: &Path,
+    desc: &Spanned<ObjectDesc>,
+    materials: &BTreeMap<&str, Arc<dyn Material>>,
+    meshes: &mut MeshCache,
+) -> Result<Box<dyn Hittable>, SceneError> {
+    let span = desc.span();
+    let desc = desc.get_ref();
+
+    let mat_name = desc.material.get_ref();
+    let Some(mat) = materials.get(mat_name.as_str()) else {
+        return Err(SceneError::at(
+            text,
+            desc.material.span(),
+            format!("unknown material `{mat_name}`"),
+        ));
+    };
+
+    let kind = desc.kind.get_ref().as_str();
+    let require = |field: Option<[f64; 3]>, field_name: &str| {
+        field.map(to_vec3).ok_or_else(|| {
+            SceneError::at(text, span.clone(), format!("{kind} needs `{field_name}`"))
+        })
+    };
+
+    let object: Box<dyn Hittable> = match kind {
+        "sphere" => {
+            let center = require(desc.center, "center")?;
+            let Some(radius) = &desc.radius else {
+                return Err(SceneError::at(
+                    text,
+                    span,
+                    "sphere needs `radius`".to_string(),
+                ));
+            };
+            let radius = positive(text, radius, "radius")?;
+            match desc.center2 {
+                Some(center2) => Box::new(Sphere::moving(
+                    center,
+                    to_vec3(center2),
+                    radius,
+                    mat.clone(),
+                )),
+                None => Box::new(Sphere::new(center, radius, mat.clone())),
+            }
+        }
+        "quad" => Box::new(Quad::new(
+            require(desc.q, "q")?,
+            require(desc.u, "u")?,
+            require(desc.v, "v")?,
+            mat.clone(),
+        )),
+        "triangle" => Box::new(Triangle::new(
+            require(desc.a, "a")?,
+            require(desc.b, "b")?,
+            require(desc.c, "c")?,
+            mat.clone(),
+        )),
+        "box" => Box::new(make_box(
+            require(desc.a, "a")?,
+            require(desc.b, "b")?,
+            mat.clone(),
+        )),
+        "mesh" => {
+            let Some(path) = &desc.path else {
+                return Err(SceneError::at(text, span, "mesh needs `path`".to_string()));
+            };
+            let key = (path.get_ref().clone(), mat_name.clone());
+            let mesh = match meshes.get(&key) {
+                Some(mesh) => mesh.clone(),
+                None => {
+                    let mesh = TriangleMesh::load_obj(base_dir.join(path.get_ref()), mat.clone())
+                        .map_err(|err| {
+                        SceneError::at(
+                            text,
+                            path.span(),
+                            format!("cannot load mesh `{}`: {err}", path.get_ref()),
+                        )
+                    })?;
+                    meshes.entry(key).or_insert(Arc::new(mesh)).clone()
+                }
+            };
+            Box::new(mesh)
+        }
+        other => {
+            return Err(SceneError::at(
+                text,
+                desc.kind.span(),
+                format!(
+                    "unknown object type `{other}`, expected sphere, quad, triangle, box or mesh"
+                ),
+            ))
+        }
+    };
+
+    let object = place_object(text, desc, object)?;
+
+    // the shape only bounds the volume, the material is its phase function
+    match &desc.density {
+        Some(density) => Ok(Box::new(ConstantMedium::with_phase_function(
+            object,
+            positive(text, density, "density")?,
+            mat.clone(),
+        ))),
+        None => Ok(object),
+    }
+}
+
+/// Wrap the object in the instances given by its `scale`, `rotate` and `translate` fields.
+fn place_object(
+    text: &str,
+    desc: &ObjectDesc,
+    object: Box<dyn Hittable>,
+) -> Result<Box<dyn Hittable>, SceneError> {
+    let mut object = object;
+
+    if let Some(scale) = &desc.scale {
+        let factor = to_vec3(*scale.get_ref());
+        object = match Transform::scale(Arc::from(object), factor) {
+            Some(scaled) => Box::new(scaled),
+            None => {
+                return Err(SceneError::at(
+                    text,
+                    scale.span(),
+                    format!("scale must not be zero, got {:?}", scale.get_ref()),
+                ))
+            }
+        };
+    }
+
+    match (desc.rotate, &desc.rotate_axis) {
+        (Some(angle), None) => object = Box::new(RotateY::new(Arc::from(object), angle)),
+        (Some(angle), Some(axis)) => {
+            let axis_vec = to_vec3(*axis.get_ref());
+            if axis_vec.length_squared() == 0.0 {
+                return Err(SceneError::at(
+                    text,
+                    axis.span(),
+                    "rotate_axis must not be zero".to_string(),
+                ));
+            }
+            object = Box::new(Transform::rotate(Arc::from(object), axis_vec, angle));
+        }
+        (None, Some(axis)) => {
+            return Err(SceneError::at(
+                text,
+                axis.span(),
+                "rotate_axis needs `rotate`".to_string(),
+            ))
+        }
+        (None, None) => {}
+    }
+
+    if let Some(offset) = desc.translate {
+        object = Box::new(Translate::new(Arc::from(object), to_vec3(offset)));
+    }
+
+    Ok(object)
+}
+
+fn to_vec3(v: [f64; 3]) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+fn color(text: &str, value: &Spanned<[f64; 3]>) -> Result<Vec3, SceneError> {
+    let rgb = *value.get_ref();
+    if rgb.iter().any(|c| *c < 0.0) {
+        return Err(SceneError::at(
+            text,
+            value.span(),
+            format!("color components must not be negative, got {rgb:?}"),
+        ));
+    }
+    Ok(to_vec3(rgb))
+}
+
+fn positive(text: &str, value: &Spanned<f64>, name: &str) -> Result<f64, SceneError> {
+    let v = *value.get_ref();
+    if v > 0.0 {
+        Ok(v)
+    } else {
+        Err(SceneError::at(
+            text,
+            value.span(),
+            format!("{name} must be positive, got {v}"),
+        ))
+    }
+}
+
+fn in_unit_range(text: &str, value: &Spanned<f64>, name: &str) -> Result<f64, SceneError> {
+    let v = *value.get_ref();
+    if (0.0..=1.0).contains(&v) {
+        Ok(v)
+    } else {
+        Err(SceneError::at(
+            text,
+            value.span(),
+            format!("{name} must be in [0, 1], got {v}"),
+        ))
+    }
+}
+
+/// 1-based line of the byte `offset` in `text`.
+fn line_of(text: &str, offset: usize) -> usize {
+    text[..offset.min(text.len())].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::HitRecord;
+    use crate::interval::Interval;
+    use crate::ray::Ray;
+    use crate::vec3::Point3;
+
+    #[test]
+    fn test_parse_example_scene() {
+        let scene = parse_scene(include_str!("../scenes/three_spheres.toml")).unwrap();
+
+        assert_eq!(scene.objects.len(), 4);
+        assert_eq!(scene.camera.image_width, 400);
+        assert_eq!(scene.camera.lookfrom, Point3::new(13.0, 2.0, 3.0));
+        assert_eq!(scene.camera.samples_per_pixel, 32);
+
+        // materials are numbered by name: bronze, brown, glass, ground
+        let id = |object: &dyn Hittable, top: Point3| {
+            let r = Ray::new(top + Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+            let mut rec = HitRecord::default();
+            assert!(object.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+            rec.mat.unwrap().id()
+        };
+        assert_eq!(id(&*scene.objects[0], Point3::new(10.0, 0.0, 10.0)), 4);
+        assert_eq!(id(&*scene.objects[1], Point3::new(0.0, 2.0, 0.0)), 3);
+        assert_eq!(id(&*scene.objects[2], Point3::new(-4.0, 2.0, 0.0)), 2);
+        assert_eq!(id(&*scene.objects[3], Point3::new(4.0, 2.0, 0.0)), 1);
+    }
+
+    #[test]
+    fn test_unknown_material_reference() {
+        let text = r#"
+[materials.ground]
+type = "lambertian"
+albedo = [0.5, 0.5, 0.5]
+
+[[objects]]
+type = "sphere"
+center = [0.0, -1000.0, 0.0]
+radius = 1000.0
+material = "grund"
+"#;
+        let err = parse_scene(text).err().unwrap();
+        assert_eq!(err.line, Some(10));
+        assert!(err.message.contains("grund"), "{err}");
+    }
+
+    #[test]
+    fn test_invalid_parameter() {
+        let text = r#"
+[materials.chrome]
+type = "metal"
+albedo = [0.8, 0.8, 0.8]
+fuzz = 1.5
+"#;
+        let err = parse_scene(text).err().unwrap();
+        assert_eq!(err.line, Some(5));
+        assert!(err.message.contains("fuzz"), "{err}");
+    }
+
+    #[test]
+    fn test_missing_parameter() {
+        let text = r#"
+[camera]
+image_width = 10
+
+[materials.glass]
+type = "dielectric"
+"#;
+        let err = parse_scene(text).err().unwrap();
+        // points at the table header of the material
+        assert_eq!(err.line, Some(5));
+        assert!(err.message.contains("refraction_index"), "{err}");
+    }
+
+    #[test]
+    fn test_planar_objects() {
+        let text = r#"
+[materials.white]
+type = "lambertian"
+albedo = [0.73, 0.73, 0.73]
+
+[[objects]]
+type = "quad"
+q = [0.0, 0.0, 0.0]
+u = [1.0, 0.0, 0.0]
+v = [0.0, 1.0, 0.0]
+material = "white"
+
+[[objects]]
+type = "triangle"
+a = [0.0, 0.0, 0.0]
+b = [1.0, 0.0, 0.0]
+c = [0.0, 1.0, 0.0]
+material = "white"
+
+[[objects]]
+type = "box"
+a = [0.0, 0.0, 0.0]
+b = [1.0, 2.0, 3.0]
+material = "white"
+
+[[objects]]
+type = "quad"
+q = [0.0, 0.0, 0.0]
+u = [1.0, 0.0, 0.0]
+material = "white"
+"#;
+        let err = parse_scene(text).err().unwrap();
+        assert_eq!(err.line, Some(26));
+        assert!(err.message.contains("`v`"), "{err}");
+
+        let valid = &text[..text.rfind("[[objects]]").unwrap()];
+        let scene = parse_scene(valid).unwrap();
+        assert_eq!(scene.objects.len(), 3);
+        let bbox = scene.objects[2].bounding_box();
+        // the flat faces of the box are padded a little
+        assert!((bbox.y.max - 2.0).abs() < 1e-3 && (bbox.z.max - 3.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_load_mesh_scene() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/cube_mesh.toml");
+        let scene = load_scene(path).unwrap();
+        assert_eq!(scene.objects.len(), 2);
+
+        let text = format!("{}light = true\n", include_str!("../scenes/cube_mesh.toml"));
+        let err = build_scene(&text, Path::new(path).parent().unwrap())
+            .err()
+            .unwrap();
+        assert!(err.message.contains("light"), "{err}");
+
+        let text = include_str!("../scenes/cube_mesh.toml").replace("cube.obj", "missing.obj");
+        let err = build_scene(&text, Path::new(path).parent().unwrap())
+            .err()
+            .unwrap();
+        assert_eq!(err.line, Some(30));
+        assert!(err.message.contains("missing.obj"), "{err}");
+    }
+
+    #[test]
+    fn test_textures() {
+        let text = r#"
+[textures.checker]
+type = "checker"
+scale = 0.5
+even = [0.2, 0.3, 0.1]
+odd = [0.9, 0.9, 0.9]
+
+[textures.marble]
+type = "noise"
+scale = 4.0
+
+[materials.ground]
+type = "lambertian"
+texture = "checker"
+
+[materials.stone]
+type = "metal"
+texture = "marble"
+fuzz = 0.2
+
+[materials.wood]
+type = "lambertian"
+texture = "oak"
+"#;
+        let err = parse_scene(text).err().unwrap();
+        assert_eq!(err.line, Some(23));
+        assert!(err.message.contains("oak"), "{err}");
+
+        let valid = &text[..text.rfind("[materials.wood]").unwrap()];
+        assert!(parse_scene(valid).is_ok());
+
+        let missing_image = "[textures.earth]\ntype = \"image\"\npath = \"missing.jpg\"\n";
+        let err = parse_scene(missing_image).err().unwrap();
+        assert_eq!(err.line, Some(3));
+    }
+
+    #[test]
+    fn test_load_image_scene() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/earth.toml");
+        let scene = load_scene(path).unwrap();
+        assert_eq!(scene.objects.len(), 1);
+    }
+
+    #[test]
+    fn test_lights_and_background() {
+        let scene = parse_scene(include_str!("../scenes/cornell_box.toml")).unwrap();
+        assert_eq!(scene.objects.len(), 8);
+        assert_eq!(scene.camera.background, Background::None);
+        assert_eq!(scene.lights.objects.len(), 1);
+
+        let text = r#"
+[camera.background]
+type = "gradient"
+bottom = [1.0, 1.0, 1.0]
+
+[materials.light]
+type = "diffuse_light"
+emit = [4.0, 4.0, 4.0]
+"#;
+        let err = parse_scene(text).err().unwrap();
+        assert_eq!(err.line, Some(2));
+        assert!(err.message.contains("`top`"), "{err}");
+
+        let text = text.replace(
+            "type = \"gradient\"",
+            "type = \"solid\"\ncolor = [0.5, 0.5, 0.5]",
+        );
+        let text = text.replace("bottom", "# bottom");
+        let scene = parse_scene(&text).unwrap();
+        assert_eq!(
+            scene.camera.background,
+            Background::Solid(Vec3::new(0.5, 0.5, 0.5))
+        );
+
+        let text = text.replace("emit", "albedo");
+        let err = parse_scene(&text).err().unwrap();
+        assert!(err.message.contains("`emit`"), "{err}");
+
+        let text = "[camera.background]\ntype = \"environment\"\n";
+        let err = parse_scene(text).err().unwrap();
+        assert!(err.message.contains("`path`"), "{err}");
+        let err = parse_scene(&format!("{text}path = \"missing.hdr\"\n"))
+            .err()
+            .unwrap();
+        assert_eq!(err.line, Some(3));
+        assert!(err.message.contains("missing.hdr"), "{err}");
+    }
+
+    #[test]
+    fn test_conductors() {
+        let text = r#"
+[materials.gold]
+type = "conductor"
+metal = "gold"
+roughness = 0.3
+
+[materials.custom]
+type = "conductor"
+eta = [0.2, 0.9, 1.1]
+k = [3.9, 2.5, 2.1]
+"#;
+        parse_scene(text).unwrap();
+        let scene = parse_scene(include_str!("../scenes/metals.toml")).unwrap();
+        assert_eq!(scene.objects.len(), 6);
+
+        let err = parse_scene(&text.replace("\"gold\"\n", "\"brass\"\n"))
+            .err()
+            .unwrap();
+        assert_eq!(err.line, Some(4));
+        assert!(err.message.contains("brass"), "{err}");
+
+        let err = parse_scene(&text.replace("k = [3.9, 2.5, 2.1]", ""))
+            .err()
+            .unwrap();
+        assert!(err.message.contains("`eta` and `k`"), "{err}");
+
+        let err = parse_scene(&text.replace("0.3", "1.3")).err().unwrap();
+        assert_eq!(err.line, Some(5));
+    }
+
+    #[test]
+    fn test_dispersion() {
+        let scene = parse_scene(include_str!("../scenes/prism.toml")).unwrap();
+        assert_eq!(scene.objects.len(), 17);
+
+        let text = r#"
+[materials.diamond]
+type = "dielectric"
+
+[materials.diamond.dispersion]
+type = "diamond"
+"#;
+        parse_scene(text).unwrap();
+        parse_scene(&text.replace(
+            "\"diamond\"\n",
+            "\"sellmeier\"\nb = [1.0, 0.2, 1.0]\nc = [0.006, 0.02, 100.0]\n",
+        ))
+        .unwrap();
+
+        let err = parse_scene(&text.replace("\"diamond\"\n", "\"cauchy\"\na = 1.5\n"))
+            .err()
+            .unwrap();
+        assert!(err.message.contains("`a` and `b`"), "{err}");
+        let err = parse_scene(&text.replace("\"diamond\"\n", "\"quartz\"\n"))
+            .err()
+            .unwrap();
+        assert_eq!(err.line, Some(6));
+
+        let err =
+            parse_scene(&text.replace("[materials.diamond.dispersion]\ntype = \"diamond\"", ""))
+                .err()
+                .unwrap();
+        assert!(
+            err.message.contains("`refraction_index` or `dispersion`"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_volumes() {
+        let text = include_str!("../scenes/cornell_smoke.toml");
+        let scene = parse_scene(text).unwrap();
+        assert_eq!(scene.objects.len(), 8);
+
+        let empty = text.replace("density = 0.01", "density = 0.0");
+        let err = parse_scene(&empty).err().unwrap();
+        assert!(err.message.contains("density"), "{err}");
+
+        let text = format!(
+            "{}light = true\ndensity = 0.5\n",
+            &text[..text.rfind("density").unwrap()]
+        );
+        let err = parse_scene(&text).err().unwrap();
+        assert!(err.message.contains("volume"), "{err}");
+    }
+
+    #[test]
+    fn test_motion_blur() {
+        let text = r#"
+[camera]
+shutter_open = 0.5
+shutter_close = 0.25
+
+[materials.white]
+type = "lambertian"
+albedo = [0.73, 0.73, 0.73]
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+center2 = [0.0, 1.0, 0.0]
+radius = 0.5
+material = "white"
+"#;
+        let err = parse_scene(text).err().unwrap();
+        assert_eq!(err.line, Some(4));
+
+        let scene = parse_scene(&text.replace("0.25", "1.0")).unwrap();
+        assert_eq!(scene.camera.shutter_open, 0.5);
+        assert!(scene.objects[0].bounding_box().y.max >= 1.5);
+
+        let light = format!("{}light = true\n", text.replace("0.25", "1.0"));
+        let err = parse_scene(&light).err().unwrap();
+        assert!(err.message.contains("moving sphere"), "{err}");
+    }
+
+    #[test]
+    fn test_adaptive_sampling() {
+        let text = r#"
+[camera]
+samples_per_pixel = 16
+noise_threshold = 0.05
+max_samples_per_pixel = 8
+"#;
+        let err = parse_scene(text).err().unwrap();
+        assert_eq!(err.line, Some(5));
+        assert!(err.message.contains("max_samples_per_pixel"), "{err}");
+
+        let scene = parse_scene(&text.replace("= 8", "= 256")).unwrap();
+        assert_eq!(scene.camera.noise_threshold, 0.05);
+        assert_eq!(scene.camera.max_samples_per_pixel, 256);
+
+        let err = parse_scene(&text.replace("0.05", "-0.05")).err().unwrap();
+        assert_eq!(err.line, Some(4));
+    }
+
+    #[test]
+    fn test_russian_roulette() {
+        let text = "[camera]\nmax_depth = 4\nrussian_roulette = 3\n";
+        assert_eq!(parse_scene(text).unwrap().camera.max_depth, 4);
+        let err = parse_scene(&text.replace("= 3", "= -3")).err().unwrap();
+        assert_eq!(err.line, Some(3));
+    }
+
+    #[test]
+    fn test_projections() {
+        let scene = parse_scene(include_str!("../scenes/panorama.toml")).unwrap();
+        assert_eq!(scene.camera.projection, Projection::Equirectangular);
+
+        let text = "[camera.projection]\ntype = \"fisheye\"\nfov = 180.0\n";
+        let camera = parse_scene(text).unwrap().camera;
+        assert_eq!(camera.projection, Projection::Fisheye { fov: 180.0 });
+        let err = parse_scene(&text.replace("180.0", "400.0")).err().unwrap();
+        assert_eq!(err.line, Some(3));
+        let err = parse_scene(&text.replace("fov = 180.0\n", ""))
+            .err()
+            .unwrap();
+        assert!(err.message.contains("`fov`"), "{err}");
+
+        let text = "[camera.projection]\ntype = \"orthographic\"\nheight = 3.0\n";
+        let camera = parse_scene(text).unwrap().camera;
+        assert_eq!(camera.projection, Projection::Orthographic { height: 3.0 });
+    }
+
+    #[test]
+    fn test_camera_orientation_and_lens() {
+        let text = r#"
+[camera]
+roll = 15.0
+quaternion = [1.0, 0.0, 1.0, 0.0]
+shift = [0.0, 0.25]
+tilt = [-10.0, 0.0]
+
+[camera.aperture]
+type = "polygon"
+blades = 6
+"#;
+        let camera = parse_scene(text).unwrap().camera;
+        assert_eq!(camera.roll, 15.0);
+        assert_eq!(camera.shift, [0.0, 0.25]);
+        assert_eq!(camera.tilt, [-10.0, 0.0]);
+        assert_eq!(
+            camera.aperture,
+            Aperture::Polygon {
+                blades: 6,
+                rotation: 0.0
+            }
+        );
+        // a quarter turn around y
+        let forward = camera
+            .orientation
+            .unwrap()
+            .transform_vector(&Vec3::new(0.0, 0.0, -1.0));
+        assert!((forward - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-12);
+
+        let err = parse_scene(&text.replace("blades = 6", "blades = 2"))
+            .err()
+            .unwrap();
+        assert_eq!(err.line, Some(10));
+        let err = parse_scene(&text.replace("-10.0", "-90.0")).err().unwrap();
+        assert_eq!(err.line, Some(6));
+
+        let skewed = text.replace(
+            "quaternion = [1.0, 0.0, 1.0, 0.0]",
+            "orientation = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 1.0, 1.0]]",
+        );
+        let err = parse_scene(&skewed).err().unwrap();
+        assert!(err.message.contains("rotation"), "{err}");
+        let both = text.replace(
+            "roll = 15.0",
+            "orientation = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]",
+        );
+        assert!(parse_scene(&both).is_err());
+
+        let scene = parse_scene(include_str!("../scenes/bokeh.toml")).unwrap();
+        assert!(matches!(
+            scene.camera.aperture,
+            Aperture::Polygon { blades: 6, .. }
+        ));
+    }
+
+    #[test]
+    fn test_invalid_camera_frame() {
+        let text = r#"
+[camera]
+max_depth = 10
+lookfrom = [0.0, 2.0, 5.0]
+lookat = [0.0, 0.0, 0.0]
+vup = [0.0, 1.0, 0.0]
+defocus_angle = 0.5
+"#;
+        assert!(parse_scene(text).is_ok());
+
+        let err = parse_scene(&text.replace("[0.0, 2.0, 5.0]", "[0.0, 0.0, 0.0]"))
+            .err()
+            .unwrap();
+        assert_eq!(err.line, Some(5));
+        assert!(err.message.contains("lookfrom"), "{err}");
+        let err = parse_scene(&text.replace("[0.0, 1.0, 0.0]", "[0.0, -0.4, -1.0]"))
+            .err()
+            .unwrap();
+        assert_eq!(err.line, Some(6));
+        assert!(err.message.contains("vup"), "{err}");
+        let err = parse_scene(&text.replace("= 10", "= -1")).err().unwrap();
+        assert_eq!(err.line, Some(3));
+        let err = parse_scene(&text.replace("0.5", "-0.5")).err().unwrap();
+        assert_eq!(err.line, Some(7));
+
+        // an explicit rotation leaves vup unused
+        let rotated = text.replace(
+            "vup = [0.0, 1.0, 0.0]",
+            "vup = [0.0, 0.0, 0.0]\nquaternion = [1.0, 0.0, 0.0, 0.0]",
+        );
+        assert!(parse_scene(&rotated).is_ok());
+    }
+
+    #[test]
+    fn test_placed_objects() {
+        let text = r#"
+[materials.white]
+type = "lambertian"
+albedo = [0.73, 0.73, 0.73]
+
+[[objects]]
+type = "box"
+a = [0.0, 0.0, 0.0]
+b = [1.0, 2.0, 1.0]
+material = "white"
+rotate = 90.0
+translate = [10.0, 0.0, 0.0]
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = "white"
+scale = [3.0, 1.0, 1.0]
+rotate = 90.0
+rotate_axis = [0.0, 0.0, 1.0]
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = "white"
+scale = [1.0, 0.0, 1.0]
+"#;
+        let err = parse_scene(text).err().unwrap();
+        assert_eq!(err.line, Some(28));
+
+        let valid = &text[..text.rfind("[[objects]]").unwrap()];
+        let scene = parse_scene(valid).unwrap();
+        // turned a quarter around y, then moved
+        let bbox = scene.objects[0].bounding_box();
+        assert!((bbox.x.min - 10.0).abs() < 1e-3 && (bbox.z.min + 1.0).abs() < 1e-3);
+        // stretched along x, then turned to y
+        let bbox = scene.objects[1].bounding_box();
+        assert!((bbox.y.max - 3.0).abs() < 1e-9 && (bbox.x.max - 1.0).abs() < 1e-9);
+
+        let text = valid.replace("rotate = 90.0\nrotate_axis", "rotate_axis");
+        let err = parse_scene(&text).err().unwrap();
+        assert!(err.message.contains("rotate"), "{err}");
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        let text = "[camera]\nimage_width = 10\nvfov = \"wide\"\n";
+        let err = parse_scene(text).err().unwrap();
+        assert_eq!(err.line, Some(3));
+    }
+}
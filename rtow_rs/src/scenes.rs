@@ -0,0 +1,495 @@
+use std::sync::Arc;
+
+use crate::{
+    background::Background,
+    camera::Camera,
+    color::Color,
+    hittable::Hittable,
+    hittable_list::HittableList,
+    instance::{RotateY, Translate},
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal, Numbered},
+    quad::{make_box, Quad},
+    rtweekend::{random_double, random_double_range, seeded_rng, RtRng},
+    scene::Scene,
+    sphere::Sphere,
+    texture::{CheckerTexture, NoiseTexture},
+    vec3::{Point3, Vec3},
+};
+
+/// The RTOW cover with the camera of the book's final render.
+pub fn cover(seed: u64) -> Scene {
+    let mut camera = Camera::new();
+    camera.aspect_ratio = 16.0 / 9.0;
+    camera.image_width = 1200;
+    camera.samples_per_pixel = 16;
+    camera.max_depth = 8;
+    camera.vfov = 20.0;
+    camera.lookfrom = Point3::new(12.0, 2.0, 3.0);
+    camera.lookat = Point3::new(0.0, 0.0, 0.0);
+    camera.vup = Vec3::new(0.0, 1.0, 0.0);
+    camera.defocus_angle = 0.6;
+    camera.focus_dist = 10.0;
+    camera.seed = seed;
+
+    let mut rng = seeded_rng(seed);
+    Scene {
+        camera,
+        objects: final_scene(&mut rng),
+        lights: HittableList::new(),
+    }
+}
+
+/// The material showcase of RTOW chapter 11: a hollow glass sphere, a diffuse and a metal one.
+pub fn materials(seed: u64) -> Scene {
+    let mut camera = Camera::new();
+    camera.aspect_ratio = 16.0 / 9.0;
+    camera.image_width = 400;
+    camera.samples_per_pixel = 100;
+    camera.max_depth = 50;
+    camera.vfov = 20.0;
+    camera.lookfrom = Point3::new(-2.0, 2.0, 1.0);
+    camera.lookat = Point3::new(0.0, 0.0, -1.0);
+    camera.vup = Vec3::new(0.0, 1.0, 0.0);
+    camera.defocus_angle = 10.0;
+    camera.focus_dist = 3.4;
+    camera.seed = seed;
+
+    let material_ground = numbered(1, Lambertian::new(Color::new(0.8, 0.8, 0.0)));
+    let material_center = numbered(2, Lambertian::new(Color::new(0.1, 0.2, 0.5)));
+    let material_left = numbered(3, Dielectric::new(1.5));
+    let material_bubble = numbered(4, Dielectric::new(1.0 / 1.5));
+    let material_right = numbered(5, Metal::new(Color::new(0.8, 0.6, 0.2), 1.0));
+
+    let objects: Vec<Box<dyn Hittable>> = vec![
+        Box::new(Sphere::new(
+            Point3::new(0.0, -100.5, -1.0),
+            100.0,
+            material_ground,
+        )),
+        Box::new(Sphere::new(
+            Point3::new(0.0, 0.0, -1.2),
+            0.5,
+            material_center,
+        )),
+        Box::new(Sphere::new(
+            Point3::new(-1.0, 0.0, -1.0),
+            0.5,
+            material_left,
+        )),
+        Box::new(Sphere::new(
+            Point3::new(-1.0, 0.0, -1.0),
+            0.4,
+            material_bubble,
+        )),
+        Box::new(Sphere::new(
+            Point3::new(1.0, 0.0, -1.0),
+            0.5,
+            material_right,
+        )),
+    ];
+
+    Scene {
+        camera,
+        objects,
+        lights: HittableList::new(),
+    }
+}
+
+/// The cover scene of RTOW: a grid of 22x22 small random spheres around three large ones.
+pub fn final_scene(rng: &mut RtRng) -> Vec<Box<dyn Hittable>> {
+    random_spheres(rng, false)
+}
+
+/// The RTOW cover where the diffuse spheres bounce up during the exposure, from RTNW chapter 2.
+pub fn bouncing_spheres(seed: u64) -> Scene {
+    let mut camera = Camera::new();
+    camera.aspect_ratio = 16.0 / 9.0;
+    camera.image_width = 400;
+    camera.samples_per_pixel = 100;
+    camera.max_depth = 50;
+    camera.vfov = 20.0;
+    camera.lookfrom = Point3::new(13.0, 2.0, 3.0);
+    camera.lookat = Point3::new(0.0, 0.0, 0.0);
+    camera.vup = Vec3::new(0.0, 1.0, 0.0);
+    camera.defocus_angle = 0.6;
+    camera.focus_dist = 10.0;
+    camera.shutter_open = 0.0;
+    camera.shutter_close = 1.0;
+    camera.seed = seed;
+
+    let mut rng = seeded_rng(seed);
+    Scene {
+        camera,
+        objects: random_spheres(&mut rng, true),
+        lights: HittableList::new(),
+    }
+}
+
+/// `mat` under `id` for the material id pass, the demo scenes number their materials in the
+/// order they make them.
+fn numbered(id: u32, mat: impl Material + 'static) -> Arc<dyn Material> {
+    Arc::new(Numbered::new(id, Arc::new(mat)))
+}
+
+fn random_spheres(rng: &mut RtRng, bouncing: bool) -> Vec<Box<dyn Hittable>> {
+    let mut world: Vec<Box<dyn Hittable>> = Vec::new();
+    let mut count = 0;
+    let mut next_id = || {
+        count += 1;
+        count
+    };
+
+    let ground_material = numbered(next_id(), Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    world.push(Box::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        ground_material,
+    )));
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat = random_double(rng);
+            let center = Point3::new(
+                (a as f64) + 0.9 * random_double(rng),
+                0.2,
+                (b as f64) + 0.9 * random_double(rng),
+            );
+
+            if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
+                if choose_mat < 0.8 && bouncing {
+                    let albedo: Color = Color::random_random(rng) * Color::random_random(rng);
+                    let center2 = center + Vec3::new(0.0, random_double_range(rng, 0.0, 0.5), 0.0);
+                    world.push(Box::new(Sphere::moving(
+                        center,
+                        center2,
+                        0.2,
+                        numbered(next_id(), Lambertian::new(albedo)),
+                    )));
+                    continue;
+                }
+
+                let sphere_material = if choose_mat < 0.8 {
+                    let albedo: Color = Color::random_random(rng) * Color::random_random(rng);
+                    numbered(next_id(), Lambertian::new(albedo))
+                } else if choose_mat < 0.95 {
+                    let albedo = Color::random(rng, 0.5, 1.0);
+                    let fuzz = random_double_range(rng, 0.0, 0.5);
+                    numbered(next_id(), Metal::new(albedo, fuzz))
+                } else {
+                    numbered(next_id(), Dielectric::new(1.5))
+                };
+                world.push(Box::new(Sphere::new(center, 0.2, sphere_material)));
+            }
+        }
+    }
+
+    let material1 = numbered(next_id(), Dielectric::new(1.5));
+    world.push(Box::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        material1,
+    )));
+
+    let material2 = numbered(next_id(), Lambertian::new(Color::new(0.4, 0.2, 0.1)));
+    world.push(Box::new(Sphere::new(
+        Point3::new(-4.0, 1.0, 0.0),
+        1.0,
+        material2,
+    )));
+
+    let material3 = numbered(next_id(), Metal::new(Color::new(0.7, 0.6, 0.5), 0.0));
+    world.push(Box::new(Sphere::new(
+        Point3::new(4.0, 1.0, 0.0),
+        1.0,
+        material3,
+    )));
+
+    world
+}
+
+/// Five colored quads around the view, from RTNW chapter 6.
+pub fn quads(seed: u64) -> Scene {
+    let mut camera = Camera::new();
+    camera.aspect_ratio = 1.0;
+    camera.image_width = 400;
+    camera.samples_per_pixel = 100;
+    camera.max_depth = 50;
+    camera.vfov = 80.0;
+    camera.lookfrom = Point3::new(0.0, 0.0, 9.0);
+    camera.lookat = Point3::new(0.0, 0.0, 0.0);
+    camera.vup = Vec3::new(0.0, 1.0, 0.0);
+    camera.defocus_angle = 0.0;
+    camera.seed = seed;
+
+    let left_red = numbered(1, Lambertian::new(Color::new(1.0, 0.2, 0.2)));
+    let back_green = numbered(2, Lambertian::new(Color::new(0.2, 1.0, 0.2)));
+    let right_blue = numbered(3, Lambertian::new(Color::new(0.2, 0.2, 1.0)));
+    let upper_orange = numbered(4, Lambertian::new(Color::new(1.0, 0.5, 0.0)));
+    let lower_teal = numbered(5, Lambertian::new(Color::new(0.2, 0.8, 0.8)));
+
+    let objects: Vec<Box<dyn Hittable>> = vec![
+        Box::new(Quad::new(
+            Point3::new(-3.0, -2.0, 5.0),
+            Vec3::new(0.0, 0.0, -4.0),
+            Vec3::new(0.0, 4.0, 0.0),
+            left_red,
+        )),
+        Box::new(Quad::new(
+            Point3::new(-2.0, -2.0, 0.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(0.0, 4.0, 0.0),
+            back_green,
+        )),
+        Box::new(Quad::new(
+            Point3::new(3.0, -2.0, 1.0),
+            Vec3::new(0.0, 0.0, 4.0),
+            Vec3::new(0.0, 4.0, 0.0),
+            right_blue,
+        )),
+        Box::new(Quad::new(
+            Point3::new(-2.0, 3.0, 1.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 4.0),
+            upper_orange,
+        )),
+        Box::new(Quad::new(
+            Point3::new(-2.0, -3.0, 5.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -4.0),
+            lower_teal,
+        )),
+    ];
+
+    Scene {
+        camera,
+        objects,
+        lights: HittableList::new(),
+    }
+}
+
+/// Two large spheres sharing one spatial checker texture, from RTNW chapter 4.
+pub fn checkered_spheres(seed: u64) -> Scene {
+    let mut camera = Camera::new();
+    camera.aspect_ratio = 16.0 / 9.0;
+    camera.image_width = 400;
+    camera.samples_per_pixel = 100;
+    camera.max_depth = 50;
+    camera.vfov = 20.0;
+    camera.lookfrom = Point3::new(13.0, 2.0, 3.0);
+    camera.lookat = Point3::new(0.0, 0.0, 0.0);
+    camera.vup = Vec3::new(0.0, 1.0, 0.0);
+    camera.defocus_angle = 0.0;
+    camera.seed = seed;
+
+    let checker = Arc::new(CheckerTexture::from_colors(
+        0.32,
+        Color::new(0.2, 0.3, 0.1),
+        Color::new(0.9, 0.9, 0.9),
+    ));
+    let material = numbered(1, Lambertian::textured(checker));
+
+    let objects: Vec<Box<dyn Hittable>> = vec![
+        Box::new(Sphere::new(
+            Point3::new(0.0, -10.0, 0.0),
+            10.0,
+            material.clone(),
+        )),
+        Box::new(Sphere::new(Point3::new(0.0, 10.0, 0.0), 10.0, material)),
+    ];
+
+    Scene {
+        camera,
+        objects,
+        lights: HittableList::new(),
+    }
+}
+
+/// A marble sphere on a marble ground, from RTNW chapter 5.
+pub fn perlin_spheres(seed: u64) -> Scene {
+    let mut camera = Camera::new();
+    camera.aspect_ratio = 16.0 / 9.0;
+    camera.image_width = 400;
+    camera.samples_per_pixel = 100;
+    camera.max_depth = 50;
+    camera.vfov = 20.0;
+    camera.lookfrom = Point3::new(13.0, 2.0, 3.0);
+    camera.lookat = Point3::new(0.0, 0.0, 0.0);
+    camera.vup = Vec3::new(0.0, 1.0, 0.0);
+    camera.defocus_angle = 0.0;
+    camera.seed = seed;
+
+    let mut rng = seeded_rng(seed);
+    let pertext = Arc::new(NoiseTexture::new(4.0, &mut rng));
+    let material = numbered(1, Lambertian::textured(pertext));
+
+    let objects: Vec<Box<dyn Hittable>> = vec![
+        Box::new(Sphere::new(
+            Point3::new(0.0, -1000.0, 0.0),
+            1000.0,
+            material.clone(),
+        )),
+        Box::new(Sphere::new(Point3::new(0.0, 2.0, 0.0), 2.0, material)),
+    ];
+
+    Scene {
+        camera,
+        objects,
+        lights: HittableList::new(),
+    }
+}
+
+/// A marble sphere lit by a rectangle and a sphere light in the dark, from RTNW chapter 7.
+pub fn simple_light(seed: u64) -> Scene {
+    let mut camera = Camera::new();
+    camera.aspect_ratio = 16.0 / 9.0;
+    camera.image_width = 400;
+    camera.samples_per_pixel = 100;
+    camera.max_depth = 50;
+    camera.background = Background::None;
+    camera.vfov = 20.0;
+    camera.lookfrom = Point3::new(26.0, 3.0, 6.0);
+    camera.lookat = Point3::new(0.0, 2.0, 0.0);
+    camera.vup = Vec3::new(0.0, 1.0, 0.0);
+    camera.defocus_angle = 0.0;
+    camera.seed = seed;
+
+    let mut rng = seeded_rng(seed);
+    let pertext = Arc::new(NoiseTexture::new(4.0, &mut rng));
+    let material = numbered(1, Lambertian::textured(pertext));
+    let difflight = numbered(2, DiffuseLight::new(Color::new(4.0, 4.0, 4.0)));
+
+    let objects: Vec<Box<dyn Hittable>> = vec![
+        Box::new(Sphere::new(
+            Point3::new(0.0, -1000.0, 0.0),
+            1000.0,
+            material.clone(),
+        )),
+        Box::new(Sphere::new(Point3::new(0.0, 2.0, 0.0), 2.0, material)),
+        Box::new(Sphere::new(
+            Point3::new(0.0, 7.0, 0.0),
+            2.0,
+            difflight.clone(),
+        )),
+        Box::new(Quad::new(
+            Point3::new(3.0, 1.0, -2.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+            difflight.clone(),
+        )),
+    ];
+
+    let mut lights = HittableList::new();
+    lights.add(Box::new(Sphere::new(
+        Point3::new(0.0, 7.0, 0.0),
+        2.0,
+        difflight.clone(),
+    )));
+    lights.add(Box::new(Quad::new(
+        Point3::new(3.0, 1.0, -2.0),
+        Vec3::new(2.0, 0.0, 0.0),
+        Vec3::new(0.0, 2.0, 0.0),
+        difflight,
+    )));
+
+    Scene {
+        camera,
+        objects,
+        lights,
+    }
+}
+
+/// The Cornell box with two turned blocks, from RTNW chapter 8.
+pub fn cornell_box(seed: u64) -> Scene {
+    let mut camera = Camera::new();
+    camera.aspect_ratio = 1.0;
+    camera.image_width = 600;
+    camera.samples_per_pixel = 200;
+    camera.max_depth = 50;
+    camera.background = Background::None;
+    camera.vfov = 40.0;
+    camera.lookfrom = Point3::new(278.0, 278.0, -800.0);
+    camera.lookat = Point3::new(278.0, 278.0, 0.0);
+    camera.vup = Vec3::new(0.0, 1.0, 0.0);
+    camera.defocus_angle = 0.0;
+    camera.seed = seed;
+
+    let red = numbered(1, Lambertian::new(Color::new(0.65, 0.05, 0.05)));
+    let white = numbered(2, Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    let green = numbered(3, Lambertian::new(Color::new(0.12, 0.45, 0.15)));
+    let light = numbered(4, DiffuseLight::new(Color::new(15.0, 15.0, 15.0)));
+
+    let objects: Vec<Box<dyn Hittable>> = vec![
+        Box::new(Quad::new(
+            Point3::new(555.0, 0.0, 0.0),
+            Vec3::new(0.0, 555.0, 0.0),
+            Vec3::new(0.0, 0.0, 555.0),
+            green,
+        )),
+        Box::new(Quad::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 555.0, 0.0),
+            Vec3::new(0.0, 0.0, 555.0),
+            red,
+        )),
+        Box::new(Quad::new(
+            Point3::new(343.0, 554.0, 332.0),
+            Vec3::new(-130.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -105.0),
+            light.clone(),
+        )),
+        Box::new(Quad::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(555.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 555.0),
+            white.clone(),
+        )),
+        Box::new(Quad::new(
+            Point3::new(555.0, 555.0, 555.0),
+            Vec3::new(-555.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -555.0),
+            white.clone(),
+        )),
+        Box::new(Quad::new(
+            Point3::new(0.0, 0.0, 555.0),
+            Vec3::new(555.0, 0.0, 0.0),
+            Vec3::new(0.0, 555.0, 0.0),
+            white.clone(),
+        )),
+        Box::new(Translate::new(
+            Arc::new(RotateY::new(
+                Arc::new(make_box(
+                    Point3::new(0.0, 0.0, 0.0),
+                    Point3::new(165.0, 330.0, 165.0),
+                    white.clone(),
+                )),
+                15.0,
+            )),
+            Vec3::new(265.0, 0.0, 295.0),
+        )),
+        Box::new(Translate::new(
+            Arc::new(RotateY::new(
+                Arc::new(make_box(
+                    Point3::new(0.0, 0.0, 0.0),
+                    Point3::new(165.0, 165.0, 165.0),
+                    white,
+                )),
+                -18.0,
+            )),
+            Vec3::new(130.0, 0.0, 65.0),
+        )),
+    ];
+
+    let mut lights = HittableList::new();
+    lights.add(Box::new(Quad::new(
+        Point3::new(343.0, 554.0, 332.0),
+        Vec3::new(-130.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -105.0),
+        light,
+    )));
+
+    Scene {
+        camera,
+        objects,
+        lights,
+    }
+}
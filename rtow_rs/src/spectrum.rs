@@ -0,0 +1,301 @@
+//! Wavelengths of visible light for the dispersive materials: their sampling, the indices of
+//! refraction depending on them and the conversion of a single wavelength back to RGB.
+//!
+//! Paths stay in RGB until they meet a dispersive material. There they split into the hero
+//! wavelength drawn by the camera and its companions, spread evenly over the visible range
+//! (hero wavelength spectral sampling, Wilkie et al. 2014). The hero picks the directions, and
+//! every wavelength is weighted by the balance heuristic over the densities with which each of
+//! them would have picked the same path: a reflection keeps them all, a refraction bends each
+//! one its own way and leaves the hero alone.
+
+use std::sync::OnceLock;
+
+use crate::color::Color;
+
+/// Shortest and longest sampled wavelengths, in nanometers.
+pub const LAMBDA_MIN: f64 = 380.0;
+pub const LAMBDA_MAX: f64 = 780.0;
+
+/// Wavelength of the helium d line, where glass catalogs give the plain index of refraction.
+pub const LAMBDA_D: f64 = 587.6;
+
+/// Count of wavelengths of a spectral sample, the hero included.
+pub const SPECTRAL_SAMPLES: usize = 4;
+
+/// Light carried by a ray.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Wavelength {
+    /// Not drawn by a camera, dispersive materials use their index at the d line
+    #[default]
+    Unsampled,
+    /// Every color in RGB, with the hero wavelength in nm a dispersive material will split
+    /// the colors around
+    Hero(f64),
+    /// The wavelengths split apart by a dispersive material
+    Spectral(SpectralSample),
+}
+
+impl Wavelength {
+    /// Hero wavelength for a uniform `u` in [0, 1).
+    pub fn hero(u: f64) -> Self {
+        Wavelength::Hero(LAMBDA_MIN + u * (LAMBDA_MAX - LAMBDA_MIN))
+    }
+
+    /// Wavelength in nm choosing the directions of the path.
+    pub fn nanometers(&self) -> Option<f64> {
+        match self {
+            Wavelength::Unsampled => None,
+            Wavelength::Hero(lambda) => Some(*lambda),
+            Wavelength::Spectral(sample) => Some(sample.hero()),
+        }
+    }
+
+    /// RGB weight of the light reaching the path, white until the colors are split.
+    pub fn weight(&self) -> Color {
+        match self {
+            Wavelength::Spectral(sample) => sample.weight(),
+            _ => Color::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+/// Hero wavelength and its companions, with what the path since the split did to each.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpectralSample {
+    /// Wavelengths in nm, the hero first, then every `1 / SPECTRAL_SAMPLES` of the range
+    /// further, wrapping around
+    pub lambdas: [f64; SPECTRAL_SAMPLES],
+    /// Product of the scattering of each wavelength along the path
+    pub scattering: [f64; SPECTRAL_SAMPLES],
+    /// Product of the densities with which each wavelength, as the hero, would have chosen
+    /// the path
+    pub pdf: [f64; SPECTRAL_SAMPLES],
+}
+
+impl SpectralSample {
+    /// Split the colors around the hero wavelength `lambda` in nm.
+    pub fn new(lambda: f64) -> Self {
+        let range = LAMBDA_MAX - LAMBDA_MIN;
+        let u = (lambda - LAMBDA_MIN) / range;
+        let lambdas = std::array::from_fn(|k| {
+            LAMBDA_MIN + (u + k as f64 / SPECTRAL_SAMPLES as f64).fract() * range
+        });
+        Self {
+            lambdas,
+            scattering: [1.0; SPECTRAL_SAMPLES],
+            pdf: [1.0; SPECTRAL_SAMPLES],
+        }
+    }
+
+    pub fn hero(&self) -> f64 {
+        self.lambdas[0]
+    }
+
+    /// The sample after an event scattering each wavelength by `scattering`, which the hero
+    /// chose with its own density and each companion would have with its `pdf`.
+    pub fn scatter(
+        &self,
+        scattering: [f64; SPECTRAL_SAMPLES],
+        pdf: [f64; SPECTRAL_SAMPLES],
+    ) -> Self {
+        Self {
+            lambdas: self.lambdas,
+            scattering: std::array::from_fn(|k| self.scattering[k] * scattering[k]),
+            pdf: std::array::from_fn(|k| self.pdf[k] * pdf[k]),
+        }
+    }
+
+    /// RGB weight of the path: the colors of the wavelengths times their scattering, over the
+    /// sum of the densities of the path for every wavelength.
+    pub fn weight(&self) -> Color {
+        let pdf: f64 = self.pdf.iter().sum();
+        if pdf <= 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        self.lambdas
+            .iter()
+            .zip(&self.scattering)
+            .fold(Color::default(), |sum, (&lambda, &scattering)| {
+                sum + scattering * wavelength_weight(lambda)
+            })
+            / pdf
+    }
+}
+
+/// Index of refraction varying with the wavelength.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dispersion {
+    /// `n = a + b / lambda^2`, lambda in micrometers
+    Cauchy { a: f64, b: f64 },
+    /// `n^2 = 1 + sum b_i lambda^2 / (lambda^2 - c_i)`, lambda in micrometers
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Dispersion {
+    /// One of the glasses `bk7`, `fused_silica` or `diamond`.
+    pub fn named(glass: &str) -> Option<Self> {
+        let (b, c) = match glass {
+            "bk7" => (
+                [1.039_612_12, 0.231_792_344, 1.010_469_45],
+                [0.006_000_698_67, 0.020_017_914_4, 103.560_653],
+            ),
+            "fused_silica" => (
+                [0.696_166_3, 0.407_942_6, 0.897_479_4],
+                [0.004_679_148_26, 0.013_512_063_1, 97.934_002_5],
+            ),
+            "diamond" => ([4.3356, 0.3306, 0.0], [0.011_236, 0.030_625, 0.0]),
+            _ => return None,
+        };
+        Some(Dispersion::Sellmeier { b, c })
+    }
+
+    /// Index of refraction at `lambda` nm.
+    pub fn index(&self, lambda: f64) -> f64 {
+        let um = lambda / 1000.0;
+        let um2 = um * um;
+        match self {
+            Dispersion::Cauchy { a, b } => a + b / um2,
+            Dispersion::Sellmeier { b, c } => {
+                let n2 = 1.0
+                    + b.iter()
+                        .zip(c)
+                        .map(|(b, c)| b * um2 / (um2 - c))
+                        .sum::<f64>();
+                n2.sqrt()
+            }
+        }
+    }
+}
+
+/// Piecewise gaussian of the CIE color matching function fits.
+fn lobe(lambda: f64, mu: f64, sigma_below: f64, sigma_above: f64) -> f64 {
+    let sigma = if lambda < mu {
+        sigma_below
+    } else {
+        sigma_above
+    };
+    let t = (lambda - mu) / sigma;
+    (-0.5 * t * t).exp()
+}
+
+/// CIE 1931 standard observer, with the multi lobe fit of Wyman, Sloan and Shirley 2013.
+pub fn cie_xyz(lambda: f64) -> Color {
+    let x = 1.056 * lobe(lambda, 599.8, 37.9, 31.0) + 0.362 * lobe(lambda, 442.0, 16.0, 26.7)
+        - 0.065 * lobe(lambda, 501.1, 20.4, 26.2);
+    let y = 0.821 * lobe(lambda, 568.8, 46.9, 40.5) + 0.286 * lobe(lambda, 530.9, 16.3, 31.1);
+    let z = 1.217 * lobe(lambda, 437.0, 11.8, 36.0) + 0.681 * lobe(lambda, 459.0, 26.0, 13.8);
+    Color::new(x, y, z)
+}
+
+/// Linear sRGB of a CIE XYZ color.
+pub fn xyz_to_rgb(xyz: &Color) -> Color {
+    let (x, y, z) = (xyz.x(), xyz.y(), xyz.z());
+    Color::new(
+        3.240_454_2 * x - 1.537_138_5 * y - 0.498_531_4 * z,
+        -0.969_266_0 * x + 1.876_010_8 * y + 0.041_556_0 * z,
+        0.055_643_4 * x - 0.204_025_9 * y + 1.057_225_2 * z,
+    )
+}
+
+/// RGB weight of a wavelength drawn uniformly over the visible range. It averages to white
+/// over the range: a white path turning into a single wavelength stays white on average.
+/// Saturated wavelengths have negative components, which only cancel out in the mean.
+pub fn wavelength_weight(lambda: f64) -> Color {
+    static MEAN: OnceLock<Color> = OnceLock::new();
+    let mean = MEAN.get_or_init(|| {
+        let n = 4000;
+        let step = (LAMBDA_MAX - LAMBDA_MIN) / n as f64;
+        let sum = (0..n)
+            .map(|i| xyz_to_rgb(&cie_xyz(LAMBDA_MIN + (i as f64 + 0.5) * step)))
+            .fold(Color::default(), |sum, rgb| sum + rgb);
+        sum / n as f64
+    });
+
+    let rgb = xyz_to_rgb(&cie_xyz(lambda));
+    Color::new(rgb.x() / mean.x(), rgb.y() / mean.y(), rgb.z() / mean.z())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glasses() {
+        // catalog indices at the d line
+        let bk7 = Dispersion::named("bk7").unwrap();
+        assert!((bk7.index(LAMBDA_D) - 1.5168).abs() < 1e-4);
+        let diamond = Dispersion::named("diamond").unwrap();
+        assert!((diamond.index(LAMBDA_D) - 2.417).abs() < 2e-3);
+        // blue light bends more
+        for glass in [bk7, diamond, Dispersion::Cauchy { a: 1.5, b: 0.004 }] {
+            assert!(glass.index(450.0) > glass.index(650.0));
+        }
+    }
+
+    #[test]
+    fn test_wavelength_weight_averages_to_white() {
+        let n = 1000;
+        let mean = (0..n)
+            .map(|i| {
+                let hero = Wavelength::hero((i as f64 + 0.5) / n as f64);
+                wavelength_weight(hero.nanometers().unwrap())
+            })
+            .fold(Color::default(), |sum, w| sum + w)
+            / n as f64;
+        assert!(
+            (mean - Color::new(1.0, 1.0, 1.0)).length() < 1e-3,
+            "{mean:?}"
+        );
+
+        let red = wavelength_weight(650.0);
+        let blue = wavelength_weight(450.0);
+        assert!(red.x() > red.z() && blue.z() > blue.x());
+    }
+
+    #[test]
+    fn test_spectral_sample() {
+        let sample = SpectralSample::new(700.0);
+        assert_eq!(sample.lambdas, [700.0, 400.0, 500.0, 600.0]);
+
+        // the companions take most of the color noise away from a single wavelength
+        let n = 1000;
+        let spread = |weight: &dyn Fn(f64) -> Color| {
+            (0..n)
+                .map(|i| {
+                    let lambda =
+                        LAMBDA_MIN + (i as f64 + 0.5) / n as f64 * (LAMBDA_MAX - LAMBDA_MIN);
+                    (weight(lambda) - Color::new(1.0, 1.0, 1.0)).length_squared()
+                })
+                .sum::<f64>()
+                / n as f64
+        };
+        let single = spread(&wavelength_weight);
+        let split = spread(&|lambda| SpectralSample::new(lambda).weight());
+        assert!(split < 0.1 * single, "{split} vs {single}");
+
+        // a reflection chosen with the hero's reflectance, which grows toward the blue, is
+        // weighted into the spectral mean of the reflected light
+        let reflectance =
+            |lambda: f64| 0.9 - 0.8 * (lambda - LAMBDA_MIN) / (LAMBDA_MAX - LAMBDA_MIN);
+        let mut expected = Color::default();
+        let mut estimate = Color::default();
+        for i in 0..n {
+            let u = (i as f64 + 0.5) / n as f64;
+            let lambda = LAMBDA_MIN + u * (LAMBDA_MAX - LAMBDA_MIN);
+            expected += reflectance(lambda) * wavelength_weight(lambda);
+
+            let sample = SpectralSample::new(lambda);
+            let r = sample.lambdas.map(reflectance);
+            // reflected with probability r[0], the other outcome is not counted here
+            estimate += r[0] * sample.scatter(r, r).weight();
+        }
+        assert!(
+            (estimate / n as f64 - expected / n as f64).length() < 1e-3,
+            "{estimate:?} != {expected:?}"
+        );
+
+        // a refraction leaves the hero alone
+        let refracted = sample.scatter([0.7, 0.0, 0.0, 0.0], [0.7, 0.0, 0.0, 0.0]);
+        assert_eq!(refracted.weight(), wavelength_weight(700.0));
+    }
+}
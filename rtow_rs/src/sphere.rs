@@ -1,33 +1,85 @@
-use std::rc::Rc;
+use std::f64::consts::PI;
+use std::sync::Arc;
 
 use crate::{
+    aabb::Aabb,
     hittable::{HitRecord, Hittable},
     interval::Interval,
     material::Material,
+    onb::Onb,
     ray::Ray,
-    vec3::Point3,
+    rtweekend::{random_double, RtRng},
+    vec3::{Point3, Vec3},
 };
 
 #[derive(Debug)]
 pub struct Sphere {
-    center: Point3,
+    /// Path of the center, from its position at time 0 to the one at time 1
+    center: Ray,
     radius: f64,
-    mat: Rc<dyn Material>,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
 }
 
 impl Sphere {
-    pub fn new(center: Point3, radius: f64, mat: Rc<dyn Material>) -> Self {
+    /// Stationary sphere.
+    pub fn new(center: Point3, radius: f64, mat: Arc<dyn Material>) -> Self {
+        let rvec = Vec3::new(radius, radius, radius);
         Self {
-            center: center.clone(),
+            center: Ray::new(center, Vec3::new(0.0, 0.0, 0.0)),
             radius,
             mat,
+            bbox: Aabb::from_points(center - rvec, center + rvec),
         }
     }
+
+    /// Sphere moving at constant speed from `center1` at time 0 to `center2` at time 1, its
+    /// bounding box covers the whole path.
+    pub fn moving(center1: Point3, center2: Point3, radius: f64, mat: Arc<dyn Material>) -> Self {
+        let rvec = Vec3::new(radius, radius, radius);
+        let box1 = Aabb::from_points(center1 - rvec, center1 + rvec);
+        let box2 = Aabb::from_points(center2 - rvec, center2 + rvec);
+        Self {
+            center: Ray::new(center1, center2 - center1),
+            radius,
+            mat,
+            bbox: Aabb::surrounding(&box1, &box2),
+        }
+    }
+
+    /// Surface coordinates of a point p on the unit sphere centered at the origin.
+    /// u: returned value [0,1] of angle around the Y axis from X=-1.
+    /// v: returned value [0,1] of angle from Y=-1 to Y=+1.
+    ///     <1 0 0> yields <0.50 0.50>       <-1  0  0> yields <0.00 0.50>
+    ///     <0 1 0> yields <0.50 1.00>       < 0 -1  0> yields <0.50 0.00>
+    ///     <0 0 1> yields <0.25 0.50>       < 0  0 -1> yields <0.75 0.50>
+    pub fn get_sphere_uv(p: &Point3) -> (f64, f64) {
+        let theta = (-p.y()).acos();
+        let phi = (-p.z()).atan2(p.x()) + PI;
+
+        (phi / (2.0 * PI), theta / PI)
+    }
+
+    /// Random direction inside the cone around +z that holds a sphere of `radius` seen from
+    /// `distance_squared` away, uniform in solid angle.
+    fn random_to_sphere(radius: f64, distance_squared: f64, rng: &mut RtRng) -> Vec3 {
+        let r1 = random_double(rng);
+        let r2 = random_double(rng);
+
+        let z = 1.0 + r2 * ((1.0 - radius * radius / distance_squared).sqrt() - 1.0);
+
+        let phi = 2.0 * PI * r1;
+        let x = phi.cos() * (1.0 - z * z).sqrt();
+        let y = phi.sin() * (1.0 - z * z).sqrt();
+
+        Vec3::new(x, y, z)
+    }
 }
 
 impl Hittable for Sphere {
     fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
-        let oc = self.center - *r.origin();
+        let current_center = self.center.at(r.time());
+        let oc = current_center - *r.origin();
         let a = r.direction().length_squared();
         let h = oc.dot(r.direction());
         let c = oc.length_squared() - self.radius * self.radius;
@@ -40,9 +92,9 @@ impl Hittable for Sphere {
         let sqrtd = discriminant.sqrt();
 
         // Find the nearest root that lies in the acceptable range.
-        let root = (h - sqrtd) / a;
+        let mut root = (h - sqrtd) / a;
         if !ray_t.surrounds(root) {
-            let root = (h + sqrtd) / a;
+            root = (h + sqrtd) / a;
             if !ray_t.surrounds(root) {
                 return false;
             }
@@ -50,32 +102,61 @@ impl Hittable for Sphere {
 
         rec.t = root;
         rec.p = r.at(rec.t);
-        let outward_normal = (rec.p - self.center) / self.radius;
+        let outward_normal = (rec.p - current_center) / self.radius;
         rec.set_face_normal(r, &outward_normal);
+        (rec.u, rec.v) = Self::get_sphere_uv(&outward_normal);
         rec.mat = Some(self.mat.clone());
         true
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    /// Uniform over the cone of directions subtended by the sphere, for an origin outside it;
+    /// uniform over all directions from inside. Only exact for stationary spheres.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let mut rec = HitRecord::default();
+        let ray = Ray::new(*origin, *direction);
+        if !self.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return 0.0;
+        }
+
+        let dist_squared = (self.center.at(0.0) - *origin).length_squared();
+        if dist_squared <= self.radius * self.radius {
+            return 1.0 / (4.0 * PI);
+        }
+        let cos_theta_max = (1.0 - self.radius * self.radius / dist_squared).sqrt();
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+
+        1.0 / solid_angle
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut RtRng) -> Vec3 {
+        let direction = self.center.at(0.0) - *origin;
+        let distance_squared = direction.length_squared();
+        if distance_squared <= self.radius * self.radius {
+            // the sphere surrounds the origin, every direction reaches it
+            return Vec3::random_unit_vector(rng);
+        }
+        let uvw = Onb::new(&direction);
+        uvw.transform(&Self::random_to_sphere(self.radius, distance_squared, rng))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::Color;
-    use crate::Lambertian;
-    use crate::Vec3;
+    use crate::color::Color;
+    use crate::material::Lambertian;
+    use crate::rtweekend::seeded_rng;
 
     #[test]
     fn test_sphere_hit() {
         let center = Vec3::new(0.0, 0.0, -5.0);
         let radius = 1.0;
-        let ground_material = Rc::new(Lambertian {
-            albedo: Color::new(0.5, 0.5, 0.5),
-        });
-        let sphere = Sphere {
-            center,
-            radius,
-            mat: ground_material,
-        };
+        let ground_material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let sphere = Sphere::new(center, radius, ground_material);
         let ray_origin = Vec3::new(0.0, 0.0, 0.0);
         let ray_direction = Vec3::new(0.0, 0.0, -1.0);
         let ray = Ray::new(ray_origin, ray_direction);
@@ -85,16 +166,30 @@ mod tests {
             t: 0.0,
             p: Vec3::new(0.0, 0.0, 0.0),
             normal: Vec3::new(0.0, 0.0, 0.0),
+            u: 0.0,
+            v: 0.0,
             front_face: false,
+            object_id: 0,
             mat: None,
         };
 
         let hit = sphere.hit(&ray, ray_t, &mut hit_record);
 
         assert!(hit, "The ray should hit the sphere.");
-        assert!(hit_record.t > 0.0, "Intersection t should be greater than 0.");
-        assert_eq!(hit_record.p, Vec3::new(0.0, 0.0, -4.0), "Intersection point should be at (0, 0, -4).");
-        assert_eq!(hit_record.normal, Vec3::new(0.0, 0.0, 1.0), "Normal should be (0, 0, 1).");
+        assert!(
+            hit_record.t > 0.0,
+            "Intersection t should be greater than 0."
+        );
+        assert_eq!(
+            hit_record.p,
+            Vec3::new(0.0, 0.0, -4.0),
+            "Intersection point should be at (0, 0, -4)."
+        );
+        assert_eq!(
+            hit_record.normal,
+            Vec3::new(0.0, 0.0, 1.0),
+            "Normal should be (0, 0, 1)."
+        );
         assert!(hit_record.mat.is_some(), "Material should be set.");
     }
 
@@ -102,14 +197,8 @@ mod tests {
     fn test_sphere_hitrecord() {
         let center = Vec3::new(0.0, 0.0, -5.0);
         let radius = 1.0;
-        let ground_material = Rc::new(Lambertian {
-            albedo: Color::new(0.5, 0.5, 0.5),
-        });
-        let sphere = Sphere {
-            center,
-            radius,
-            mat: ground_material,
-        };
+        let ground_material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let sphere = Sphere::new(center, radius, ground_material);
         let ray_origin = Vec3::new(0.0, 0.0, -5.5);
         let ray_direction = Vec3::new(0.0, 0.0, 1.0);
         let ray = Ray::new(ray_origin, ray_direction);
@@ -119,16 +208,92 @@ mod tests {
             t: 0.0,
             p: Vec3::new(0.0, 0.0, 0.0),
             normal: Vec3::new(0.0, 0.0, 0.0),
+            u: 0.0,
+            v: 0.0,
             front_face: false,
+            object_id: 0,
             mat: None,
         };
 
         let hit = sphere.hit(&ray, ray_t, &mut hit_record);
 
         assert!(hit, "The ray should hit the sphere.");
-        assert!(hit_record.t > 0.0, "Intersection t should be greater than 0.");
-        assert_eq!(hit_record.p, Vec3::new(0.0, 0.0, -4.0), "Intersection point should be at (0, 0, -4).");
-        assert_eq!(hit_record.normal, Vec3::new(0.0, 0.0, -1.0), "Normal should be (0, 0, -1.0).");
+        assert!(
+            hit_record.t > 0.0,
+            "Intersection t should be greater than 0."
+        );
+        assert_eq!(
+            hit_record.p,
+            Vec3::new(0.0, 0.0, -4.0),
+            "Intersection point should be at (0, 0, -4)."
+        );
+        assert_eq!(
+            hit_record.normal,
+            Vec3::new(0.0, 0.0, -1.0),
+            "Normal should be (0, 0, -1.0)."
+        );
         assert!(hit_record.mat.is_some(), "Material should be set.");
     }
+
+    #[test]
+    fn test_moving_sphere() {
+        let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let sphere = Sphere::moving(
+            Point3::new(0.0, 0.0, -5.0),
+            Point3::new(0.0, 2.0, -5.0),
+            0.5,
+            mat,
+        );
+        let ray_t = Interval::new(0.0, 100.0);
+        let mut rec = HitRecord::default();
+
+        // the sphere has left the x axis by time 1 and sits at y = 1 halfway
+        let along_axis = |time| Ray::with_time(Point3::default(), Vec3::new(0.0, 0.0, -1.0), time);
+        assert!(sphere.hit(&along_axis(0.0), ray_t, &mut rec));
+        assert!((rec.t - 4.5).abs() < 1e-12);
+        assert!(!sphere.hit(&along_axis(1.0), ray_t, &mut rec));
+        let halfway = Ray::with_time(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, -1.0), 0.5);
+        assert!(sphere.hit(&halfway, ray_t, &mut rec));
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-12);
+
+        // the bounding box covers the whole path
+        let bbox = sphere.bounding_box();
+        assert!(bbox.y.min <= -0.5 && bbox.y.max >= 2.5);
+    }
+
+    #[test]
+    fn test_light_sampling_from_inside() {
+        let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 2.0, mat);
+        let mut rng = seeded_rng(3);
+
+        // inside the light every direction reaches it, with a finite density
+        let origin = Point3::new(0.5, 0.0, 0.0);
+        for _ in 0..100 {
+            let direction = sphere.random(&origin, &mut rng);
+            assert!((direction.length() - 1.0).abs() < 1e-9, "{direction:?}");
+            assert_eq!(sphere.pdf_value(&origin, &direction), 1.0 / (4.0 * PI));
+        }
+
+        // outside it, the directions stay in the cone of the sphere
+        let origin = Point3::new(4.0, 0.0, 0.0);
+        let direction = sphere.random(&origin, &mut rng);
+        assert!(direction.unit().x() < -0.86);
+        let pdf = sphere.pdf_value(&origin, &direction);
+        assert!(pdf.is_finite() && pdf > 1.0 / (4.0 * PI));
+    }
+
+    #[test]
+    fn test_sphere_uv() {
+        let check = |p: Point3, u: f64, v: f64| {
+            let (su, sv) = Sphere::get_sphere_uv(&p);
+            assert!((su - u).abs() < 1e-12 && (sv - v).abs() < 1e-12, "{p:?}");
+        };
+        check(Point3::new(1.0, 0.0, 0.0), 0.5, 0.5);
+        check(Point3::new(0.0, 1.0, 0.0), 0.5, 1.0);
+        check(Point3::new(0.0, 0.0, 1.0), 0.25, 0.5);
+        check(Point3::new(-1.0, 0.0, 0.0), 0.0, 0.5);
+        check(Point3::new(0.0, -1.0, 0.0), 0.5, 0.0);
+        check(Point3::new(0.0, 0.0, -1.0), 0.75, 0.5);
+    }
 }
@@ -0,0 +1,74 @@
+/// Mean and variance of a stream of samples, updated one sample at a time with
+/// Welford's algorithm so that long runs do not lose precision.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RunningStats {
+    count: u32,
+    mean: f64,
+    /// Sum of the squared differences to the current mean
+    m2: f64,
+}
+
+impl RunningStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, x: f64) {
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Unbiased sample variance, 0 until there are two samples.
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            0.0
+        } else {
+            self.m2 / (self.count - 1) as f64
+        }
+    }
+
+    /// Estimated standard deviation of the mean.
+    pub fn standard_error(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            (self.variance() / self.count as f64).sqrt()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_running_stats() {
+        let samples = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        let mut stats = RunningStats::new();
+        for x in samples {
+            stats.push(x);
+        }
+
+        assert_eq!(stats.count(), 8);
+        assert!((stats.mean() - 5.0).abs() < 1e-12);
+        assert!((stats.variance() - 32.0 / 7.0).abs() < 1e-12);
+        assert!((stats.standard_error() - (32.0 / 7.0 / 8.0f64).sqrt()).abs() < 1e-12);
+
+        // a large offset must not swamp the spread
+        let mut shifted = RunningStats::new();
+        for x in samples {
+            shifted.push(x + 1e9);
+        }
+        assert!((shifted.variance() - 32.0 / 7.0).abs() < 1e-6);
+    }
+}
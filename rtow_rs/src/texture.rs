@@ -0,0 +1,200 @@
+use std::fmt::Debug;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::{color::Color, interval::Interval, perlin::Perlin, rtweekend::RtRng, vec3::Point3};
+
+/// A color that varies over a surface, looked up by the surface coordinates (u, v) and the
+/// hit point p.
+pub trait Texture: Debug + Send + Sync {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
+}
+
+#[derive(Debug)]
+pub struct SolidColor {
+    pub albedo: Color,
+}
+
+impl SolidColor {
+    pub fn new(albedo: Color) -> Self {
+        Self { albedo }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        self.albedo
+    }
+}
+
+/// Alternates between two textures on the cells of a 3D grid of edge `scale`.
+#[derive(Debug)]
+pub struct CheckerTexture {
+    inv_scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        Self {
+            inv_scale: 1.0 / scale,
+            even,
+            odd,
+        }
+    }
+
+    pub fn from_colors(scale: f64, c1: Color, c2: Color) -> Self {
+        Self::new(
+            scale,
+            Arc::new(SolidColor::new(c1)),
+            Arc::new(SolidColor::new(c2)),
+        )
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let x_int = (self.inv_scale * p.x()).floor() as i64;
+        let y_int = (self.inv_scale * p.y()).floor() as i64;
+        let z_int = (self.inv_scale * p.z()).floor() as i64;
+
+        if (x_int + y_int + z_int).rem_euclid(2) == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+/// Texture read from an image file, (0, 0) is the lower left corner of the image.
+#[derive(Debug)]
+pub struct ImageTexture {
+    width: u32,
+    height: u32,
+    /// Linear colors, row by row from the top
+    pixels: Vec<Color>,
+}
+
+impl ImageTexture {
+    /// Load a PNG or JPEG image, its 8 bit gamma encoded values are converted to linear.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, image::ImageError> {
+        let image = image::open(path)?.into_rgb8();
+
+        let to_linear = |byte: u8| (byte as f64 / 255.0).powf(2.2);
+        let pixels = image
+            .pixels()
+            .map(|pixel| {
+                let [r, g, b] = pixel.0;
+                Color::new(to_linear(r), to_linear(g), to_linear(b))
+            })
+            .collect();
+
+        Ok(Self {
+            width: image.width(),
+            height: image.height(),
+            pixels,
+        })
+    }
+
+    pub fn from_pixels(width: u32, height: u32, pixels: Vec<Color>) -> Self {
+        assert_eq!(pixels.len(), (width * height) as usize);
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        // If we have no texture data, then return solid cyan as a debugging aid.
+        if self.height == 0 || self.width == 0 {
+            return Color::new(0.0, 1.0, 1.0);
+        }
+
+        // Clamp input texture coordinates to [0, 1] x [1, 0], flip v to image coordinates.
+        let u = Interval::new(0.0, 1.0).clamp(u);
+        let v = 1.0 - Interval::new(0.0, 1.0).clamp(v);
+
+        let i = ((u * self.width as f64) as u32).min(self.width - 1);
+        let j = ((v * self.height as f64) as u32).min(self.height - 1);
+
+        self.pixels[(j * self.width + i) as usize]
+    }
+}
+
+/// Marble-like stripes along z, disturbed by turbulent Perlin noise.
+#[derive(Debug)]
+pub struct NoiseTexture {
+    noise: Perlin,
+    scale: f64,
+}
+
+impl NoiseTexture {
+    pub fn new(scale: f64, rng: &mut RtRng) -> Self {
+        Self {
+            noise: Perlin::new(rng),
+            scale,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        Color::new(0.5, 0.5, 0.5)
+            * (1.0 + (self.scale * p.z() + 10.0 * self.noise.turb(p, 7)).sin())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checker() {
+        let checker =
+            CheckerTexture::from_colors(1.0, Color::new(1.0, 1.0, 1.0), Color::new(0.0, 0.0, 0.0));
+        let white = Color::new(1.0, 1.0, 1.0);
+
+        assert_eq!(checker.value(0.0, 0.0, &Point3::new(0.5, 0.5, 0.5)), white);
+        assert_ne!(checker.value(0.0, 0.0, &Point3::new(1.5, 0.5, 0.5)), white);
+        // cells keep alternating across the origin
+        assert_ne!(checker.value(0.0, 0.0, &Point3::new(-0.5, 0.5, 0.5)), white);
+        assert_eq!(
+            checker.value(0.0, 0.0, &Point3::new(-0.5, -0.5, 0.5)),
+            white
+        );
+    }
+
+    #[test]
+    fn test_image_lookup() {
+        let top_left = Color::new(1.0, 0.0, 0.0);
+        let bottom_left = Color::new(0.0, 0.0, 1.0);
+        let image = ImageTexture::from_pixels(
+            2,
+            2,
+            vec![top_left, Color::default(), bottom_left, Color::default()],
+        );
+        let p = Point3::default();
+
+        assert_eq!(image.value(0.0, 1.0, &p), top_left);
+        assert_eq!(image.value(0.0, 0.0, &p), bottom_left);
+        assert_eq!(image.value(-3.0, -3.0, &p), bottom_left);
+        assert_eq!(image.value(1.0, 1.0, &p), Color::default());
+    }
+
+    #[test]
+    fn test_load_image() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../rtnw_cc/images/earthmap.jpg"
+        );
+        let earth = ImageTexture::load(path).unwrap();
+        assert_eq!(earth.width, 2 * earth.height);
+
+        let missing = ImageTexture::load("missing.png");
+        assert!(missing.is_err());
+    }
+}
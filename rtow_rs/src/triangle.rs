@@ -0,0 +1,161 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    quad::Plane,
+    ray::Ray,
+    rtweekend::{random_double, RtRng},
+    vec3::{Point3, Vec3},
+};
+
+/// Triangle with the vertices a, b and c, the front face is the one where they wind
+/// counter-clockwise.
+#[derive(Debug)]
+pub struct Triangle {
+    plane: Plane,
+    /// Unit normals at a, b and c for smooth shading, the flat plane normal is used without them
+    normals: Option<[Vec3; 3]>,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Triangle {
+    pub fn new(a: Point3, b: Point3, c: Point3, mat: Arc<dyn Material>) -> Self {
+        let bbox = Aabb::surrounding(&Aabb::from_points(a, b), &Aabb::from_points(a, c));
+        Self {
+            plane: Plane::new(a, b - a, c - a),
+            normals: None,
+            mat,
+            bbox,
+        }
+    }
+
+    /// Triangle shaded with the normal interpolated from the normals given at its vertices.
+    pub fn with_normals(vertices: [Point3; 3], normals: [Vec3; 3], mat: Arc<dyn Material>) -> Self {
+        let [a, b, c] = vertices;
+        let mut triangle = Triangle::new(a, b, c, mat);
+        triangle.normals = Some(normals.map(|n| n.unit()));
+        triangle
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let Some((t, alpha, beta)) = self.plane.intersect(r, &ray_t) else {
+            return false;
+        };
+
+        // alpha and beta are the barycentric weights of b and c, a gets the rest.
+        if alpha < 0.0 || beta < 0.0 || alpha + beta > 1.0 {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = r.at(t);
+        rec.u = alpha;
+        rec.v = beta;
+        rec.mat = Some(self.mat.clone());
+        rec.set_face_normal(r, &self.plane.normal());
+
+        // The geometric normal decides the face, the shading normal follows it to that side.
+        if let Some([na, nb, nc]) = self.normals {
+            let shading_normal = ((1.0 - alpha - beta) * na + alpha * nb + beta * nc).unit();
+            rec.normal = if rec.front_face {
+                shading_normal
+            } else {
+                -shading_normal
+            };
+        }
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let mut rec = HitRecord::default();
+        let ray = Ray::new(*origin, *direction);
+        if !self.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return 0.0;
+        }
+
+        self.plane
+            .solid_angle_pdf(rec.t, direction, 0.5 * self.plane.area())
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut RtRng) -> Vec3 {
+        // fold the samples of the other half of the parallelogram back into the triangle
+        let mut alpha = random_double(rng);
+        let mut beta = random_double(rng);
+        if alpha + beta > 1.0 {
+            alpha = 1.0 - alpha;
+            beta = 1.0 - beta;
+        }
+        self.plane.point(alpha, beta) - *origin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::Lambertian;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn test_triangle_hit() {
+        let triangle = Triangle::new(
+            Point3::new(0.0, 0.0, -1.0),
+            Point3::new(1.0, 0.0, -1.0),
+            Point3::new(0.0, 1.0, -1.0),
+            Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        );
+        let ray_t = Interval::new(0.001, f64::INFINITY);
+        let mut rec: HitRecord = Default::default();
+
+        let r = Ray::new(Point3::new(0.25, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(triangle.hit(&r, ray_t, &mut rec));
+        assert_eq!(rec.t, 1.0);
+        assert_eq!((rec.u, rec.v), (0.25, 0.5));
+        assert!(rec.front_face);
+        assert_eq!(rec.normal, Vec3::new(0.0, 0.0, 1.0));
+
+        // inside the bounding quad but beyond the hypotenuse
+        let r = Ray::new(Point3::new(0.75, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!triangle.hit(&r, ray_t, &mut rec));
+    }
+
+    #[test]
+    fn test_triangle_smooth_normal() {
+        let triangle = Triangle::with_normals(
+            [
+                Point3::new(0.0, 0.0, -1.0),
+                Point3::new(1.0, 0.0, -1.0),
+                Point3::new(0.0, 1.0, -1.0),
+            ],
+            [
+                Vec3::new(0.0, 0.0, 1.0),
+                Vec3::new(1.0, 0.0, 1.0),
+                Vec3::new(0.0, 0.0, 1.0),
+            ],
+            Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        );
+        let ray_t = Interval::new(0.001, f64::INFINITY);
+        let mut rec: HitRecord = Default::default();
+
+        // at vertex b the normal is the one given for b
+        let r = Ray::new(Point3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(triangle.hit(&r, ray_t, &mut rec));
+        assert!((rec.normal - Vec3::new(1.0, 0.0, 1.0).unit()).length() < 1e-12);
+
+        // from behind, the shading normal is flipped with the face
+        let r = Ray::new(Point3::new(0.0, 0.0, -2.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(triangle.hit(&r, ray_t, &mut rec));
+        assert!(!rec.front_face);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-12);
+    }
+}
@@ -1,8 +1,7 @@
-use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub};
+use std::f64::consts::PI;
+use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub};
 
-use rand::Rng;
-
-use crate::rtweekend::{random_double, random_double_range};
+use crate::rtweekend::{random_double, random_double_range, RtRng};
 
 // some code reference glam
 #[derive(Clone, Copy, Default, PartialEq, Debug)]
@@ -29,18 +28,6 @@ impl Vec3 {
         self.z
     }
 
-    pub fn index_mut(&mut self, index: usize) -> &mut f64 {
-        if index == 0 {
-            return &mut self.x;
-        }
-
-        if index == 1 {
-            return &mut self.y;
-        }
-
-        return &mut self.z;
-    }
-
     pub fn length_squared(self) -> f64 {
         self.x * self.x + self.y * self.y + self.z * self.z
     }
@@ -61,24 +48,22 @@ impl Vec3 {
         )
     }
 
-    pub fn random(min: f64, max: f64) -> Vec3 {
-        let mut rng = rand::thread_rng();
+    pub fn random(rng: &mut RtRng, min: f64, max: f64) -> Vec3 {
         let mut vec3 = Vec3::default();
 
         for index in 0..3 {
-            let num = min + (max - min) * rng.gen::<f64>();
-            *vec3.index_mut(index) = num;
+            let num = random_double_range(rng, min, max);
+            vec3[index] = num;
         }
 
-        return vec3;
+        vec3
     }
 
-    pub fn random_random() -> Vec3 {
-        let mut rng = rand::thread_rng();
+    pub fn random_random(rng: &mut RtRng) -> Vec3 {
         let mut vec3 = Vec3::default();
         for index in 0..3 {
-            let num = rng.gen::<f64>();
-            *vec3.index_mut(index) = num;
+            let num = random_double(rng);
+            vec3[index] = num;
         }
         vec3
     }
@@ -91,9 +76,9 @@ impl Vec3 {
         )
     }
 
-    pub fn random_unit_vector() -> Vec3 {
+    pub fn random_unit_vector(rng: &mut RtRng) -> Vec3 {
         loop {
-            let p = Vec3::random_random();
+            let p = Vec3::random(rng, -1.0, 1.0);
             let lensq = p.length_squared();
             if 1e-160 < lensq && lensq <= 1.0 {
                 return p.unit();
@@ -101,11 +86,11 @@ impl Vec3 {
         }
     }
 
-    pub fn random_in_unit_disk() -> Vec3 {
+    pub fn random_in_unit_disk(rng: &mut RtRng) -> Vec3 {
         loop {
             let p = Vec3::new(
-                random_double_range(-1.0, 1.0),
-                random_double_range(-1.0, 1.0),
+                random_double_range(rng, -1.0, 1.0),
+                random_double_range(rng, -1.0, 1.0),
                 0.0,
             );
             if p.length_squared() < 1.0 {
@@ -114,6 +99,19 @@ impl Vec3 {
         }
     }
 
+    /// Random direction around +z with a density proportional to cos(theta).
+    pub fn random_cosine_direction(rng: &mut RtRng) -> Vec3 {
+        let r1 = random_double(rng);
+        let r2 = random_double(rng);
+
+        let phi = 2.0 * PI * r1;
+        let x = phi.cos() * r2.sqrt();
+        let y = phi.sin() * r2.sqrt();
+        let z = (1.0 - r2).sqrt();
+
+        Vec3::new(x, y, z)
+    }
+
     pub fn neal_zero(&self) -> bool {
         let s = 1e-8;
         self.x.abs() < s && self.y.abs() < s && self.z.abs() < s
@@ -121,7 +119,7 @@ impl Vec3 {
 
     // to be verify
     pub fn reflect(&self, n: &Vec3) -> Vec3 {
-        (*self - (2.0 * (self.dot(n))) * n).clone()
+        *self - (2.0 * (self.dot(n))) * n
     }
 
     // to be verify
@@ -129,7 +127,28 @@ impl Vec3 {
         let cos_theta = (-*self).dot(n).min(1.0);
         let r_out_perp: Vec3 = etai_over_etat * (*self + cos_theta * n);
         let r_out_parallel: Vec3 = -(1.0 - r_out_perp.length_squared()).abs().sqrt() * n;
-        return r_out_perp + r_out_parallel;
+        r_out_perp + r_out_parallel
+    }
+}
+
+impl Index<usize> for Vec3 {
+    type Output = f64;
+    fn index(&self, index: usize) -> &f64 {
+        match index {
+            0 => &self.x,
+            1 => &self.y,
+            _ => &self.z,
+        }
+    }
+}
+
+impl IndexMut<usize> for Vec3 {
+    fn index_mut(&mut self, index: usize) -> &mut f64 {
+        match index {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            _ => &mut self.z,
+        }
     }
 }
 
@@ -179,7 +198,7 @@ impl Add for Vec3 {
 impl Add for &Vec3 {
     type Output = Vec3;
     fn add(self, v: &Vec3) -> Vec3 {
-        Vec3::new(self.x + &v.x, self.y + &v.y, self.z + &v.z)
+        Vec3::new(self.x + v.x, self.y + v.y, self.z + v.z)
     }
 }
 
@@ -238,7 +257,7 @@ impl Div<f64> for Vec3 {
     type Output = Vec3;
     #[inline]
     fn div(self, rhs: f64) -> Vec3 {
-        return self * (1.0 / rhs);
+        self * (1.0 / rhs)
     }
 }
 